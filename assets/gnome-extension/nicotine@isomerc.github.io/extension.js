@@ -0,0 +1,111 @@
+// Exposes the window operations Nicotine needs on the session bus, since
+// GNOME Shell doesn't let other processes list or activate windows.
+
+import Gio from 'gi://Gio';
+import Meta from 'gi://Meta';
+import {Extension} from 'resource:///org/gnome/shell/extensions/extension.js';
+
+const OBJECT_PATH = '/org/nicotine/Windows';
+
+const INTERFACE = `
+<node>
+  <interface name="org.nicotine.Windows">
+    <method name="ListWindows">
+      <arg type="s" direction="out" name="windows"/>
+    </method>
+    <method name="GetFocused">
+      <arg type="u" direction="out" name="id"/>
+    </method>
+    <method name="ActivateWindow">
+      <arg type="u" direction="in" name="id"/>
+    </method>
+    <method name="Move">
+      <arg type="u" direction="in" name="id"/>
+      <arg type="i" direction="in" name="x"/>
+      <arg type="i" direction="in" name="y"/>
+    </method>
+    <method name="MoveResize">
+      <arg type="u" direction="in" name="id"/>
+      <arg type="i" direction="in" name="x"/>
+      <arg type="i" direction="in" name="y"/>
+      <arg type="u" direction="in" name="width"/>
+      <arg type="u" direction="in" name="height"/>
+    </method>
+    <method name="Minimize">
+      <arg type="u" direction="in" name="id"/>
+    </method>
+    <method name="Unminimize">
+      <arg type="u" direction="in" name="id"/>
+    </method>
+    <method name="Close">
+      <arg type="u" direction="in" name="id"/>
+    </method>
+  </interface>
+</node>`;
+
+function normalWindows() {
+    return global.get_window_actors()
+        .map(actor => actor.meta_window)
+        .filter(window => window.get_window_type() === Meta.WindowType.NORMAL);
+}
+
+// Stable sequence numbers fit in 32 bits and stay fixed for a window's lifetime
+function findWindow(id) {
+    return normalWindows().find(window => window.get_stable_sequence() === id);
+}
+
+class NicotineWindows {
+    ListWindows() {
+        return JSON.stringify(normalWindows().map(window => ({
+            id: window.get_stable_sequence(),
+            title: window.get_title() ?? '',
+            wm_class: window.get_wm_class() ?? '',
+            demands_attention: window.demands_attention || window.urgent,
+        })));
+    }
+
+    GetFocused() {
+        return global.display.focus_window?.get_stable_sequence() ?? 0;
+    }
+
+    ActivateWindow(id) {
+        findWindow(id)?.activate(global.get_current_time());
+    }
+
+    Move(id, x, y) {
+        findWindow(id)?.move_frame(true, x, y);
+    }
+
+    MoveResize(id, x, y, width, height) {
+        const window = findWindow(id);
+        if (!window)
+            return;
+        if (window.get_maximized())
+            window.unmaximize(Meta.MaximizeFlags.BOTH);
+        window.move_resize_frame(true, x, y, width, height);
+    }
+
+    Minimize(id) {
+        findWindow(id)?.minimize();
+    }
+
+    Unminimize(id) {
+        findWindow(id)?.unminimize();
+    }
+
+    Close(id) {
+        findWindow(id)?.delete(global.get_current_time());
+    }
+}
+
+export default class NicotineExtension extends Extension {
+    enable() {
+        this._dbus = Gio.DBusExportedObject.wrapJSObject(INTERFACE, new NicotineWindows());
+        this._dbus.export(Gio.DBus.session, OBJECT_PATH);
+    }
+
+    disable() {
+        this._dbus?.unexport();
+        this._dbus = null;
+    }
+}
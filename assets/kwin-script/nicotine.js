@@ -0,0 +1,72 @@
+// Loaded by Nicotine over org.kde.KWin.Scripting and kept running while it
+// uses the KWin backend. Nicotine replaces __SERVICE__ with its own D-Bus
+// name; the script reports the window list there whenever it changes, and
+// long-polls for windows to activate.
+const service = "__SERVICE__";
+const path = "/org/nicotine/KWin";
+const iface = "org.nicotine.KWin";
+
+// KWin 6 renamed clients to windows
+const kwin6 = "activeWindow" in workspace;
+const windowList = () => (kwin6 ? workspace.windowList() : workspace.clientList());
+
+// `closing` is left out: it can still be in the list while it's removed
+function report(closing) {
+    const windows = [];
+    for (const window of windowList()) {
+        // Only XWayland clients have an X11 id, and EVE runs under XWayland
+        if (!window.windowId || window === closing) {
+            continue;
+        }
+        windows.push({
+            id: window.windowId,
+            title: window.caption,
+            wm_class: [window.resourceName, window.resourceClass],
+            demands_attention: window.demandsAttention,
+        });
+    }
+    callDBus(service, path, iface, "Windows", JSON.stringify(windows));
+}
+
+function watch(window) {
+    window.captionChanged.connect(() => report());
+    window.demandsAttentionChanged.connect(() => report());
+}
+
+function activate(id) {
+    for (const window of windowList()) {
+        if (window.windowId === id) {
+            window.minimized = false;
+            if (kwin6) {
+                workspace.activeWindow = window;
+            } else {
+                workspace.activeClient = window;
+            }
+            break;
+        }
+    }
+}
+
+// Nicotine answers after a few seconds even when there's nothing to do, so
+// the call never runs into D-Bus's timeout. If Nicotine is gone the callback
+// never fires and the script goes quiet.
+function poll() {
+    callDBus(service, path, iface, "NextActivation", function (id) {
+        if (id) {
+            activate(id);
+        }
+        poll();
+    });
+}
+
+const added = kwin6 ? workspace.windowAdded : workspace.clientAdded;
+const removed = kwin6 ? workspace.windowRemoved : workspace.clientRemoved;
+added.connect(function (window) {
+    watch(window);
+    report();
+});
+removed.connect(report);
+
+windowList().forEach(watch);
+report();
+poll();
@@ -0,0 +1,262 @@
+use crate::config::{Config, LayoutMode};
+use crate::window_manager::{EveWindow, Monitor, WindowGeometry, WindowManager};
+use anyhow::Result;
+use log::warn;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Default)]
+struct Slots {
+    /// Latest activation requested but not yet handed to the window manager
+    pending: Option<u32>,
+    /// Activation the worker is currently applying
+    in_flight: Option<u32>,
+}
+
+#[derive(Default)]
+struct Shared {
+    slots: Mutex<Slots>,
+    wake: Condvar,
+}
+
+/// Window manager wrapper that applies activations from a single worker thread,
+/// keeping only the most recent request. When input outpaces the window manager,
+/// intermediate activations are dropped instead of queueing up and making focus
+/// run on after the last press.
+pub struct ActivationQueue {
+    inner: Arc<dyn WindowManager>,
+    shared: Arc<Shared>,
+}
+
+impl ActivationQueue {
+    pub fn new(inner: Arc<dyn WindowManager>) -> Self {
+        let shared = Arc::new(Shared::default());
+
+        let worker_wm = Arc::clone(&inner);
+        let worker_shared = Arc::clone(&shared);
+        std::thread::spawn(move || Self::run_worker(&*worker_wm, &worker_shared));
+
+        Self { inner, shared }
+    }
+
+    fn run_worker(wm: &dyn WindowManager, shared: &Shared) {
+        loop {
+            let window_id = {
+                let mut slots = shared.slots.lock().unwrap();
+                while slots.pending.is_none() {
+                    slots = shared.wake.wait(slots).unwrap();
+                }
+                let window_id = slots.pending.take();
+                slots.in_flight = window_id;
+                window_id
+            };
+
+            if let Some(window_id) = window_id {
+                if let Err(e) = wm.activate_window(window_id) {
+                    warn!("Failed to activate window 0x{:08x}: {}", window_id, e);
+                }
+            }
+
+            shared.slots.lock().unwrap().in_flight = None;
+        }
+    }
+}
+
+impl WindowManager for ActivationQueue {
+    fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
+        self.inner.get_eve_windows()
+    }
+
+    fn activate_window(&self, window_id: u32) -> Result<()> {
+        self.shared.slots.lock().unwrap().pending = Some(window_id);
+        self.shared.wake.notify_one();
+        Ok(())
+    }
+
+    fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
+        self.inner.stack_windows(windows, config)
+    }
+
+    fn layout_windows(
+        &self,
+        windows: &[EveWindow],
+        config: &Config,
+        mode: LayoutMode,
+        overrides: &[(String, WindowGeometry)],
+    ) -> Result<()> {
+        self.inner.layout_windows(windows, config, mode, overrides)
+    }
+
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        self.inner.place_window(window_id, geometry)
+    }
+
+    /// Report a queued activation as already active, so cycling keeps
+    /// advancing from where the user is heading rather than where the
+    /// window manager has caught up to
+    fn get_active_window(&self) -> Result<u32> {
+        let requested = {
+            let slots = self.shared.slots.lock().unwrap();
+            slots.pending.or(slots.in_flight)
+        };
+
+        match requested {
+            Some(window_id) => Ok(window_id),
+            None => self.inner.get_active_window(),
+        }
+    }
+
+    fn dim_inactive(&self, window_ids: &[u32], active_id: u32, opacity: f32) -> Result<()> {
+        self.inner.dim_inactive(window_ids, active_id, opacity)
+    }
+
+    fn restore_opacity(&self) -> Result<()> {
+        self.inner.restore_opacity()
+    }
+
+    fn send_key_to_window(&self, window_id: u32, keycode: u8) -> Result<()> {
+        // Keys must land after their window is focused, so bypass the queue
+        self.inner.send_key_to_window(window_id, keycode)
+    }
+
+    fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
+        self.inner.find_window_by_title(title)
+    }
+
+    fn supports_positioning(&self) -> bool {
+        self.inner.supports_positioning()
+    }
+
+    fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
+        self.inner.move_window(window_id, x, y)
+    }
+
+    fn supports_restacking(&self) -> bool {
+        self.inner.supports_restacking()
+    }
+
+    fn raise_all(&self, windows: &[EveWindow]) -> Result<()> {
+        self.inner.raise_all(windows)
+    }
+
+    fn lower_all(&self, windows: &[EveWindow]) -> Result<()> {
+        self.inner.lower_all(windows)
+    }
+
+    fn window_under_cursor(&self) -> Result<Option<u32>> {
+        self.inner.window_under_cursor()
+    }
+
+    fn get_window_geometry(&self, window_id: u32) -> Result<Option<WindowGeometry>> {
+        self.inner.get_window_geometry(window_id)
+    }
+
+    fn window_geometries(&self, window_ids: &[u32]) -> Result<HashMap<u32, WindowGeometry>> {
+        self.inner.window_geometries(window_ids)
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        self.inner.monitors()
+    }
+
+    fn is_minimized(&self, window_id: u32) -> Result<Option<bool>> {
+        self.inner.is_minimized(window_id)
+    }
+
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        self.inner.close_window(window_id)
+    }
+
+    fn minimize_window(&self, window_id: u32) -> Result<()> {
+        self.inner.minimize_window(window_id)
+    }
+
+    fn restore_window(&self, window_id: u32) -> Result<()> {
+        self.inner.restore_window(window_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Window manager that takes a while to apply each activation
+    struct SlowWindowManager {
+        activated: Mutex<Vec<u32>>,
+        delay: Duration,
+    }
+
+    impl WindowManager for SlowWindowManager {
+        fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
+            Ok(vec![])
+        }
+
+        fn activate_window(&self, window_id: u32) -> Result<()> {
+            std::thread::sleep(self.delay);
+            self.activated.lock().unwrap().push(window_id);
+            Ok(())
+        }
+
+        fn stack_windows(&self, _windows: &[EveWindow], _config: &Config) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_active_window(&self) -> Result<u32> {
+            Ok(self.activated.lock().unwrap().last().copied().unwrap_or(0))
+        }
+
+        fn find_window_by_title(&self, _title: &str) -> Result<Option<u32>> {
+            Ok(None)
+        }
+
+        fn minimize_window(&self, _window_id: u32) -> Result<()> {
+            Ok(())
+        }
+
+        fn restore_window(&self, _window_id: u32) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn wait_for_active(queue: &ActivationQueue, slow: &SlowWindowManager, window_id: u32) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while slow.activated.lock().unwrap().last() != Some(&window_id) {
+            assert!(Instant::now() < deadline, "activation never applied");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(queue.get_active_window().unwrap(), window_id);
+    }
+
+    #[test]
+    fn test_burst_of_activations_lands_on_latest() {
+        let slow = Arc::new(SlowWindowManager {
+            activated: Mutex::new(Vec::new()),
+            delay: Duration::from_millis(50),
+        });
+        let queue = ActivationQueue::new(slow.clone());
+
+        for id in 1..=5 {
+            queue.activate_window(id).unwrap();
+        }
+
+        wait_for_active(&queue, &slow, 5);
+        let activated = slow.activated.lock().unwrap().clone();
+        // At most the activation already in flight plus the latest one
+        assert!(activated.len() <= 2, "applied {:?}", activated);
+    }
+
+    #[test]
+    fn test_pending_activation_reported_as_active() {
+        let slow = Arc::new(SlowWindowManager {
+            activated: Mutex::new(Vec::new()),
+            delay: Duration::from_millis(100),
+        });
+        let queue = ActivationQueue::new(slow.clone());
+
+        queue.activate_window(7).unwrap();
+        assert_eq!(queue.get_active_window().unwrap(), 7);
+
+        wait_for_active(&queue, &slow, 7);
+    }
+}
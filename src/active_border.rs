@@ -0,0 +1,92 @@
+use anyhow::Result;
+use std::sync::Arc;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::*;
+use x11rb::rust_connection::RustConnection;
+use x11rb::COPY_DEPTH_FROM_PARENT;
+
+/// A colored frame drawn around the active client, made of four thin
+/// override-redirect windows so it sits outside the client instead of on it
+pub struct ActiveBorder {
+    conn: Arc<RustConnection>,
+    root: Window,
+    strips: [Window; 4],
+    thickness: u32,
+}
+
+impl ActiveBorder {
+    pub fn new(
+        conn: Arc<RustConnection>,
+        screen_num: usize,
+        color: [u8; 3],
+        thickness: u32,
+    ) -> Result<Self> {
+        let screen = &conn.setup().roots[screen_num];
+        let root = screen.root;
+        let pixel = (u32::from(color[0]) << 16) | (u32::from(color[1]) << 8) | u32::from(color[2]);
+
+        let mut strips = [0; 4];
+        for strip in &mut strips {
+            let id = conn.generate_id()?;
+            conn.create_window(
+                COPY_DEPTH_FROM_PARENT,
+                id,
+                root,
+                0,
+                0,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                screen.root_visual,
+                &CreateWindowAux::new()
+                    .background_pixel(pixel)
+                    .override_redirect(1),
+            )?;
+            *strip = id;
+        }
+        conn.flush()?;
+
+        Ok(Self {
+            conn,
+            root,
+            strips,
+            thickness: thickness.max(1),
+        })
+    }
+
+    /// Move the border around a window's current on-screen geometry
+    pub fn surround(&self, window_id: u32) -> Result<()> {
+        let geometry = self.conn.get_geometry(window_id)?.reply()?;
+        let origin = self
+            .conn
+            .translate_coordinates(window_id, self.root, 0, 0)?
+            .reply()?;
+
+        let t = self.thickness as i32;
+        let (x, y) = (i32::from(origin.dst_x), i32::from(origin.dst_y));
+        let (w, h) = (i32::from(geometry.width), i32::from(geometry.height));
+
+        // Top, bottom, left, right
+        let rects = [
+            (x - t, y - t, w + 2 * t, t),
+            (x - t, y + h, w + 2 * t, t),
+            (x - t, y, t, h),
+            (x + w, y, t, h),
+        ];
+
+        for (strip, (sx, sy, sw, sh)) in self.strips.iter().zip(rects) {
+            let values = ConfigureWindowAux::new()
+                .x(sx)
+                .y(sy)
+                .width(sw.max(1) as u32)
+                .height(sh.max(1) as u32)
+                .stack_mode(StackMode::ABOVE);
+            self.conn.configure_window(*strip, &values)?;
+            self.conn.map_window(*strip)?;
+        }
+
+        self.conn.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::window_manager::EveWindow;
+use std::time::{Duration, Instant};
+
+struct TrackedWindow {
+    window: EveWindow,
+    first_seen: Instant,
+    last_seen: Instant,
+    admitted: bool,
+}
+
+/// Smooths the detected window set against transient windows (splash screens,
+/// loading windows) that match the EVE filter for a moment. A window must be
+/// seen for `settle` before it becomes a client, and missing for `settle`
+/// before it's dropped.
+pub struct ClientSettler {
+    settle: Duration,
+    tracked: Vec<TrackedWindow>,
+}
+
+impl ClientSettler {
+    pub fn new(settle_ms: u64) -> Self {
+        Self {
+            settle: Duration::from_millis(settle_ms),
+            tracked: Vec::new(),
+        }
+    }
+
+    /// Treat windows that already exist as settled, e.g. when the daemon starts
+    pub fn seed(&mut self, windows: Vec<EveWindow>, now: Instant) -> Vec<EveWindow> {
+        for window in windows {
+            self.tracked.push(TrackedWindow {
+                window,
+                first_seen: now,
+                last_seen: now,
+                admitted: true,
+            });
+        }
+        self.clients()
+    }
+
+    /// Record one refresh of the window list and return the settled clients
+    pub fn observe(&mut self, windows: Vec<EveWindow>, now: Instant) -> Vec<EveWindow> {
+        if self.settle.is_zero() {
+            return windows;
+        }
+
+        for window in windows {
+            match self.tracked.iter_mut().find(|t| t.window.id == window.id) {
+                Some(tracked) => {
+                    tracked.window = window;
+                    tracked.last_seen = now;
+                }
+                None => self.tracked.push(TrackedWindow {
+                    window,
+                    first_seen: now,
+                    last_seen: now,
+                    admitted: false,
+                }),
+            }
+        }
+
+        let settle = self.settle;
+        self.tracked.retain(|t| {
+            // A window that vanished before settling was transient
+            let present = t.last_seen == now;
+            present || (t.admitted && now.duration_since(t.last_seen) < settle)
+        });
+
+        for tracked in &mut self.tracked {
+            if now.duration_since(tracked.first_seen) >= settle {
+                tracked.admitted = true;
+            }
+        }
+
+        self.clients()
+    }
+
+    fn clients(&self) -> Vec<EveWindow> {
+        self.tracked
+            .iter()
+            .filter(|t| t.admitted)
+            .map(|t| t.window.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u32) -> EveWindow {
+        EveWindow {
+            id,
+            title: format!("Character {}", id),
+            demands_attention: false,
+        }
+    }
+
+    fn ids(windows: &[EveWindow]) -> Vec<u32> {
+        windows.iter().map(|w| w.id).collect()
+    }
+
+    #[test]
+    fn test_window_admitted_after_settle_time() {
+        let start = Instant::now();
+        let mut settler = ClientSettler::new(1000);
+        settler.seed(vec![window(1)], start);
+
+        let seen = settler.observe(vec![window(1), window(2)], start);
+        assert_eq!(ids(&seen), vec![1]);
+
+        let seen = settler.observe(
+            vec![window(1), window(2)],
+            start + Duration::from_millis(500),
+        );
+        assert_eq!(ids(&seen), vec![1]);
+
+        let seen = settler.observe(
+            vec![window(1), window(2)],
+            start + Duration::from_millis(1000),
+        );
+        assert_eq!(ids(&seen), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_transient_window_never_admitted() {
+        let start = Instant::now();
+        let mut settler = ClientSettler::new(1000);
+        settler.seed(vec![window(1)], start);
+
+        settler.observe(vec![window(1), window(2)], start);
+        let seen = settler.observe(vec![window(1)], start + Duration::from_millis(500));
+        assert_eq!(ids(&seen), vec![1]);
+
+        // Reappearing later starts the settle time over
+        let seen = settler.observe(
+            vec![window(1), window(2)],
+            start + Duration::from_millis(1200),
+        );
+        assert_eq!(ids(&seen), vec![1]);
+    }
+
+    #[test]
+    fn test_client_kept_until_missing_for_settle_time() {
+        let start = Instant::now();
+        let mut settler = ClientSettler::new(1000);
+        settler.seed(vec![window(1), window(2)], start);
+
+        let seen = settler.observe(vec![window(2)], start + Duration::from_millis(500));
+        assert_eq!(ids(&seen), vec![1, 2]);
+
+        let seen = settler.observe(vec![window(2)], start + Duration::from_millis(1500));
+        assert_eq!(ids(&seen), vec![2]);
+    }
+
+    #[test]
+    fn test_zero_settle_passes_windows_through() {
+        let mut settler = ClientSettler::new(0);
+        let seen = settler.observe(vec![window(3), window(1)], Instant::now());
+        assert_eq!(ids(&seen), vec![3, 1]);
+    }
+}
@@ -1,7 +1,244 @@
+use crate::paths;
+use crate::wayland_backends::wayland_display_size;
+use crate::window_manager::{detect_display_server, DisplayServer, WindowGeometry};
 use anyhow::{Context, Result};
+use log::warn;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// On-disk config format, chosen by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => "config.toml",
+            ConfigFormat::Json => "config.json",
+        }
+    }
+
+    fn parse(self, contents: &str) -> Result<Config> {
+        match self {
+            ConfigFormat::Toml => Ok(toml::from_str(contents)?),
+            ConfigFormat::Json => Ok(serde_json::from_str(contents)?),
+        }
+    }
+
+    fn serialize(self, config: &Config) -> Result<String> {
+        match self {
+            ConfigFormat::Toml => Ok(toml::to_string_pretty(config)?),
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(config)?),
+        }
+    }
+
+    /// Set top-level number keys in a config file's contents, keeping its
+    /// comments, layout and every other key as written
+    fn set_numbers(self, contents: &str, values: &[(&str, f32)]) -> Result<String> {
+        // Through the shortest decimal form, so 512.3 isn't saved as 512.2999877929688
+        let number = |value: f32| -> f64 { value.to_string().parse().unwrap_or_default() };
+
+        let updated = match self {
+            ConfigFormat::Toml => {
+                let mut document: toml_edit::DocumentMut = contents.parse()?;
+                for &(key, value) in values {
+                    match document
+                        .get_mut(key)
+                        .and_then(toml_edit::Item::as_value_mut)
+                    {
+                        Some(existing) => {
+                            let decor = existing.decor().clone();
+                            *existing = number(value).into();
+                            *existing.decor_mut() = decor;
+                        }
+                        None => document[key] = toml_edit::value(number(value)),
+                    }
+                }
+                document.to_string()
+            }
+            ConfigFormat::Json => {
+                let mut document: serde_json::Value = serde_json::from_str(contents)?;
+                let object = document
+                    .as_object_mut()
+                    .context("Config isn't a JSON object")?;
+                for &(key, value) in values {
+                    object.insert(key.to_string(), number(value).into());
+                }
+                serde_json::to_string_pretty(&document)?
+            }
+        };
+
+        // Never write back something the next load would reject
+        self.parse(&updated)?;
+        Ok(updated)
+    }
+}
+
+/// How `forward` moves through the client list
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CycleMode {
+    /// Step forward, wrapping from the last client to the first
+    #[default]
+    Normal,
+    /// Bounce back and forth between the ends of the list
+    PingPong,
+}
+
+/// Direction of a horizontal wheel tilt (REL_HWHEEL)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Tilt {
+    Left,
+    Right,
+}
+
+/// How the X11 backend asks for a client to be focused
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum X11ActivationMethod {
+    /// Ask the window manager with a _NET_ACTIVE_WINDOW client message
+    #[default]
+    NetActiveWindow,
+    /// Raise the window and give it input focus directly, for WMs without EWMH
+    SetInputFocus,
+    /// Run `wmctrl -i -a`
+    Wmctrl,
+}
+
+/// How `stack` arranges clients on screen
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutMode {
+    /// Every client at the same centered position
+    #[default]
+    Stack,
+    /// Tile clients in a near-square grid
+    Grid,
+    /// Side-by-side full-height columns
+    Columns,
+    /// Overlapping, each offset by `cascade_step`
+    Cascade,
+}
+
+impl LayoutMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "stack" => Some(LayoutMode::Stack),
+            "grid" => Some(LayoutMode::Grid),
+            "columns" => Some(LayoutMode::Columns),
+            "cascade" => Some(LayoutMode::Cascade),
+            _ => None,
+        }
+    }
+}
+
+/// Which windows count as clients and how their display title is derived
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WindowMatch {
+    #[serde(default = "default_title_prefix")]
+    pub title_prefix: Option<String>, // Windows whose title starts with this are clients
+    #[serde(default = "default_title_regex")]
+    pub title_regex: Option<String>, // Also match titles against this; capture group 1 becomes the display title
+    #[serde(default = "default_strip_prefix")]
+    pub strip_prefix: bool, // Drop title_prefix from the displayed title
+    #[serde(default = "default_exclude_title_substrings")]
+    pub exclude_title_substrings: Vec<String>, // Windows whose title contains any of these are never clients
+}
+
+impl Default for WindowMatch {
+    fn default() -> Self {
+        Self {
+            title_prefix: default_title_prefix(),
+            title_regex: default_title_regex(),
+            strip_prefix: default_strip_prefix(),
+            exclude_title_substrings: default_exclude_title_substrings(),
+        }
+    }
+}
+
+/// Overlay colors, for light desktops or color-blind players
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlayTheme {
+    #[serde(default = "default_background_rgba")]
+    pub background_rgba: [u8; 4], // Panel background; alpha below 255 lets the desktop show through
+    #[serde(default = "default_accent_rgb")]
+    pub accent_rgb: [u8; 3], // Header bar, active client and buttons
+    #[serde(default = "default_text_rgb")]
+    pub text_rgb: [u8; 3], // Inactive client names
+    #[serde(default = "default_border_rgb")]
+    pub border_rgb: [u8; 3], // Panel and minimap outlines
+    #[serde(default = "default_attention_rgb")]
+    pub attention_rgb: [u8; 3], // Clients demanding attention
+}
+
+impl Default for OverlayTheme {
+    fn default() -> Self {
+        Self {
+            background_rgba: default_background_rgba(),
+            accent_rgb: default_accent_rgb(),
+            text_rgb: default_text_rgb(),
+            border_rgb: default_border_rgb(),
+            attention_rgb: default_attention_rgb(),
+        }
+    }
+}
+
+fn default_background_rgba() -> [u8; 4] {
+    [252, 250, 242, 255]
+}
+
+fn default_accent_rgb() -> [u8; 3] {
+    [196, 30, 58]
+}
+
+fn default_text_rgb() -> [u8; 3] {
+    [30, 30, 30]
+}
+
+fn default_border_rgb() -> [u8; 3] {
+    [180, 155, 105]
+}
+
+fn default_attention_rgb() -> [u8; 3] {
+    [214, 120, 0]
+}
+
+fn default_title_prefix() -> Option<String> {
+    Some("EVE - ".to_string())
+}
+
+fn default_title_regex() -> Option<String> {
+    None
+}
+
+fn default_strip_prefix() -> bool {
+    true
+}
+
+fn default_exclude_title_substrings() -> Vec<String> {
+    vec!["Launcher".to_string()]
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
@@ -10,14 +247,36 @@ pub struct Config {
     pub panel_height: u32,
     pub eve_width: u32,
     pub eve_height: u32,
+    #[serde(default = "default_eve_x")]
+    pub eve_x: Option<i32>, // Where stacked clients go; centered on the display when unset
+    #[serde(default = "default_eve_y")]
+    pub eve_y: Option<i32>,
     pub overlay_x: f32,
     pub overlay_y: f32,
+    #[serde(default = "default_overlay_width")]
+    pub overlay_width: f32,
+    #[serde(default = "default_overlay_height")]
+    pub overlay_height: f32,
+    #[serde(default = "default_overlay_auto_height")]
+    pub overlay_auto_height: bool, // Grow/shrink the overlay to fit the client list
+    #[serde(default = "default_overlay_font_path")]
+    pub overlay_font_path: Option<String>, // TTF/OTF used instead of the embedded font, e.g. for CJK names
+    #[serde(default = "default_overlay_font_size")]
+    pub overlay_font_size: f32, // Client list text size
+    #[serde(default = "default_overlay_click_through")]
+    pub overlay_click_through: bool, // Compact overlay showing only the current client, ignoring the mouse
     #[serde(default = "default_enable_mouse")]
     pub enable_mouse_buttons: bool,
     #[serde(default = "default_forward_button")]
     pub forward_button: u16, // BTN_SIDE (mouse button 9)
     #[serde(default = "default_backward_button")]
     pub backward_button: u16, // BTN_EXTRA (mouse button 8)
+    #[serde(default = "default_forward_tilt")]
+    pub forward_tilt: Option<Tilt>, // Wheel tilt that cycles forward, "left" or "right" (unset = off)
+    #[serde(default = "default_backward_tilt")]
+    pub backward_tilt: Option<Tilt>,
+    #[serde(default = "default_invert_mouse_cycling")]
+    pub invert_mouse_cycling: bool, // Swap what the forward and backward buttons/tilts do
     #[serde(default = "default_enable_keyboard")]
     pub enable_keyboard_buttons: bool,
     #[serde(default = "default_forward_key")]
@@ -34,6 +293,118 @@ pub struct Config {
     pub keyboard_device_path: Option<String>,
     #[serde(default = "default_modifier_key")]
     pub modifier_key: Option<u16>,
+    #[serde(default = "default_chord_modifiers")]
+    pub chord_modifiers: Vec<u16>, // Keys that must all be held for `chords`, e.g. [29, 56] for Ctrl+Alt (empty = off)
+    #[serde(default = "default_group_character_select")]
+    pub group_character_select: bool, // Collapse clients at character select into one slot
+    #[serde(default = "default_unfullscreen_before_stack")]
+    pub unfullscreen_before_stack: bool, // Otherwise fullscreen clients are skipped when stacking
+    #[serde(default)]
+    pub layout_mode: LayoutMode,
+    #[serde(default = "default_cascade_step")]
+    pub cascade_step: u32, // Pixel offset between clients in the cascade layout
+    #[serde(default = "default_restack_tolerance_px")]
+    pub restack_tolerance_px: u32, // Clients within this many pixels of their spot aren't moved again
+    #[serde(default = "default_stack_monitor")]
+    pub stack_monitor: Option<String>, // Monitor to arrange clients on (see `nicotine monitors`)
+    #[serde(default = "default_exclude_wm_classes")]
+    pub exclude_wm_classes: Vec<String>, // Helper windows (Steam overlay etc.) never treated as clients
+    #[serde(default = "default_wm_class_candidates")]
+    pub wm_class_candidates: Vec<String>, // Window classes that identify an EVE client (Steam, Lutris, WINE, Flatpak)
+    #[serde(default = "default_cycle_debounce_ms")]
+    pub cycle_debounce_ms: u64, // Ignore cycle buttons/keys pressed again within this many ms
+    #[serde(default = "default_activate_retries")]
+    pub activate_retries: u32, // Check this many times that focus landed after activating (X11, 0 = don't check)
+    #[serde(default = "default_activate_retry_ms")]
+    pub activate_retry_ms: u64, // Delay between those checks
+    #[serde(default)]
+    pub x11_activation_method: X11ActivationMethod,
+    #[serde(default = "default_refresh_interval_ms")]
+    pub refresh_interval_ms: u64, // How often the client list is re-read; higher saves power but reacts slower
+    #[serde(default = "default_overlay_fps")]
+    pub overlay_fps: u32, // Overlay redraw rate
+    #[serde(default = "default_client_settle_ms")]
+    pub client_settle_ms: u64, // How long a window must exist (or be gone) before the client list changes
+    #[serde(default)]
+    pub cycle_mode: CycleMode,
+    #[serde(default = "default_pingpong_every_call")]
+    pub pingpong_every_call: bool, // In pingpong mode, reverse direction after every cycle
+    #[serde(default = "default_cycle_wrap")]
+    pub cycle_wrap: bool, // Wrap from the last client to the first; false stops at either end
+    #[serde(default = "default_daemonless")]
+    pub daemonless: bool, // Cycle commands never use the daemon; state is kept in a file instead
+    #[serde(default = "default_require_confirmation_token")]
+    pub require_confirmation_token: Option<String>, // Destructive socket commands must carry this token
+    #[serde(default = "default_enable_broadcast")]
+    pub enable_broadcast: bool, // Accept `broadcast` commands that send a key to every client (X11 only)
+    #[serde(default = "default_log_file")]
+    pub log_file: Option<String>, // Where the detached daemon writes its output (default in the runtime dir)
+    #[serde(default = "default_disable_update_check")]
+    pub disable_update_check: bool, // Never ask GitHub for a newer release on `start`
+    #[serde(default = "default_autoload_profile")]
+    pub autoload_profile: Option<String>, // Profile applied on start instead of the last used one
+    #[serde(default = "default_show_minimap")]
+    pub show_minimap: bool, // Draw a map of client positions in the overlay (X11 only)
+    #[serde(default = "default_confirm_close")]
+    pub confirm_close: bool, // Ask before the overlay's close button closes a client
+    #[serde(default = "default_active_border")]
+    pub active_border: bool, // Draw a border around the active client (X11 only)
+    #[serde(default = "default_active_border_color")]
+    pub active_border_color: [u8; 3],
+    #[serde(default = "default_active_border_thickness")]
+    pub active_border_thickness: u32,
+    #[serde(default = "default_dim_inactive_opacity")]
+    pub dim_inactive_opacity: Option<f32>, // Opacity (0.0-1.0) of clients other than the active one (X11 only)
+    #[serde(default = "default_anchor_character")]
+    pub anchor_character: Option<String>, // Your main character, e.g. Some("Main Pilot")
+    #[serde(default = "default_skip_anchor_in_cycle")]
+    pub skip_anchor_in_cycle: bool, // Leave the anchor out of forward/backward cycling
+    #[serde(default = "default_exclude_titles")]
+    pub exclude_titles: Vec<String>, // Clients (titles without "EVE - ") left out of forward/backward cycling
+    #[serde(default = "default_launch_commands")]
+    pub launch_commands: Vec<String>, // Shell commands `nicotine launch` runs, one per client
+    #[serde(default = "default_launch_timeout_secs")]
+    pub launch_timeout_secs: u64, // How long `nicotine launch` waits for the clients before giving up
+    #[serde(default)]
+    pub window_match: WindowMatch,
+    #[serde(default)]
+    pub overlay: OverlayTheme,
+    #[serde(default = "default_aliases")]
+    pub aliases: BTreeMap<String, String>, // Custom command names, e.g. next = "forward"
+    #[serde(default = "default_chords")]
+    pub chords: BTreeMap<String, String>, // Keycode -> "forward", "backward" or "switch N" while chord_modifiers are held
+}
+
+fn default_eve_x() -> Option<i32> {
+    None
+}
+
+fn default_eve_y() -> Option<i32> {
+    None
+}
+
+fn default_overlay_width() -> f32 {
+    220.0
+}
+
+fn default_overlay_height() -> f32 {
+    320.0
+}
+
+fn default_overlay_auto_height() -> bool {
+    false
+}
+
+fn default_overlay_click_through() -> bool {
+    false
+}
+
+fn default_overlay_font_path() -> Option<String> {
+    None
+}
+
+fn default_overlay_font_size() -> f32 {
+    13.0
 }
 
 fn default_enable_mouse() -> bool {
@@ -48,6 +419,18 @@ fn default_backward_button() -> u16 {
     275 // BTN_EXTRA (backward button, mouse button 8)
 }
 
+fn default_forward_tilt() -> Option<Tilt> {
+    None
+}
+
+fn default_backward_tilt() -> Option<Tilt> {
+    None
+}
+
+fn default_invert_mouse_cycling() -> bool {
+    false
+}
+
 fn default_enable_keyboard() -> bool {
     false // Disabled by default to avoid conflicts
 }
@@ -76,44 +459,343 @@ fn default_keyboard_device_path() -> Option<String> {
     None
 }
 
+fn default_chord_modifiers() -> Vec<u16> {
+    Vec::new()
+}
+
+/// KEY_1..KEY_9 (codes 2..10) switch to clients 1..9
+fn default_chords() -> BTreeMap<String, String> {
+    (1..=9)
+        .map(|n| ((n + 1).to_string(), format!("switch {}", n)))
+        .collect()
+}
+
 fn default_modifier_key() -> Option<u16> {
     None // No modifier for backward shifting by default
 }
 
+fn default_group_character_select() -> bool {
+    false
+}
+
+fn default_unfullscreen_before_stack() -> bool {
+    false
+}
+
+fn default_restack_tolerance_px() -> u32 {
+    2
+}
+
+fn default_log_file() -> Option<String> {
+    None
+}
+
+fn default_disable_update_check() -> bool {
+    false
+}
+
+fn default_stack_monitor() -> Option<String> {
+    None
+}
+
+fn default_cascade_step() -> u32 {
+    40
+}
+
+fn default_exclude_wm_classes() -> Vec<String> {
+    vec!["Steam".to_string(), "steamwebhelper".to_string()]
+}
+
+fn default_wm_class_candidates() -> Vec<String> {
+    vec![
+        "steam_app_8500".to_string(),
+        "exefile.exe".to_string(),
+        "eve online".to_string(),
+        "eve-online".to_string(),
+    ]
+}
+
+fn default_cycle_debounce_ms() -> u64 {
+    0
+}
+
+fn default_activate_retries() -> u32 {
+    0
+}
+
+fn default_activate_retry_ms() -> u64 {
+    20
+}
+
+fn default_refresh_interval_ms() -> u64 {
+    500
+}
+
+fn default_overlay_fps() -> u32 {
+    30
+}
+
+fn default_client_settle_ms() -> u64 {
+    0
+}
+
+fn default_pingpong_every_call() -> bool {
+    false
+}
+
+fn default_cycle_wrap() -> bool {
+    true
+}
+
+fn default_daemonless() -> bool {
+    false
+}
+
+fn default_require_confirmation_token() -> Option<String> {
+    None
+}
+
+fn default_enable_broadcast() -> bool {
+    false
+}
+
+fn default_autoload_profile() -> Option<String> {
+    None
+}
+
+fn default_show_minimap() -> bool {
+    false
+}
+
+fn default_confirm_close() -> bool {
+    true
+}
+
+fn default_active_border() -> bool {
+    false
+}
+
+fn default_active_border_color() -> [u8; 3] {
+    [196, 30, 58] // Overlay red
+}
+
+fn default_active_border_thickness() -> u32 {
+    3
+}
+
+fn default_dim_inactive_opacity() -> Option<f32> {
+    None
+}
+
+fn default_anchor_character() -> Option<String> {
+    None
+}
+
+fn default_skip_anchor_in_cycle() -> bool {
+    false
+}
+
+fn default_exclude_titles() -> Vec<String> {
+    Vec::new()
+}
+
+fn default_launch_commands() -> Vec<String> {
+    Vec::new()
+}
+
+fn default_launch_timeout_secs() -> u64 {
+    300
+}
+
+fn default_aliases() -> BTreeMap<String, String> {
+    BTreeMap::new()
+}
+
 impl Config {
-    fn config_dir() -> PathBuf {
+    pub(crate) fn config_dir() -> PathBuf {
         let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("nicotine");
         path
     }
 
+    fn config_path_for(format: ConfigFormat) -> PathBuf {
+        let mut path = Self::config_dir();
+        path.push(format.file_name());
+        path
+    }
+
+    /// config.json if present, otherwise config.toml
     fn config_path() -> PathBuf {
+        let json_path = Self::config_path_for(ConfigFormat::Json);
+        if json_path.exists() {
+            return json_path;
+        }
+        Self::config_path_for(ConfigFormat::Toml)
+    }
+
+    fn profiles_dir() -> PathBuf {
+        let mut path = Self::config_dir();
+        path.push("profiles");
+        path
+    }
+
+    fn profile_path(name: &str) -> PathBuf {
+        let mut path = Self::profiles_dir();
+        path.push(format!("{}.toml", name));
+        path
+    }
+
+    fn last_profile_path() -> PathBuf {
         let mut path = Self::config_dir();
-        path.push("config.toml");
+        path.push("last_profile");
         path
     }
 
+    /// Profile names become file names under profiles/, so they can't
+    /// point anywhere else
+    fn check_profile_name(name: &str) -> Result<()> {
+        if name.is_empty() || name.contains('/') || name.contains("..") {
+            anyhow::bail!("Invalid profile name '{}'", name);
+        }
+        Ok(())
+    }
+
+    /// Load a named profile from profiles/<name>.toml
+    pub fn load_profile(name: &str) -> Result<Self> {
+        Self::check_profile_name(name)?;
+        let path = Self::profile_path(name);
+        if !path.exists() {
+            anyhow::bail!("Profile '{}' not found at {}", name, path.display());
+        }
+        Self::load_from(&path).with_context(|| format!("Failed to load profile '{}'", name))
+    }
+
+    /// Load a config file, parsing it as TOML or JSON based on its extension
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config = ConfigFormat::from_path(path)
+            .parse(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config in {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Checks serde can't express
+    fn validate(&self) -> Result<()> {
+        // Destructive commands are sent as `verb:TOKEN` and split at the last ':'
+        if let Some(token) = &self.require_confirmation_token {
+            if token.contains(':') {
+                anyhow::bail!("require_confirmation_token can't contain ':'");
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the profiles in profiles/, sorted
+    pub fn list_profiles() -> Result<Vec<String>> {
+        Self::profile_names(&Self::profiles_dir())
+    }
+
+    fn profile_names(dir: &Path) -> Result<Vec<String>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Make a profile the main config by copying it over config.toml (or
+    /// converting it, when config.json is in use). Returns the file written.
+    pub fn use_profile(name: &str) -> Result<PathBuf> {
+        let profile = Self::load_profile(name)?;
+        let config_path = Self::config_path();
+
+        match ConfigFormat::from_path(&config_path) {
+            // A straight copy keeps the profile's comments
+            ConfigFormat::Toml => {
+                fs::create_dir_all(Self::config_dir())?;
+                fs::copy(Self::profile_path(name), &config_path)?;
+            }
+            ConfigFormat::Json => {
+                fs::write(&config_path, ConfigFormat::Json.serialize(&profile)?)?;
+            }
+        }
+        Self::record_last_profile(name)?;
+        Ok(config_path)
+    }
+
+    /// Name of the most recently loaded profile, if any was ever loaded
+    pub fn last_profile() -> Option<String> {
+        fs::read_to_string(Self::last_profile_path())
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+
+    pub fn record_last_profile(name: &str) -> Result<()> {
+        fs::create_dir_all(Self::config_dir())?;
+        fs::write(Self::last_profile_path(), name)?;
+        Ok(())
+    }
+
+    /// Profile to apply on start: the configured autoload profile, else the last used one
+    pub fn startup_profile(&self) -> Option<String> {
+        self.autoload_profile.clone().or_else(Self::last_profile)
+    }
+
+    /// The daemon log: `log_file` if set, otherwise the runtime directory's
+    pub fn log_path(&self) -> Result<PathBuf> {
+        match &self.log_file {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => paths::log_file(),
+        }
+    }
+
+    pub fn characters_path() -> PathBuf {
+        Self::config_dir().join("characters.txt")
+    }
+
     /// Load character order from characters.txt
     /// Each line is a character name (without "EVE - " prefix)
     /// Returns None if file doesn't exist
     pub fn load_characters() -> Option<Vec<String>> {
-        let mut path = Self::config_dir();
-        path.push("characters.txt");
+        Self::load_character_entries().map(|entries| character_order(&entries))
+    }
 
-        if !path.exists() {
-            return None;
-        }
+    /// Characters given their own `= WIDTHxHEIGHT+X+Y` in characters.txt
+    pub fn load_character_geometry() -> Vec<(String, WindowGeometry)> {
+        character_geometry(&Self::load_character_entries().unwrap_or_default())
+    }
 
-        fs::read_to_string(&path).ok().map(|contents| {
-            contents
-                .lines()
-                .map(|line| line.trim().to_string())
-                .filter(|line| !line.is_empty() && !line.starts_with('#'))
-                .collect()
-        })
+    /// Every line of characters.txt with its geometry, if any, for callers
+    /// that need both the order and the geometry from a single read
+    pub fn load_character_entries() -> Option<Vec<(String, Option<WindowGeometry>)>> {
+        let contents = fs::read_to_string(Self::characters_path()).ok()?;
+        Some(parse_characters(&contents))
     }
 
     fn detect_display_size() -> (u32, u32) {
+        // xrandr only sees XWayland's outputs there, if it exists at all
+        if detect_display_server() == DisplayServer::Wayland {
+            if let Some(size) = wayland_display_size() {
+                return size;
+            }
+        }
+
         // Try to detect display size using xrandr
         if let Ok(output) = std::process::Command::new("xrandr")
             .args(["--current"])
@@ -139,30 +821,30 @@ impl Config {
         (1920, 1080)
     }
 
-    pub fn load() -> Result<Self> {
-        let config_path = Self::config_path();
-
-        // Try to load existing config
-        if let Ok(contents) = fs::read_to_string(&config_path) {
-            return toml::from_str(&contents).context("Failed to parse config.toml");
-        }
-
-        // Auto-generate config based on detected display
-        println!("Generating config based on your display...");
-        let (display_width, display_height) = Self::detect_display_size();
-        println!("Detected display: {}x{}", display_width, display_height);
-
-        let config = Self {
+    /// Default config for a display of the given size
+    pub(crate) fn for_display(display_width: u32, display_height: u32) -> Self {
+        Self {
             display_width,
             display_height,
             panel_height: 0, // Assume no panel by default
             eve_width: (display_width as f32 * 0.54) as u32, // ~54% of width
             eve_height: display_height,
+            eve_x: default_eve_x(),
+            eve_y: default_eve_y(),
             overlay_x: 10.0,
             overlay_y: 10.0,
+            overlay_width: default_overlay_width(),
+            overlay_height: default_overlay_height(),
+            overlay_auto_height: default_overlay_auto_height(),
+            overlay_font_path: default_overlay_font_path(),
+            overlay_font_size: default_overlay_font_size(),
+            overlay_click_through: default_overlay_click_through(),
             enable_mouse_buttons: true,
             forward_button: 276,  // BTN_SIDE (button 9)
             backward_button: 275, // BTN_EXTRA (button 8)
+            forward_tilt: default_forward_tilt(),
+            backward_tilt: default_backward_tilt(),
+            invert_mouse_cycling: default_invert_mouse_cycling(),
             enable_keyboard_buttons: false,
             forward_key: 15,  // KEY_TAB
             backward_key: 15, // KEY_TAB (with Shift)
@@ -171,7 +853,68 @@ impl Config {
             minimize_inactive: false,
             keyboard_device_path: None,
             modifier_key: None,
-        };
+            chord_modifiers: default_chord_modifiers(),
+            group_character_select: default_group_character_select(),
+            unfullscreen_before_stack: default_unfullscreen_before_stack(),
+            layout_mode: LayoutMode::default(),
+            cascade_step: default_cascade_step(),
+            restack_tolerance_px: default_restack_tolerance_px(),
+            stack_monitor: default_stack_monitor(),
+            exclude_wm_classes: default_exclude_wm_classes(),
+            wm_class_candidates: default_wm_class_candidates(),
+            cycle_debounce_ms: default_cycle_debounce_ms(),
+            activate_retries: default_activate_retries(),
+            activate_retry_ms: default_activate_retry_ms(),
+            x11_activation_method: X11ActivationMethod::default(),
+            refresh_interval_ms: default_refresh_interval_ms(),
+            overlay_fps: default_overlay_fps(),
+            client_settle_ms: default_client_settle_ms(),
+            cycle_mode: CycleMode::default(),
+            pingpong_every_call: default_pingpong_every_call(),
+            cycle_wrap: default_cycle_wrap(),
+            daemonless: default_daemonless(),
+            require_confirmation_token: default_require_confirmation_token(),
+            log_file: default_log_file(),
+            disable_update_check: default_disable_update_check(),
+            enable_broadcast: default_enable_broadcast(),
+            autoload_profile: default_autoload_profile(),
+            show_minimap: default_show_minimap(),
+            confirm_close: default_confirm_close(),
+            active_border: default_active_border(),
+            active_border_color: default_active_border_color(),
+            active_border_thickness: default_active_border_thickness(),
+            dim_inactive_opacity: default_dim_inactive_opacity(),
+            anchor_character: default_anchor_character(),
+            skip_anchor_in_cycle: default_skip_anchor_in_cycle(),
+            exclude_titles: default_exclude_titles(),
+            launch_commands: default_launch_commands(),
+            launch_timeout_secs: default_launch_timeout_secs(),
+            window_match: WindowMatch::default(),
+            overlay: OverlayTheme::default(),
+            aliases: default_aliases(),
+            chords: default_chords(),
+        }
+    }
+
+    /// Load the named profile, or config.toml (generating it on first run)
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        if let Some(name) = profile {
+            return Self::load_profile(name);
+        }
+
+        let config_path = Self::config_path();
+
+        // Try to load existing config
+        if config_path.exists() {
+            return Self::load_from(&config_path);
+        }
+
+        // Auto-generate config based on detected display
+        println!("Generating config based on your display...");
+        let (display_width, display_height) = Self::detect_display_size();
+        println!("Detected display: {}x{}", display_width, display_height);
+
+        let config = Self::for_display(display_width, display_height);
 
         // Save the generated config
         if let Some(parent) = config_path.parent() {
@@ -185,70 +928,212 @@ impl Config {
         Ok(config)
     }
 
-    pub fn save_default() -> Result<()> {
-        let config_path = Self::config_path();
+    pub fn save_default(format: ConfigFormat) -> Result<()> {
+        let config_path = Self::config_path_for(format);
         let (display_width, display_height) = Self::detect_display_size();
 
-        let config = Self {
-            display_width,
-            display_height,
-            panel_height: 0,
-            eve_width: (display_width as f32 * 0.54) as u32,
-            eve_height: display_height,
-            overlay_x: 10.0,
-            overlay_y: 10.0,
-            enable_mouse_buttons: true,
-            forward_button: 276,
-            backward_button: 275,
-            enable_keyboard_buttons: false,
-            forward_key: 15,
-            backward_key: 15,
-            show_overlay: true,
-            mouse_device_path: None,
-            minimize_inactive: false,
-            keyboard_device_path: None,
-            modifier_key: None,
-        };
+        let config = Self::for_display(display_width, display_height);
 
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent)?;
         }
-        let contents = toml::to_string_pretty(&config)?;
+        let contents = format.serialize(&config)?;
         fs::write(&config_path, contents)?;
         println!("Created config: {}", config_path.display());
         Ok(())
     }
 
+    /// Expand a user-defined command alias into the command and any arguments it adds
+    /// Aliases may point at other aliases; built-in commands always win over an alias
+    /// with the same name. Commands that aren't aliases are returned unchanged.
+    pub fn resolve_alias(
+        &self,
+        command: &str,
+        is_builtin: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>> {
+        let mut expanded = vec![command.to_string()];
+        let mut seen = vec![command.to_string()];
+
+        loop {
+            let current = expanded[0].clone();
+            let target = match self.aliases.get(&current) {
+                Some(target) => target,
+                None => return Ok(expanded),
+            };
+
+            if is_builtin(&current) {
+                warn!(
+                    "alias '{}' shadows a built-in command and is ignored",
+                    current
+                );
+                return Ok(expanded);
+            }
+
+            let mut words: Vec<String> = target.split_whitespace().map(String::from).collect();
+            if words.is_empty() {
+                anyhow::bail!("Alias '{}' is empty", current);
+            }
+
+            if seen.contains(&words[0]) {
+                anyhow::bail!(
+                    "Alias cycle detected: {} -> {}",
+                    seen.join(" -> "),
+                    words[0]
+                );
+            }
+            seen.push(words[0].clone());
+
+            // Arguments given to the alias are appended after the ones it expands to
+            words.extend(expanded.drain(1..));
+            expanded = words;
+        }
+    }
+
+    /// The file holding the running config: the active profile's, or config.toml
+    fn saved_path(profile: Option<&str>) -> Result<PathBuf> {
+        match profile {
+            Some(name) => {
+                Self::check_profile_name(name)?;
+                Ok(Self::profile_path(name))
+            }
+            None => Ok(Self::config_path()),
+        }
+    }
+
+    /// Read-modify-write `values` in the saved config file, leaving everything else untouched
+    fn update_saved(profile: Option<&str>, values: &[(&str, f32)]) -> Result<()> {
+        let config_path = Self::saved_path(profile)?;
+        let contents = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read {}", config_path.display()))?;
+
+        let updated = ConfigFormat::from_path(&config_path)
+            .set_numbers(&contents, values)
+            .with_context(|| format!("Failed to update {}", config_path.display()))?;
+        fs::write(&config_path, updated)?;
+        Ok(())
+    }
+
+    /// Save the overlay size after the user resizes it
+    pub fn update_overlay_size(profile: Option<&str>, width: f32, height: f32) -> Result<()> {
+        Self::update_saved(
+            profile,
+            &[("overlay_width", width), ("overlay_height", height)],
+        )
+    }
+
+    /// Save the overlay position after the user drags it
+    pub fn update_overlay_position(profile: Option<&str>, x: f32, y: f32) -> Result<()> {
+        Self::update_saved(profile, &[("overlay_x", x), ("overlay_y", y)])
+    }
+
+    /// Left edge of stacked clients: `eve_x`, or centered on the display
+    pub fn eve_x(&self) -> i32 {
+        self.eve_x
+            .unwrap_or((self.display_width.saturating_sub(self.eve_width) / 2) as i32)
+    }
+
+    /// Top edge of stacked clients: `eve_y`, or the top of the display
+    pub fn eve_y(&self) -> i32 {
+        self.eve_y.unwrap_or(0)
+    }
+
     pub fn eve_height_adjusted(&self) -> u32 {
-        self.display_height - self.panel_height
+        self.display_height.saturating_sub(self.panel_height)
     }
 }
 
+/// characters.txt lines, each a name optionally followed by `= WIDTHxHEIGHT+X+Y`
+pub fn character_order(entries: &[(String, Option<WindowGeometry>)]) -> Vec<String> {
+    entries.iter().map(|(name, _)| name.clone()).collect()
+}
+
+pub fn character_geometry(
+    entries: &[(String, Option<WindowGeometry>)],
+) -> Vec<(String, WindowGeometry)> {
+    entries
+        .iter()
+        .filter_map(|(name, geometry)| Some((name.clone(), (*geometry)?)))
+        .collect()
+}
+
+fn parse_characters(contents: &str) -> Vec<(String, Option<WindowGeometry>)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            if let Some((name, geometry)) = line.rsplit_once('=') {
+                match parse_geometry(geometry.trim()) {
+                    Some(geometry) if !name.trim().is_empty() => {
+                        return (name.trim().to_string(), Some(geometry));
+                    }
+                    _ => warn!(
+                        "characters.txt: '{}' isn't WIDTHxHEIGHT+X+Y, reading the line as a name",
+                        geometry.trim()
+                    ),
+                }
+            }
+            (line.to_string(), None)
+        })
+        .collect()
+}
+
+/// X-style geometry such as `800x600+0+40`. Offsets are root coordinates, so
+/// `-10` is left of the screen rather than measured from its right edge.
+fn parse_geometry(s: &str) -> Option<WindowGeometry> {
+    let (width, rest) = s.split_once('x')?;
+    let (height, offsets) = rest.split_at(rest.find(['+', '-'])?);
+    let (x, y) = offsets.split_at(offsets[1..].find(['+', '-'])? + 1);
+    Some(WindowGeometry {
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_characters_with_and_without_geometry() {
+        let entries =
+            parse_characters("# fleet\nMain Character\nScout Alt = 800x600+0+40\n\nOdd = Name\n");
+        assert_eq!(
+            entries,
+            vec![
+                ("Main Character".to_string(), None),
+                (
+                    "Scout Alt".to_string(),
+                    Some(WindowGeometry {
+                        x: 0,
+                        y: 40,
+                        width: 800,
+                        height: 600
+                    })
+                ),
+                ("Odd = Name".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            parse_geometry("1280x720-10+5"),
+            Some(WindowGeometry {
+                x: -10,
+                y: 5,
+                width: 1280,
+                height: 720
+            })
+        );
+        assert_eq!(parse_geometry("1280x720"), None);
+    }
+
     #[test]
     fn test_eve_height_adjusted_with_panel() {
         let config = Config {
-            display_width: 1920,
-            display_height: 1080,
             panel_height: 40,
             eve_width: 1000,
-            eve_height: 1080,
-            overlay_x: 10.0,
-            overlay_y: 10.0,
-            enable_mouse_buttons: true,
-            forward_button: 276,
-            backward_button: 275,
-            enable_keyboard_buttons: false,
-            forward_key: 15,
-            backward_key: 15,
-            show_overlay: true,
-            mouse_device_path: None,
-            minimize_inactive: false,
-            keyboard_device_path: None,
-            modifier_key: None,
+            ..Config::for_display(1920, 1080)
         };
 
         // Height should be: 1080 - 40 = 1040
@@ -258,50 +1143,29 @@ mod tests {
     #[test]
     fn test_eve_height_adjusted_without_panel() {
         let config = Config {
-            display_width: 1920,
-            display_height: 1080,
             panel_height: 0,
             eve_width: 1000,
-            eve_height: 1080,
-            overlay_x: 10.0,
-            overlay_y: 10.0,
-            enable_mouse_buttons: true,
-            forward_button: 276,
-            backward_button: 275,
-            enable_keyboard_buttons: false,
-            forward_key: 15,
-            backward_key: 15,
-            show_overlay: true,
-            mouse_device_path: None,
-            minimize_inactive: false,
-            keyboard_device_path: None,
-            modifier_key: None,
+            ..Config::for_display(1920, 1080)
         };
 
         assert_eq!(config.eve_height_adjusted(), 1080);
     }
 
+    #[test]
+    fn test_eve_height_adjusted_panel_taller_than_display() {
+        let config = Config {
+            panel_height: 1200,
+            ..Config::for_display(1280, 1024)
+        };
+
+        assert_eq!(config.eve_height_adjusted(), 0);
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = Config {
-            display_width: 7680,
-            display_height: 2160,
-            panel_height: 0,
             eve_width: 4147,
-            eve_height: 2160,
-            overlay_x: 10.0,
-            overlay_y: 10.0,
-            enable_mouse_buttons: true,
-            forward_button: 276,
-            backward_button: 275,
-            enable_keyboard_buttons: false,
-            forward_key: 15,
-            backward_key: 15,
-            show_overlay: true,
-            mouse_device_path: None,
-            minimize_inactive: false,
-            keyboard_device_path: None,
-            modifier_key: None,
+            ..Config::for_display(7680, 2160)
         };
 
         let toml_str = toml::to_string(&config).unwrap();
@@ -311,4 +1175,190 @@ mod tests {
         assert_eq!(deserialized.display_height, 2160);
         assert_eq!(deserialized.eve_width, 4147);
     }
+
+    fn config_with_aliases(aliases: &[(&str, &str)]) -> Config {
+        Config {
+            aliases: aliases
+                .iter()
+                .map(|(name, target)| (name.to_string(), target.to_string()))
+                .collect(),
+            ..Config::for_display(1920, 1080)
+        }
+    }
+
+    fn is_builtin(command: &str) -> bool {
+        ["forward", "backward", "switch", "f"].contains(&command)
+    }
+
+    #[test]
+    fn test_resolve_alias_expands_chain_and_arguments() {
+        let config =
+            config_with_aliases(&[("n", "next"), ("next", "forward"), ("main", "switch 1")]);
+
+        assert_eq!(
+            config.resolve_alias("n", is_builtin).unwrap(),
+            vec!["forward"]
+        );
+        assert_eq!(
+            config.resolve_alias("main", is_builtin).unwrap(),
+            vec!["switch", "1"]
+        );
+        assert_eq!(
+            config.resolve_alias("stack", is_builtin).unwrap(),
+            vec!["stack"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_alias_detects_cycles() {
+        let config = config_with_aliases(&[("a", "b"), ("b", "a")]);
+        assert!(config.resolve_alias("a", is_builtin).is_err());
+    }
+
+    #[test]
+    fn test_resolve_alias_builtin_wins() {
+        let config = config_with_aliases(&[("f", "backward")]);
+        assert_eq!(config.resolve_alias("f", is_builtin).unwrap(), vec!["f"]);
+    }
+
+    #[test]
+    fn test_config_with_aliases_serializes() {
+        let config = config_with_aliases(&[("next", "forward")]);
+
+        let toml_str = toml::to_string_pretty(&config).unwrap();
+        let deserialized: Config = toml::from_str(&toml_str).unwrap();
+
+        assert_eq!(deserialized.aliases.get("next").unwrap(), "forward");
+    }
+
+    #[test]
+    fn test_partial_overlay_theme_keeps_defaults() {
+        let theme: OverlayTheme = toml::from_str("accent_rgb = [0, 90, 200]").unwrap();
+        assert_eq!(theme.accent_rgb, [0, 90, 200]);
+        assert_eq!(theme.background_rgba, default_background_rgba());
+        assert_eq!(theme.text_rgb, default_text_rgb());
+    }
+
+    #[test]
+    fn test_x11_activation_method_names() {
+        let toml_str = toml::to_string(&Config::for_display(1920, 1080)).unwrap();
+        assert!(toml_str.contains("x11_activation_method = \"net_active_window\""));
+
+        let config: Config = toml::from_str(&toml_str.replace(
+            "x11_activation_method = \"net_active_window\"",
+            "x11_activation_method = \"set_input_focus\"",
+        ))
+        .unwrap();
+        assert_eq!(
+            config.x11_activation_method,
+            X11ActivationMethod::SetInputFocus
+        );
+    }
+
+    #[test]
+    fn test_config_json_round_trip() {
+        let config = config_with_aliases(&[("next", "forward")]);
+
+        let json = ConfigFormat::Json.serialize(&config).unwrap();
+        let deserialized = ConfigFormat::Json.parse(&json).unwrap();
+
+        assert_eq!(deserialized.display_width, 1920);
+        assert_eq!(deserialized.aliases.get("next").unwrap(), "forward");
+    }
+
+    #[test]
+    fn test_confirmation_token_cant_contain_the_separator() {
+        let mut config = Config::for_display(1920, 1080);
+        config.require_confirmation_token = Some("s3cret".to_string());
+        assert!(config.validate().is_ok());
+        config.require_confirmation_token = Some("s3:cret".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_profile_names_cant_leave_the_profiles_dir() {
+        assert!(Config::check_profile_name("mining").is_ok());
+        assert!(Config::check_profile_name("incursion-2").is_ok());
+        for name in ["", "../config", "..", "a/b", "/etc/passwd"] {
+            assert!(Config::check_profile_name(name).is_err(), "{:?}", name);
+            assert!(Config::load(Some(name)).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_saved_path_follows_the_active_profile() {
+        assert_eq!(Config::saved_path(None).unwrap(), Config::config_path());
+        assert_eq!(
+            Config::saved_path(Some("pvp")).unwrap(),
+            Config::profiles_dir().join("pvp.toml")
+        );
+        assert!(Config::saved_path(Some("../config")).is_err());
+    }
+
+    #[test]
+    fn test_profile_names_lists_toml_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["mining.toml", "incursion.toml", "notes.txt"] {
+            fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let names = Config::profile_names(dir.path()).unwrap();
+        assert_eq!(names, vec!["incursion", "mining"]);
+
+        let missing = dir.path().join("profiles");
+        assert!(Config::profile_names(&missing).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_numbers_keeps_the_rest_of_the_toml() {
+        let generated = toml::to_string_pretty(&Config::for_display(1920, 1080)).unwrap();
+        let contents = format!("# Tuned for the left monitor\n{}", generated).replacen(
+            "overlay_x = 10.0",
+            "overlay_x = 10.0 # left edge",
+            1,
+        );
+
+        let updated = ConfigFormat::Toml
+            .set_numbers(&contents, &[("overlay_x", 512.3), ("overlay_y", 40.0)])
+            .unwrap();
+        assert_eq!(
+            updated,
+            contents
+                .replacen("overlay_x = 10.0 #", "overlay_x = 512.3 #", 1)
+                .replacen("overlay_y = 10.0", "overlay_y = 40.0", 1)
+        );
+
+        // Nothing is written back that wouldn't load
+        assert!(ConfigFormat::Toml
+            .set_numbers("overlay_x = 10.0\n", &[("overlay_x", 5.0)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_numbers_keeps_json_key_order() {
+        let contents = ConfigFormat::Json
+            .serialize(&Config::for_display(1920, 1080))
+            .unwrap();
+        let updated = ConfigFormat::Json
+            .set_numbers(&contents, &[("overlay_y", 40.0)])
+            .unwrap();
+        assert_eq!(
+            updated,
+            contents.replacen("\"overlay_y\": 10.0", "\"overlay_y\": 40.0", 1)
+        );
+    }
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/u/.config/nicotine/config.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(ConfigFormat::from_name("JSON"), Some(ConfigFormat::Json));
+        assert_eq!(ConfigFormat::from_name("yaml"), None);
+    }
 }
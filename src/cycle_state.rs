@@ -1,13 +1,68 @@
-use crate::window_manager::{EveWindow, WindowManager};
-use anyhow::Result;
+use crate::config::{Config, CycleMode};
+use crate::paths;
+use crate::window_manager::{EveWindow, WindowManager, CHARACTER_SELECT_TITLE};
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
-const INDEX_FILE: &str = "/tmp/nicotine-index";
+/// The parts of a cycle state that daemonless invocations carry between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersistedState {
+    /// Client that was current when the last command finished
+    pub current_window: Option<u32>,
+    pub pingpong_forward: bool,
+    pub character_select_next: usize,
+    /// Client that was current before the last switch, for `toggle`
+    #[serde(default)]
+    pub previous_window: Option<u32>,
+}
+
+impl PersistedState {
+    /// Read a saved state, starting fresh if there is none or it can't be parsed
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_else(|| Self {
+                pingpong_forward: true,
+                ..Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write cycle state to {}", path.display()))
+    }
+}
 
 pub struct CycleState {
     current_index: usize,
     windows: Vec<EveWindow>,
+    group_character_select: bool,
+    /// Index of the entry standing in for all clients at character select
+    character_select_slot: Option<usize>,
+    /// Window ids collapsed into the character select slot
+    character_select_ids: Vec<u32>,
+    /// Round-robin position within the character select slot
+    character_select_next: usize,
+    cycle_mode: CycleMode,
+    /// Flip ping-pong direction on every call instead of only at the list ends
+    pingpong_every_call: bool,
+    pingpong_forward: bool,
+    /// Step from the last client to the first (and back); otherwise stop at the ends
+    cycle_wrap: bool,
+    /// Title of a client left out of forward/backward cycling
+    cycle_anchor: Option<String>,
+    /// Titles of further clients left out of forward/backward cycling
+    exclude_titles: Vec<String>,
+    /// Client that was current before the most recent change, for `toggle_last`
+    previous_window: Option<u32>,
+    /// Names from characters.txt; listed clients sort first, in this order
+    character_order: Vec<String>,
+    /// Opacity given to every client but the one activated
+    dim_inactive_opacity: Option<f32>,
 }
 
 impl CycleState {
@@ -15,42 +70,184 @@ impl CycleState {
         Self {
             current_index: 0,
             windows: Vec::new(),
+            group_character_select: false,
+            character_select_slot: None,
+            character_select_ids: Vec::new(),
+            character_select_next: 0,
+            cycle_mode: CycleMode::Normal,
+            pingpong_every_call: false,
+            pingpong_forward: true,
+            cycle_wrap: true,
+            cycle_anchor: None,
+            exclude_titles: Vec::new(),
+            previous_window: None,
+            character_order: Vec::new(),
+            dim_inactive_opacity: None,
         }
     }
 
-    pub fn update_windows(&mut self, windows: Vec<EveWindow>) {
-        self.windows = windows;
-        // Clamp current index
-        if self.current_index >= self.windows.len() && !self.windows.is_empty() {
-            self.current_index = 0;
+    /// Create a cycle state using the cycling options from config
+    pub fn from_config(config: &Config) -> Self {
+        let mut state = Self::new();
+        state.apply_config(config);
+        state
+    }
+
+    /// Pick up cycling options from a (re)loaded config, keeping the window list
+    pub fn apply_config(&mut self, config: &Config) {
+        self.group_character_select = config.group_character_select;
+        self.cycle_mode = config.cycle_mode;
+        self.pingpong_every_call = config.pingpong_every_call;
+        self.cycle_wrap = config.cycle_wrap;
+        self.cycle_anchor = if config.skip_anchor_in_cycle {
+            config.anchor_character.clone()
+        } else {
+            None
+        };
+        self.exclude_titles = config.exclude_titles.clone();
+        self.dim_inactive_opacity = config.dim_inactive_opacity;
+    }
+
+    /// Capture what a later daemonless invocation needs to continue from here
+    pub fn snapshot(&self) -> PersistedState {
+        PersistedState {
+            current_window: self.windows.get(self.current_index).map(|w| w.id),
+            pingpong_forward: self.pingpong_forward,
+            character_select_next: self.character_select_next,
+            previous_window: self.previous_window,
         }
     }
 
-    pub fn cycle_forward(&mut self, wm: &dyn WindowManager, minimize_inactive: bool) -> Result<()> {
-        if self.windows.is_empty() {
-            return Ok(());
+    /// Load windows and continue from the saved position. The list is sorted
+    /// the same way on every run, so the rotation needs no saving.
+    pub fn restore(&mut self, saved: &PersistedState, windows: Vec<EveWindow>) {
+        self.pingpong_forward = saved.pingpong_forward;
+        self.character_select_next = saved.character_select_next;
+        self.update_windows(windows);
+
+        if let Some(current) = saved.current_window {
+            self.sync_with_active(current);
         }
+        self.previous_window = saved
+            .previous_window
+            .filter(|&id| self.index_of(id).is_some());
+    }
 
-        let previous_index = self.current_index;
-        self.current_index = (self.current_index + 1) % self.windows.len();
-        self.write_index();
+    /// Order clients by characters.txt; `None` sorts everything by title
+    pub fn set_character_order(&mut self, order: Option<&[String]>) {
+        self.character_order = order.map(<[String]>::to_vec).unwrap_or_default();
+    }
 
-        let new_window_id = self.windows[self.current_index].id;
+    /// Replace the window list, keeping the current client selected by id
+    pub fn update_windows(&mut self, mut windows: Vec<EveWindow>) {
+        let current_id = self.windows.get(self.current_index).map(|w| w.id);
 
-        if minimize_inactive {
-            // Restore new window first (in case it was minimized)
-            let _ = wm.restore_window(new_window_id);
+        // Window managers list clients in whatever order they like; sort so
+        // cycling is the same on every refresh
+        windows.sort_by_cached_key(|w| {
+            let position = self
+                .character_order
+                .iter()
+                .position(|name| *name == w.title)
+                .unwrap_or(usize::MAX);
+            (position, w.title.to_lowercase(), w.id)
+        });
+
+        self.windows = if self.group_character_select {
+            self.group_character_select_windows(windows)
+        } else {
+            self.character_select_slot = None;
+            self.character_select_ids.clear();
+            windows
+        };
+
+        // Follow the current client to wherever it's listed now. If it closed,
+        // stay at the same position so selection lands on a neighbour.
+        let last = self.windows.len().saturating_sub(1);
+        self.current_index = current_id
+            .and_then(|id| self.index_of(id))
+            .unwrap_or(self.current_index.min(last));
+
+        // Forget a previous client that has closed
+        if let Some(previous) = self.previous_window {
+            if self.index_of(previous).is_none() {
+                self.previous_window = None;
+            }
         }
+    }
 
-        wm.activate_window(new_window_id)?;
+    /// Collapse every client at character select into a single entry, placed where
+    /// the first of them appeared. Named clients are left untouched.
+    fn group_character_select_windows(&mut self, windows: Vec<EveWindow>) -> Vec<EveWindow> {
+        self.character_select_ids = windows
+            .iter()
+            .filter(|w| w.is_character_select())
+            .map(|w| w.id)
+            .collect();
+
+        if self.character_select_ids.len() < 2 {
+            self.character_select_slot = None;
+            return windows;
+        }
 
-        if minimize_inactive && previous_index != self.current_index {
-            // Minimize the previous window after activating the new one
-            let previous_window_id = self.windows[previous_index].id;
-            let _ = wm.minimize_window(previous_window_id);
+        let count = self.character_select_ids.len();
+        let slot_id = self.character_select_ids[self.character_select_next % count];
+        let mut grouped = Vec::with_capacity(windows.len() - count + 1);
+        self.character_select_slot = None;
+
+        for window in windows {
+            if !window.is_character_select() {
+                grouped.push(window);
+            } else if self.character_select_slot.is_none() {
+                self.character_select_slot = Some(grouped.len());
+                grouped.push(EveWindow {
+                    id: slot_id,
+                    title: format!("{} ({})", CHARACTER_SELECT_TITLE, count),
+                    demands_attention: false,
+                });
+            }
         }
 
-        Ok(())
+        grouped
+    }
+
+    /// Get the window id to activate for an entry, advancing the round-robin
+    /// when the entry is the character select slot
+    fn activation_target(&mut self, index: usize) -> u32 {
+        if self.character_select_slot == Some(index) {
+            let count = self.character_select_ids.len();
+            let id = self.character_select_ids[self.character_select_next % count];
+            self.character_select_next = (self.character_select_next + 1) % count;
+            self.windows[index].id = id;
+            return id;
+        }
+
+        self.windows[index].id
+    }
+
+    pub fn cycle_forward(&mut self, wm: &dyn WindowManager, minimize_inactive: bool) -> Result<()> {
+        if self.windows.is_empty() {
+            return Ok(());
+        }
+
+        if self.cycle_mode == CycleMode::PingPong {
+            return self.cycle_pingpong(wm, minimize_inactive);
+        }
+
+        let len = self.windows.len();
+        let wrap = self.cycle_wrap;
+        let previous_index = self.current_index;
+        let Some(next) = self.next_cycle_member(|i| {
+            if i + 1 < len {
+                Some(i + 1)
+            } else {
+                wrap.then_some(0)
+            }
+        }) else {
+            return Ok(());
+        };
+        self.current_index = next;
+        self.activate_current(previous_index, wm, minimize_inactive)
     }
 
     pub fn cycle_backward(
@@ -62,23 +259,117 @@ impl CycleState {
             return Ok(());
         }
 
+        let len = self.windows.len();
+        let wrap = self.cycle_wrap;
         let previous_index = self.current_index;
-        if self.current_index == 0 {
-            self.current_index = self.windows.len() - 1;
+        let Some(next) = self.next_cycle_member(|i| {
+            if i > 0 {
+                Some(i - 1)
+            } else {
+                wrap.then_some(len - 1)
+            }
+        }) else {
+            return Ok(());
+        };
+        self.current_index = next;
+        self.activate_current(previous_index, wm, minimize_inactive)
+    }
+
+    /// Whether an entry is left out of forward/backward cycling. It stays in
+    /// the window list so it can still be shown and switched to directly.
+    fn skipped_in_cycle(&self, index: usize) -> bool {
+        let title = &self.windows[index].title;
+        self.cycle_anchor.as_deref() == Some(title.as_str()) || self.exclude_titles.contains(title)
+    }
+
+    /// Apply `step` from the current index until it lands on an entry that
+    /// takes part in cycling. None if every entry is skipped or `step` runs
+    /// off the end of the list.
+    fn next_cycle_member(&self, step: impl Fn(usize) -> Option<usize>) -> Option<usize> {
+        let mut index = self.current_index;
+        for _ in 0..self.windows.len() {
+            index = step(index)?;
+            if !self.skipped_in_cycle(index) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Step in the current ping-pong direction, bouncing off either end of the list
+    fn cycle_pingpong(&mut self, wm: &dyn WindowManager, minimize_inactive: bool) -> Result<()> {
+        let previous_index = self.current_index;
+        let forward = self.pingpong_forward;
+
+        let target = self
+            .pingpong_target(forward)
+            .or_else(|| self.pingpong_target(!forward))
+            .or_else(|| (!self.skipped_in_cycle(previous_index)).then_some(previous_index));
+        let Some(next) = target else {
+            return Ok(());
+        };
+
+        let step_forward = next > previous_index;
+        self.current_index = next;
+
+        self.pingpong_forward = if self.pingpong_every_call {
+            !step_forward
+        } else {
+            step_forward
+        };
+
+        self.activate_current(previous_index, wm, minimize_inactive)
+    }
+
+    /// Nearest cycling entry in one direction from the current index, without wrapping
+    fn pingpong_target(&self, forward: bool) -> Option<usize> {
+        if forward {
+            (self.current_index + 1..self.windows.len()).find(|&i| !self.skipped_in_cycle(i))
         } else {
-            self.current_index -= 1;
+            (0..self.current_index)
+                .rev()
+                .find(|&i| !self.skipped_in_cycle(i))
         }
+    }
 
+    /// Activate the window at current_index after it moved away from previous_index
+    fn activate_current(
+        &mut self,
+        previous_index: usize,
+        wm: &dyn WindowManager,
+        minimize_inactive: bool,
+    ) -> Result<()> {
         self.write_index();
+        if previous_index != self.current_index {
+            self.previous_window = Some(self.windows[previous_index].id);
+        }
 
-        let new_window_id = self.windows[self.current_index].id;
+        let new_window_id = self.activation_target(self.current_index);
 
         if minimize_inactive {
             // Restore new window first (in case it was minimized)
             let _ = wm.restore_window(new_window_id);
         }
 
-        wm.activate_window(new_window_id)?;
+        // Re-activating the focused client only makes some WMs flicker
+        if wm.get_active_window().ok() != Some(new_window_id) {
+            wm.activate_window(new_window_id)?;
+        }
+
+        if let Some(opacity) = self.dim_inactive_opacity {
+            let ids: Vec<u32> = self
+                .windows
+                .iter()
+                .map(|w| w.id)
+                .chain(self.character_select_ids.iter().copied())
+                .collect();
+            if let Err(e) = wm.dim_inactive(&ids, new_window_id, opacity) {
+                warn!("Failed to dim inactive clients: {}", e);
+            }
+        }
+
+        // The client has our attention now, so drop its highlight without waiting for a refresh
+        self.windows[self.current_index].demands_attention = false;
 
         if minimize_inactive && previous_index != self.current_index {
             // Minimize the previous window after activating the new one
@@ -89,18 +380,37 @@ impl CycleState {
         Ok(())
     }
 
+    /// Jump to the next client (after the current one) demanding attention
+    /// Does nothing if no client has the urgency hint set
+    pub fn next_attention(
+        &mut self,
+        wm: &dyn WindowManager,
+        minimize_inactive: bool,
+    ) -> Result<()> {
+        let count = self.windows.len();
+        let target = (1..=count)
+            .map(|offset| (self.current_index + offset) % count)
+            .find(|&i| self.windows[i].demands_attention);
+
+        if let Some(target) = target {
+            let previous_index = self.current_index;
+            self.current_index = target;
+            self.activate_current(previous_index, wm, minimize_inactive)?;
+        }
+
+        Ok(())
+    }
+
     fn write_index(&self) {
-        let _ = fs::write(INDEX_FILE, self.current_index.to_string());
+        if let Ok(path) = paths::index_file() {
+            let _ = fs::write(path, self.current_index.to_string());
+        }
     }
 
     pub fn read_index_from_file() -> Option<usize> {
-        if Path::new(INDEX_FILE).exists() {
-            fs::read_to_string(INDEX_FILE)
-                .ok()
-                .and_then(|s| s.trim().parse().ok())
-        } else {
-            None
-        }
+        fs::read_to_string(paths::index_file().ok()?)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
     }
 
     pub fn get_windows(&self) -> &[EveWindow] {
@@ -111,6 +421,10 @@ impl CycleState {
         self.current_index
     }
 
+    pub fn current_window(&self) -> Option<&EveWindow> {
+        self.windows.get(self.current_index)
+    }
+
     pub fn set_current_index(&mut self, index: usize) {
         if index < self.windows.len() || self.windows.is_empty() {
             self.current_index = index;
@@ -118,13 +432,40 @@ impl CycleState {
     }
 
     pub fn sync_with_active(&mut self, active_window: u32) {
-        // Find which window is active and update current_index
-        for (i, window) in self.windows.iter().enumerate() {
-            if window.id == active_window {
-                self.current_index = i;
-                break;
+        if let Some(index) = self.index_of(active_window) {
+            if index != self.current_index {
+                // Focus moved outside of nicotine; remember where it came from
+                if let Some(window) = self.windows.get(self.current_index) {
+                    self.previous_window = Some(window.id);
+                }
+                self.current_index = index;
+            }
+        }
+    }
+
+    /// Entry holding a window id, mapping any client at character select to its slot
+    fn index_of(&self, window_id: u32) -> Option<usize> {
+        if let Some(slot) = self.character_select_slot {
+            if self.character_select_ids.contains(&window_id) {
+                return Some(slot);
             }
         }
+        self.windows.iter().position(|w| w.id == window_id)
+    }
+
+    /// Jump back to the client that was current before the last switch,
+    /// Alt-Tab style. Does nothing until there have been two clients in use.
+    pub fn toggle_last(&mut self, wm: &dyn WindowManager, minimize_inactive: bool) -> Result<()> {
+        let Some(target) = self.previous_window.and_then(|id| self.index_of(id)) else {
+            return Ok(());
+        };
+        if target == self.current_index {
+            return Ok(());
+        }
+
+        let previous_index = self.current_index;
+        self.current_index = target;
+        self.activate_current(previous_index, wm, minimize_inactive)
     }
 
     /// Switch to a specific target number (1-indexed)
@@ -181,22 +522,77 @@ impl CycleState {
 
         let previous_index = self.current_index;
         self.current_index = target_index;
-        self.write_index();
+        self.activate_current(previous_index, wm, minimize_inactive)
+    }
 
-        let new_window_id = self.windows[self.current_index].id;
+    /// Index of the client for a character name: an exact title match wins,
+    /// then a case-insensitive one, then the only title containing `name`
+    fn find_by_name(&self, name: &str) -> Result<usize> {
+        let name = name.trim();
+        if let Some(index) = self.windows.iter().position(|w| w.title == name) {
+            return Ok(index);
+        }
 
-        if minimize_inactive {
-            let _ = wm.restore_window(new_window_id);
+        let lower = name.to_lowercase();
+        if let Some(index) = self
+            .windows
+            .iter()
+            .position(|w| w.title.to_lowercase() == lower)
+        {
+            return Ok(index);
         }
 
-        wm.activate_window(new_window_id)?;
+        let partial: Vec<usize> = (0..self.windows.len())
+            .filter(|&i| self.windows[i].title.to_lowercase().contains(&lower))
+            .collect();
+        match partial.as_slice() {
+            [index] => Ok(*index),
+            [] => anyhow::bail!("Character '{}' not found in active windows", name),
+            _ => {
+                let titles: Vec<&str> = partial
+                    .iter()
+                    .map(|&i| self.windows[i].title.as_str())
+                    .collect();
+                anyhow::bail!("'{}' matches several clients: {}", name, titles.join(", "))
+            }
+        }
+    }
 
-        if minimize_inactive {
-            let previous_window_id = self.windows[previous_index].id;
-            let _ = wm.minimize_window(previous_window_id);
+    /// Switch to the client playing a character, independent of list order
+    pub fn switch_to_name(
+        &mut self,
+        name: &str,
+        wm: &dyn WindowManager,
+        minimize_inactive: bool,
+    ) -> Result<()> {
+        let target_index = self.find_by_name(name)?;
+        if target_index == self.current_index {
+            return Ok(());
         }
 
-        Ok(())
+        let previous_index = self.current_index;
+        self.current_index = target_index;
+        self.activate_current(previous_index, wm, minimize_inactive)
+    }
+
+    /// Switch to the client with this window id. Windows that aren't clients
+    /// are ignored.
+    pub fn switch_to_window(
+        &mut self,
+        window_id: u32,
+        wm: &dyn WindowManager,
+        minimize_inactive: bool,
+    ) -> Result<()> {
+        let Some(target_index) = self.index_of(window_id) else {
+            return Ok(());
+        };
+        if target_index == self.current_index {
+            return Ok(());
+        }
+
+        let previous_index = self.current_index;
+        self.current_index = target_index;
+        self.activate_current(previous_index, wm, minimize_inactive)
     }
 }
 
@@ -208,6 +604,7 @@ mod tests {
         EveWindow {
             id,
             title: title.to_string(),
+            demands_attention: false,
         }
     }
 
@@ -254,8 +651,8 @@ mod tests {
         ];
         state.update_windows(windows);
 
-        // Index should be clamped back to 0
-        assert_eq!(state.get_current_index(), 0);
+        // The current client closed; clamp to the last one left
+        assert_eq!(state.get_current_index(), 1);
     }
 
     #[test]
@@ -360,26 +757,144 @@ mod tests {
         assert_eq!(state.get_current_index(), 2);
     }
 
-    // Mock WindowManager for testing switch_to
+    #[test]
+    fn test_update_windows_sorts_by_character_order_then_title_then_id() {
+        let mut state = CycleState::new();
+        state.set_character_order(Some(&["Zulu".to_string(), "Mike".to_string()]));
+        state.update_windows(vec![
+            create_test_window(5, "bravo"),
+            create_test_window(4, "Mike"),
+            create_test_window(3, "Alpha"),
+            create_test_window(2, "Zulu"),
+            create_test_window(1, "Alpha"),
+        ]);
+        let ids: Vec<u32> = state.get_windows().iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec![2, 4, 1, 3, 5]);
+
+        // The selection follows the client when the listing order changes
+        state.sync_with_active(3);
+        state.set_character_order(None);
+        state.update_windows(vec![
+            create_test_window(2, "Zulu"),
+            create_test_window(3, "Alpha"),
+            create_test_window(4, "Mike"),
+        ]);
+        let ids: Vec<u32> = state.get_windows().iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec![3, 4, 2]);
+        assert_eq!(state.get_current_index(), 0);
+    }
+
+    #[test]
+    fn test_selection_moves_to_neighbour_when_current_closes() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(1, "Alpha"),
+            create_test_window(2, "Beta"),
+            create_test_window(3, "Gamma"),
+        ]);
+        state.sync_with_active(2);
+
+        state.update_windows(vec![
+            create_test_window(1, "Alpha"),
+            create_test_window(3, "Gamma"),
+        ]);
+        assert_eq!(state.current_window().map(|w| w.id), Some(3));
+
+        state.update_windows(vec![create_test_window(1, "Alpha")]);
+        assert_eq!(state.current_window().map(|w| w.id), Some(1));
+
+        state.update_windows(vec![]);
+        assert_eq!(state.get_current_index(), 0);
+    }
+
+    #[test]
+    fn test_selection_kept_when_client_opens_before_it() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(1, "Beta"),
+            create_test_window(2, "Gamma"),
+        ]);
+        state.sync_with_active(2);
+
+        state.update_windows(vec![
+            create_test_window(1, "Beta"),
+            create_test_window(2, "Gamma"),
+            create_test_window(3, "Alpha"),
+        ]);
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(state.current_window().map(|w| w.id), Some(2));
+    }
+
+    #[test]
+    fn test_selection_follows_client_when_list_reorders() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(1, "EVE"),
+            create_test_window(2, "Mike"),
+            create_test_window(3, "Zulu"),
+        ]);
+        state.sync_with_active(1);
+
+        // Logging in renames the client, which moves it in the sorted list
+        state.update_windows(vec![
+            create_test_window(1, "Yankee"),
+            create_test_window(2, "Mike"),
+            create_test_window(3, "Zulu"),
+        ]);
+        assert_eq!(state.get_current_index(), 1);
+        assert_eq!(state.current_window().map(|w| w.id), Some(1));
+    }
+
+    /// In-memory window manager: records activations and minimizes, and
+    /// reports whatever client list and active window a test gives it
     struct MockWindowManager {
         activated_windows: std::sync::Mutex<Vec<u32>>,
+        minimized_windows: std::sync::Mutex<Vec<u32>>,
+        /// Reported by get_active_window; 0 (nothing) unless a test sets it
+        active_window: std::sync::Mutex<u32>,
+        /// Reported by get_eve_windows
+        windows: std::sync::Mutex<Vec<EveWindow>>,
+        /// Arguments of each dim_inactive call
+        dimmed: std::sync::Mutex<Vec<(Vec<u32>, u32, f32)>>,
     }
 
     impl MockWindowManager {
         fn new() -> Self {
+            Self::with_windows(Vec::new())
+        }
+
+        fn with_windows(windows: Vec<EveWindow>) -> Self {
             Self {
                 activated_windows: std::sync::Mutex::new(Vec::new()),
+                minimized_windows: std::sync::Mutex::new(Vec::new()),
+                active_window: std::sync::Mutex::new(0),
+                windows: std::sync::Mutex::new(windows),
+                dimmed: std::sync::Mutex::new(Vec::new()),
             }
         }
 
+        fn set_active(&self, window_id: u32) {
+            *self.active_window.lock().unwrap() = window_id;
+        }
+
+        /// Refresh a state from this window manager the way the daemon does
+        fn refresh(&self, state: &mut CycleState) {
+            state.update_windows(self.get_eve_windows().unwrap());
+            state.sync_with_active(self.get_active_window().unwrap());
+        }
+
         fn get_activated(&self) -> Vec<u32> {
             self.activated_windows.lock().unwrap().clone()
         }
+
+        fn get_minimized(&self) -> Vec<u32> {
+            self.minimized_windows.lock().unwrap().clone()
+        }
     }
 
     impl WindowManager for MockWindowManager {
         fn get_eve_windows(&self) -> anyhow::Result<Vec<EveWindow>> {
-            Ok(vec![])
+            Ok(self.windows.lock().unwrap().clone())
         }
 
         fn activate_window(&self, window_id: u32) -> anyhow::Result<()> {
@@ -396,22 +911,236 @@ mod tests {
         }
 
         fn get_active_window(&self) -> anyhow::Result<u32> {
-            Ok(0)
+            Ok(*self.active_window.lock().unwrap())
+        }
+
+        fn dim_inactive(
+            &self,
+            window_ids: &[u32],
+            active_id: u32,
+            opacity: f32,
+        ) -> anyhow::Result<()> {
+            self.dimmed
+                .lock()
+                .unwrap()
+                .push((window_ids.to_vec(), active_id, opacity));
+            Ok(())
         }
 
         fn find_window_by_title(&self, _title: &str) -> anyhow::Result<Option<u32>> {
             Ok(None)
         }
 
-        fn minimize_window(&self, _window_id: u32) -> anyhow::Result<()> {
+        fn minimize_window(&self, window_id: u32) -> anyhow::Result<()> {
+            self.minimized_windows.lock().unwrap().push(window_id);
             Ok(())
         }
 
-        fn restore_window(&self, _window_id: u32) -> anyhow::Result<()> {
+        fn restore_window(&self, window_id: u32) -> anyhow::Result<()> {
+            self.minimized_windows
+                .lock()
+                .unwrap()
+                .retain(|&id| id != window_id);
             Ok(())
         }
     }
 
+    fn three_windows() -> Vec<EveWindow> {
+        vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+            create_test_window(300, "Gamma"),
+        ]
+    }
+
+    #[test]
+    fn test_cycle_wraps_at_list_ends_by_default() {
+        let mut state = CycleState::new();
+        state.update_windows(three_windows());
+        let wm = MockWindowManager::new();
+
+        state.current_index = 2;
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 0);
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(wm.get_activated(), vec![100, 300]);
+    }
+
+    #[test]
+    fn test_cycle_on_empty_list_does_nothing() {
+        let wm = MockWindowManager::new();
+        let mut state = CycleState::new();
+        wm.refresh(&mut state);
+
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 0);
+        assert!(state.current_window().is_none());
+        assert!(wm.get_activated().is_empty());
+    }
+
+    #[test]
+    fn test_cycle_full_circle_in_both_directions() {
+        let wm = MockWindowManager::with_windows(three_windows());
+        let mut state = CycleState::new();
+        wm.refresh(&mut state);
+
+        for _ in 0..3 {
+            state.cycle_forward(&wm, false).unwrap();
+        }
+        assert_eq!(state.get_current_index(), 0);
+        for _ in 0..3 {
+            state.cycle_backward(&wm, false).unwrap();
+        }
+        assert_eq!(state.get_current_index(), 0);
+        assert_eq!(wm.get_activated(), vec![200, 300, 100, 300, 200, 100]);
+    }
+
+    #[test]
+    fn test_cycle_continues_from_client_focused_elsewhere() {
+        let wm = MockWindowManager::with_windows(three_windows());
+        let mut state = CycleState::new();
+        wm.refresh(&mut state);
+
+        // The user clicked Gamma; the next refresh picks that up
+        wm.set_active(300);
+        wm.refresh(&mut state);
+        assert_eq!(state.get_current_index(), 2);
+
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![100]);
+        wm.set_active(100);
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![100, 300]);
+    }
+
+    #[test]
+    fn test_cycle_follows_character_order() {
+        let wm = MockWindowManager::with_windows(three_windows());
+        let mut state = CycleState::new();
+        let order = vec!["Gamma".to_string(), "Alpha".to_string()];
+        state.set_character_order(Some(&order));
+        wm.refresh(&mut state);
+
+        // Listed characters first, in file order, then the rest by title
+        let titles: Vec<&str> = state
+            .get_windows()
+            .iter()
+            .map(|w| w.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Gamma", "Alpha", "Beta"]);
+
+        for _ in 0..3 {
+            state.cycle_forward(&wm, false).unwrap();
+        }
+        assert_eq!(wm.get_activated(), vec![100, 200, 300]);
+
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![100, 200, 300, 200]);
+    }
+
+    #[test]
+    fn test_cycle_without_wrap_stops_at_list_ends() {
+        let mut config = Config::for_display(1920, 1080);
+        config.cycle_wrap = false;
+        let mut state = CycleState::from_config(&config);
+        state.update_windows(three_windows());
+        let wm = MockWindowManager::new();
+
+        state.current_index = 1;
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 2);
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 2);
+
+        state.current_index = 0;
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 0);
+        assert_eq!(wm.get_activated(), vec![300]);
+    }
+
+    #[test]
+    fn test_cycle_single_client_with_and_without_wrap() {
+        let wm = MockWindowManager::new();
+        let mut state = CycleState::new();
+        state.update_windows(vec![create_test_window(100, "Alpha")]);
+
+        // Wrapping lands back on the only client
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 0);
+        assert_eq!(wm.get_activated(), vec![100, 100]);
+
+        let mut config = Config::for_display(1920, 1080);
+        config.cycle_wrap = false;
+        state.apply_config(&config);
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 0);
+        assert_eq!(wm.get_activated(), vec![100, 100]);
+    }
+
+    #[test]
+    fn test_dim_inactive_uses_the_listed_clients() {
+        let wm = MockWindowManager::new();
+        let mut state = CycleState::new();
+        state.update_windows(three_windows());
+
+        state.cycle_forward(&wm, false).unwrap();
+        assert!(wm.dimmed.lock().unwrap().is_empty());
+
+        let mut config = Config::for_display(1920, 1080);
+        config.dim_inactive_opacity = Some(0.6);
+        state.apply_config(&config);
+        state.cycle_forward(&wm, false).unwrap();
+        let ids: Vec<u32> = three_windows().iter().map(|w| w.id).collect();
+        let active = state.get_windows()[state.get_current_index()].id;
+        assert_eq!(*wm.dimmed.lock().unwrap(), vec![(ids, active, 0.6)]);
+    }
+
+    #[test]
+    fn test_already_active_client_is_not_activated_again() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+        ]);
+        let wm = MockWindowManager::new();
+        // Focus got to Beta behind nicotine's back, ahead of the cycle
+        *wm.active_window.lock().unwrap() = 200;
+
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(state.current_window().map(|w| w.id), Some(200));
+        assert!(wm.get_activated().is_empty());
+
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![100]);
+    }
+
+    #[test]
+    fn test_minimize_inactive_applies_to_every_switch_path() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+            create_test_window(300, "Gamma"),
+        ]);
+        let wm = MockWindowManager::new();
+
+        state.cycle_forward(&wm, true).unwrap();
+        assert_eq!(wm.get_minimized(), vec![100]);
+
+        state.cycle_backward(&wm, true).unwrap();
+        assert_eq!(wm.get_minimized(), vec![200]);
+
+        state.switch_to(3, &wm, true, None).unwrap();
+        assert_eq!(wm.get_minimized(), vec![200, 100]);
+
+        state.switch_to_name("Beta", &wm, true).unwrap();
+        assert_eq!(wm.get_minimized(), vec![100, 300]);
+    }
+
     #[test]
     fn test_switch_to_by_index_no_character_order() {
         let mut state = CycleState::new();
@@ -447,8 +1176,66 @@ mod tests {
         let char_order = vec!["Alpha".to_string(), "Beta".to_string(), "Gamma".to_string()];
 
         // Switch to target 1 (Alpha) - should find window 200
+        // Alpha sorts first, so it's already the current client
         state.switch_to(1, &wm, false, Some(&char_order)).unwrap();
-        assert_eq!(state.get_current_index(), 1); // Index of Alpha in windows
+        assert_eq!(state.get_current_index(), 0); // Index of Alpha in the sorted windows
+        assert!(wm.get_activated().is_empty());
+
+        // Switch to target 3 (Gamma) - should find window 100
+        state.switch_to(3, &wm, false, Some(&char_order)).unwrap();
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(wm.get_activated(), vec![100]);
+    }
+
+    #[test]
+    fn test_switch_to_name_prefers_exact_then_case_insensitive_then_partial() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Jita Trader"),
+            create_test_window(200, "jita trader alt"),
+            create_test_window(300, "Miner Bob"),
+        ]);
+        let wm = MockWindowManager::new();
+
+        state.switch_to_name("jita trader alt", &wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 1);
+
+        state.switch_to_name("JITA TRADER", &wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 0);
+
+        state.switch_to_name("bob", &wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(wm.get_activated(), vec![200, 100, 300]);
+    }
+
+    #[test]
+    fn test_switch_to_name_rejects_ambiguous_and_unknown() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Jita Trader"),
+            create_test_window(200, "Jita Hauler"),
+        ]);
+        let wm = MockWindowManager::new();
+
+        assert!(state.switch_to_name("jita", &wm, false).is_err());
+        assert!(state.switch_to_name("Amarr", &wm, false).is_err());
+        assert!(wm.get_activated().is_empty());
+    }
+
+    #[test]
+    fn test_switch_to_window_ignores_non_clients() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+        ]);
+        let wm = MockWindowManager::new();
+
+        state.switch_to_window(200, &wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 1);
+
+        state.switch_to_window(999, &wm, false).unwrap();
+        assert_eq!(state.get_current_index(), 1);
         assert_eq!(wm.get_activated(), vec![200]);
     }
 
@@ -487,6 +1274,50 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_switch_to_past_character_order_is_an_error() {
+        let wm = MockWindowManager::with_windows(three_windows());
+        let mut state = CycleState::new();
+        wm.refresh(&mut state);
+        let order = vec!["Beta".to_string()];
+
+        let err = state.switch_to(2, &wm, false, Some(&order)).unwrap_err();
+        assert!(err.to_string().contains("only 1 characters configured"));
+        assert!(wm.get_activated().is_empty());
+
+        state.switch_to(1, &wm, false, Some(&order)).unwrap();
+        assert_eq!(wm.get_activated(), vec![200]);
+    }
+
+    #[test]
+    fn test_switch_to_single_client() {
+        let wm = MockWindowManager::with_windows(vec![create_test_window(100, "Alpha")]);
+        let mut state = CycleState::new();
+        wm.refresh(&mut state);
+
+        // Already on the only client
+        state.switch_to(1, &wm, false, None).unwrap();
+        assert!(wm.get_activated().is_empty());
+        assert!(state.switch_to(2, &wm, false, None).is_err());
+    }
+
+    #[test]
+    fn test_sync_with_active_ignores_unknown_and_empty() {
+        let wm = MockWindowManager::new();
+        let mut state = CycleState::new();
+        wm.set_active(100);
+        wm.refresh(&mut state);
+        assert_eq!(state.get_current_index(), 0);
+
+        // A non-EVE window getting focus leaves the selection alone
+        *wm.windows.lock().unwrap() = three_windows();
+        wm.set_active(200);
+        wm.refresh(&mut state);
+        wm.set_active(999);
+        wm.refresh(&mut state);
+        assert_eq!(state.current_window().map(|w| w.id), Some(200));
+    }
+
     #[test]
     fn test_switch_to_character_not_logged_in() {
         let mut state = CycleState::new();
@@ -529,4 +1360,383 @@ mod tests {
         state.switch_to(1, &wm, false, None).unwrap();
         assert!(wm.get_activated().is_empty());
     }
+
+    fn grouping_state() -> CycleState {
+        let mut state = CycleState::new();
+        state.group_character_select = true;
+        state
+    }
+
+    #[test]
+    fn test_character_select_windows_collapse_into_one_slot() {
+        let mut state = grouping_state();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "EVE"),
+            create_test_window(300, "EVE"),
+            create_test_window(400, "Beta"),
+            create_test_window(500, "EVE"),
+        ]);
+
+        let titles: Vec<&str> = state
+            .get_windows()
+            .iter()
+            .map(|w| w.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Alpha", "Beta", "EVE (3)"]);
+    }
+
+    #[test]
+    fn test_character_select_not_grouped_when_disabled() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(200, "EVE"),
+            create_test_window(300, "EVE"),
+        ]);
+
+        assert_eq!(state.get_windows().len(), 2);
+    }
+
+    #[test]
+    fn test_character_select_slot_activates_round_robin() {
+        let mut state = grouping_state();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "EVE"),
+            create_test_window(300, "EVE"),
+        ]);
+
+        let wm = MockWindowManager::new();
+
+        // Alpha -> slot -> Alpha -> slot should visit both character select clients
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_forward(&wm, false).unwrap();
+
+        assert_eq!(wm.get_activated(), vec![200, 100, 300, 100]);
+    }
+
+    #[test]
+    fn test_sync_with_active_maps_character_select_to_slot() {
+        let mut state = grouping_state();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "EVE"),
+            create_test_window(300, "EVE"),
+        ]);
+
+        state.sync_with_active(300);
+        assert_eq!(state.get_current_index(), 1);
+    }
+
+    #[test]
+    fn test_character_select_expands_as_clients_log_in() {
+        let mut state = grouping_state();
+        state.update_windows(vec![
+            create_test_window(200, "EVE"),
+            create_test_window(300, "EVE"),
+        ]);
+        assert_eq!(state.get_windows().len(), 1);
+
+        // One client picks a character, leaving a single client at character select
+        state.update_windows(vec![
+            create_test_window(200, "Alpha"),
+            create_test_window(300, "EVE"),
+        ]);
+
+        let titles: Vec<&str> = state
+            .get_windows()
+            .iter()
+            .map(|w| w.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Alpha", "EVE"]);
+    }
+
+    fn pingpong_sequence(window_count: u32, every_call: bool, steps: usize) -> Vec<u32> {
+        let mut state = CycleState::new();
+        state.cycle_mode = CycleMode::PingPong;
+        state.pingpong_every_call = every_call;
+        state.update_windows(
+            (1..=window_count)
+                .map(|id| create_test_window(id, &format!("Character {}", id)))
+                .collect(),
+        );
+
+        let wm = MockWindowManager::new();
+        for _ in 0..steps {
+            state.cycle_forward(&wm, false).unwrap();
+        }
+        wm.get_activated()
+    }
+
+    #[test]
+    fn test_pingpong_two_windows_oscillates() {
+        assert_eq!(pingpong_sequence(2, false, 4), vec![2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_pingpong_three_windows_bounces_at_ends() {
+        assert_eq!(pingpong_sequence(3, false, 6), vec![2, 3, 2, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_pingpong_four_windows_bounces_at_ends() {
+        assert_eq!(pingpong_sequence(4, false, 7), vec![2, 3, 4, 3, 2, 1, 2]);
+    }
+
+    #[test]
+    fn test_pingpong_every_call_stays_between_adjacent_clients() {
+        assert_eq!(pingpong_sequence(3, true, 4), vec![2, 1, 2, 1]);
+        assert_eq!(pingpong_sequence(4, true, 4), vec![2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_next_attention_jumps_to_urgent_client() {
+        let mut state = CycleState::new();
+        let mut windows = vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+            create_test_window(300, "Gamma"),
+        ];
+        windows[2].demands_attention = true;
+        state.update_windows(windows);
+
+        let wm = MockWindowManager::new();
+        state.next_attention(&wm, false).unwrap();
+
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(wm.get_activated(), vec![300]);
+        // Highlight is cleared once the client is activated
+        assert!(!state.get_windows()[2].demands_attention);
+    }
+
+    #[test]
+    fn test_next_attention_without_urgent_client_does_nothing() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+        ]);
+
+        let wm = MockWindowManager::new();
+        state.next_attention(&wm, false).unwrap();
+
+        assert_eq!(state.get_current_index(), 0);
+        assert!(wm.get_activated().is_empty());
+    }
+
+    fn anchored_state(anchor: &str) -> CycleState {
+        let mut state = CycleState::new();
+        state.cycle_anchor = Some(anchor.to_string());
+        state.update_windows(vec![
+            create_test_window(100, "Main"),
+            create_test_window(200, "Alt One"),
+            create_test_window(300, "Alt Two"),
+        ]);
+        state
+    }
+
+    #[test]
+    fn test_cycle_forward_skips_anchor() {
+        let mut state = anchored_state("Main");
+        let wm = MockWindowManager::new();
+        for _ in 0..4 {
+            state.cycle_forward(&wm, false).unwrap();
+        }
+
+        // Sorted by title, the anchor comes last
+        assert_eq!(wm.get_activated(), vec![300, 200, 300, 200]);
+        // The anchor is still listed for display and direct switching
+        assert_eq!(state.get_windows().len(), 3);
+    }
+
+    #[test]
+    fn test_cycle_backward_skips_anchor() {
+        let mut state = anchored_state("Main");
+        let wm = MockWindowManager::new();
+        for _ in 0..3 {
+            state.cycle_backward(&wm, false).unwrap();
+        }
+
+        assert_eq!(wm.get_activated(), vec![300, 200, 300]);
+    }
+
+    #[test]
+    fn test_switch_to_reaches_skipped_anchor() {
+        let mut state = anchored_state("Main");
+        state.set_current_index(1);
+        let wm = MockWindowManager::new();
+        state.switch_to(3, &wm, false, None).unwrap();
+
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(wm.get_activated(), vec![100]);
+    }
+
+    #[test]
+    fn test_pingpong_skips_anchor() {
+        let mut state = anchored_state("Alt One");
+        state.cycle_mode = CycleMode::PingPong;
+        let wm = MockWindowManager::new();
+        for _ in 0..4 {
+            state.cycle_forward(&wm, false).unwrap();
+        }
+
+        assert_eq!(wm.get_activated(), vec![300, 100, 300, 100]);
+    }
+
+    #[test]
+    fn test_cycle_with_only_anchor_does_nothing() {
+        let mut state = CycleState::new();
+        state.cycle_anchor = Some("Main".to_string());
+        state.update_windows(vec![create_test_window(100, "Main")]);
+        let wm = MockWindowManager::new();
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_backward(&wm, false).unwrap();
+
+        assert!(wm.get_activated().is_empty());
+    }
+
+    fn excluding_state() -> CycleState {
+        let mut state = CycleState::new();
+        state.exclude_titles = vec!["Market Alt".to_string()];
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Market Alt"),
+            create_test_window(300, "Gamma"),
+        ]);
+        state
+    }
+
+    #[test]
+    fn test_cycle_skips_excluded_titles() {
+        let mut state = excluding_state();
+        let wm = MockWindowManager::new();
+        for _ in 0..3 {
+            state.cycle_forward(&wm, false).unwrap();
+        }
+        assert_eq!(wm.get_activated(), vec![300, 100, 300]);
+
+        let mut state = excluding_state();
+        let wm = MockWindowManager::new();
+        for _ in 0..3 {
+            state.cycle_backward(&wm, false).unwrap();
+        }
+        assert_eq!(wm.get_activated(), vec![300, 100, 300]);
+    }
+
+    #[test]
+    fn test_excluded_client_reachable_and_cycled_away_from() {
+        let mut state = excluding_state();
+        let wm = MockWindowManager::new();
+        // Sorted by title, the excluded client is last
+        state.switch_to(3, &wm, false, None).unwrap();
+        assert_eq!(state.get_current_index(), 2);
+        assert_eq!(wm.get_activated(), vec![200]);
+
+        // Focus landing on the excluded client from outside moves on from its position
+        let mut state = excluding_state();
+        state.sync_with_active(200);
+        assert_eq!(state.get_current_index(), 2);
+        let wm = MockWindowManager::new();
+        state.cycle_forward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![100]);
+
+        state.sync_with_active(200);
+        state.cycle_backward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![100, 300]);
+    }
+
+    #[test]
+    fn test_restore_continues_from_snapshot() {
+        let mut state = CycleState::new();
+        state.cycle_mode = CycleMode::PingPong;
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+            create_test_window(300, "Gamma"),
+        ]);
+        let wm = MockWindowManager::new();
+        state.cycle_forward(&wm, false).unwrap();
+        state.cycle_forward(&wm, false).unwrap();
+
+        let saved: PersistedState =
+            serde_json::from_str(&serde_json::to_string(&state.snapshot()).unwrap()).unwrap();
+
+        // The WM now lists the clients in a different order
+        let mut restored = CycleState::new();
+        restored.cycle_mode = CycleMode::PingPong;
+        restored.restore(
+            &saved,
+            vec![
+                create_test_window(300, "Gamma"),
+                create_test_window(100, "Alpha"),
+                create_test_window(200, "Beta"),
+            ],
+        );
+
+        assert_eq!(restored.get_current_index(), 2);
+        let ids: Vec<u32> = restored.get_windows().iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec![100, 200, 300]);
+
+        // Ping-pong direction carried over: bounces back from the end
+        restored.cycle_forward(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![200, 300, 200]);
+    }
+
+    #[test]
+    fn test_toggle_last_flips_between_two_most_recent() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+            create_test_window(300, "Gamma"),
+        ]);
+        let wm = MockWindowManager::new();
+
+        // Nothing to go back to yet
+        state.toggle_last(&wm, false).unwrap();
+        assert!(wm.get_activated().is_empty());
+
+        state.switch_to(3, &wm, false, None).unwrap();
+        state.toggle_last(&wm, false).unwrap();
+        state.toggle_last(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![300, 100, 300]);
+
+        // Focus changed outside nicotine counts as a switch too
+        state.sync_with_active(200);
+        state.toggle_last(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![300, 100, 300, 300]);
+        assert_eq!(state.get_current_index(), 2);
+    }
+
+    #[test]
+    fn test_toggle_last_follows_windows_being_added_and_removed() {
+        let mut state = CycleState::new();
+        state.update_windows(vec![
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+        ]);
+        let wm = MockWindowManager::new();
+        state.switch_to(2, &wm, false, None).unwrap();
+
+        // A new client listed first shifts indices; the previous client is tracked by id
+        state.update_windows(vec![
+            create_test_window(50, "Zulu"),
+            create_test_window(100, "Alpha"),
+            create_test_window(200, "Beta"),
+        ]);
+        state.sync_with_active(200);
+        state.toggle_last(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![200, 100]);
+
+        // Closing the previous client leaves nothing to toggle to
+        state.update_windows(vec![
+            create_test_window(50, "Zulu"),
+            create_test_window(100, "Alpha"),
+        ]);
+        state.sync_with_active(100);
+        state.toggle_last(&wm, false).unwrap();
+        assert_eq!(wm.get_activated(), vec![200, 100]);
+    }
 }
@@ -1,23 +1,113 @@
-use crate::config::Config;
+use crate::activation_queue::ActivationQueue;
+use crate::client_settle::ClientSettler;
+use crate::config::{self, Config};
 use crate::cycle_state::CycleState;
 use crate::keyboard_listener::KeyboardListener;
 use crate::mouse_listener::MouseListener;
-use crate::window_manager::WindowManager;
-use anyhow::Result;
+use crate::paths;
+use crate::window_manager::{WindowGeometry, WindowManager};
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use notify::event::{MetadataKind, ModifyKind};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-const SOCKET_PATH: &str = "/tmp/nicotine.sock";
+/// How long `stop` waits for a daemon to exit before signalling it
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a client waits for the daemon to answer a command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a liveness check waits for `pong`
+const PING_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often the characters.txt watcher checks whether the daemon is stopping
+const CHARACTERS_WATCH_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Further events this soon after the first are part of the same save
+const CHARACTERS_SETTLE: Duration = Duration::from_millis(100);
+
+/// Whether a watcher event created, changed or removed `path`. Reads (and
+/// the access times they touch) are left out, or reloading the file would
+/// trigger another reload.
+fn changes_file(event: &notify::Result<notify::Event>, path: &Path) -> bool {
+    event.as_ref().is_ok_and(|event| {
+        let read = matches!(
+            event.kind,
+            EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime))
+        );
+        (event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove())
+            && !read
+            && event.paths.iter().any(|changed| changed == path)
+    })
+}
+
+/// What an input listener is bound to, as reported by `input-status`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListenerStatus {
+    pub enabled: bool,
+    pub device_path: Option<String>,
+    pub device_name: Option<String>,
+    pub error: Option<String>,
+    pub forward_code: u16,
+    pub backward_code: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputStatus {
+    pub mouse: ListenerStatus,
+    pub keyboard: ListenerStatus,
+    pub modifier_key: Option<u16>,
+}
+
+/// Snapshot of the daemon's state, as reported by `status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub window_count: usize,
+    pub active_index: usize,
+    pub titles: Vec<String>,
+    pub mouse_listener: bool,
+    pub keyboard_listener: bool,
+}
 
 #[derive(Debug)]
 pub enum Command {
     Forward,
     Backward,
     Switch(usize),
+    /// Switch to the client whose title matches a character name
+    SwitchName(String),
+    NextAttention,
+    /// Go back to the previously used client
+    Toggle,
+    /// Send an X11 keycode to every client
+    Broadcast(String),
+    /// Raise every client above other windows
+    Raise,
+    /// Lower every client below other windows
+    Lower,
+    /// Arrange every client in the configured layout
+    Stack,
+    /// Switch to the client under the mouse pointer
+    ActivateUnderCursor,
+    InputStatus,
+    Status,
+    /// Answer `pong`, to check the daemon is alive
+    Ping,
     Refresh,
+    ReloadCharacters,
+    ReloadConfig,
     Quit,
 }
 
@@ -27,7 +117,18 @@ impl Command {
         match s {
             "forward" => Some(Command::Forward),
             "backward" => Some(Command::Backward),
+            "next-attention" => Some(Command::NextAttention),
+            "toggle" => Some(Command::Toggle),
+            "raise" => Some(Command::Raise),
+            "lower" => Some(Command::Lower),
+            "stack" => Some(Command::Stack),
+            "activate-under-cursor" => Some(Command::ActivateUnderCursor),
+            "input-status" => Some(Command::InputStatus),
+            "status" => Some(Command::Status),
+            "ping" => Some(Command::Ping),
             "refresh" => Some(Command::Refresh),
+            "reload-characters" => Some(Command::ReloadCharacters),
+            "reload-config" => Some(Command::ReloadConfig),
             "quit" => Some(Command::Quit),
             _ => {
                 // Check for switch:N format
@@ -36,82 +137,182 @@ impl Command {
                         return Some(Command::Switch(num));
                     }
                 }
+                if let Some(name) = s.strip_prefix("switch-name:") {
+                    if !name.is_empty() {
+                        return Some(Command::SwitchName(name.to_string()));
+                    }
+                }
+                if let Some(key) = s.strip_prefix("broadcast:") {
+                    if !key.is_empty() {
+                        return Some(Command::Broadcast(key.to_string()));
+                    }
+                }
                 None
             }
         }
     }
+
+    /// Commands that can disrupt running clients. When a confirmation token is
+    /// configured they're only accepted as `verb:TOKEN`.
+    pub fn is_destructive(&self) -> bool {
+        matches!(self, Command::Quit | Command::Broadcast(_))
+    }
+
+    /// Parse a command line, splitting the `:TOKEN` suffix off destructive commands
+    pub fn parse_with_token(s: &str) -> Option<(Self, Option<String>)> {
+        let s = s.trim();
+        if let Some((verb, token)) = s.rsplit_once(':') {
+            if let Some(command) = Self::from_str(verb).filter(Self::is_destructive) {
+                return Some((command, Some(token.to_string())));
+            }
+        }
+
+        Self::from_str(s).map(|command| (command, None))
+    }
+}
+
+/// Add the configured confirmation token to a destructive command line
+pub fn with_confirmation_token(command: &str, config: &Config) -> String {
+    match &config.require_confirmation_token {
+        Some(token) => format!("{}:{}", command, token),
+        None => command.to_string(),
+    }
 }
 
 pub struct Daemon {
     wm: Arc<dyn WindowManager>,
     state: Arc<Mutex<CycleState>>,
-    config: Config,
-    character_order: Option<Vec<String>>,
+    settler: Arc<Mutex<ClientSettler>>,
+    config: Arc<RwLock<Config>>,
+    /// Profile the config came from, so reloads read the same file
+    profile: Option<String>,
+    character_order: Arc<Mutex<Option<Vec<String>>>>,
+    /// Characters with their own geometry in characters.txt, read with the order
+    character_geometry: Arc<Mutex<Vec<(String, WindowGeometry)>>>,
+    mouse_status: Arc<Mutex<ListenerStatus>>,
+    keyboard_status: Arc<Mutex<ListenerStatus>>,
+    /// Set by Quit; the accept loop and background threads stop when they see it
+    shutdown: Arc<AtomicBool>,
+    /// Told once the socket is bound and commands can be sent
+    ready: Option<mpsc::Sender<()>>,
 }
 
 impl Daemon {
-    pub fn new(wm: Arc<dyn WindowManager>, config: Config) -> Self {
-        let state = Arc::new(Mutex::new(CycleState::new()));
+    pub fn new(wm: Arc<dyn WindowManager>, config: Config, profile: Option<String>) -> Self {
+        // Cycle presses can arrive faster than the WM applies them; only the
+        // latest activation is kept so focus doesn't overshoot after a burst
+        let wm: Arc<dyn WindowManager> = Arc::new(ActivationQueue::new(wm));
+        let state = Arc::new(Mutex::new(CycleState::from_config(&config)));
+        let mut settler = ClientSettler::new(config.client_settle_ms);
+
+        // Load character order for targeted cycling and the cycle order
+        let entries = Config::load_character_entries();
+        let character_order = entries.as_deref().map(config::character_order);
+        let character_geometry = Arc::new(Mutex::new(config::character_geometry(
+            entries.as_deref().unwrap_or_default(),
+        )));
+        if character_order.is_some() {
+            info!("Loaded character order from characters.txt");
+        }
+        state
+            .lock()
+            .unwrap()
+            .set_character_order(character_order.as_deref());
+        let character_order = Arc::new(Mutex::new(character_order));
 
-        // Initialize windows
+        // Initialize windows; clients already open don't need to settle
         if let Ok(windows) = wm.get_eve_windows() {
+            let windows = settler.seed(windows, Instant::now());
             state.lock().unwrap().update_windows(windows);
         }
+        let settler = Arc::new(Mutex::new(settler));
 
-        // Load character order for targeted cycling
-        let character_order = Config::load_characters();
-        if character_order.is_some() {
-            println!("Loaded character order from characters.txt");
-        }
+        let mouse_status = Arc::new(Mutex::new(ListenerStatus {
+            enabled: config.enable_mouse_buttons,
+            forward_code: config.forward_button,
+            backward_code: config.backward_button,
+            ..Default::default()
+        }));
+        let keyboard_status = Arc::new(Mutex::new(ListenerStatus {
+            enabled: config.enable_keyboard_buttons,
+            forward_code: config.forward_key,
+            backward_code: config.backward_key,
+            ..Default::default()
+        }));
 
         Self {
             wm,
             state,
-            config,
+            settler,
+            config: Arc::new(RwLock::new(config)),
+            profile,
             character_order,
+            character_geometry,
+            mouse_status,
+            keyboard_status,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            ready: None,
         }
     }
 
+    /// Send on `ready` once the daemon is accepting commands. If it fails to
+    /// start, the sender is dropped instead.
+    pub fn notify_ready(&mut self, ready: mpsc::Sender<()>) {
+        self.ready = Some(ready);
+    }
+
     pub fn run(&mut self) -> Result<()> {
+        let config = self.config.read().unwrap().clone();
+
         // Remove old socket if it exists
-        let _ = fs::remove_file(SOCKET_PATH);
+        let socket_path = paths::socket_path()?;
+        let _ = fs::remove_file(&socket_path);
+
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("Failed to bind {}", socket_path.display()))?;
+        info!("EVE Multibox daemon listening on {}", socket_path.display());
 
-        let listener = UnixListener::bind(SOCKET_PATH)?;
-        println!("EVE Multibox daemon listening on {}", SOCKET_PATH);
+        // Written before reporting ready, so a `stop` or `reload` sent as soon
+        // as `start` returns can fall back to signalling this pid
+        let pid_file = paths::pid_file()?;
+        if let Err(e) = fs::write(&pid_file, std::process::id().to_string()) {
+            warn!("Failed to write {}: {}", pid_file.display(), e);
+        }
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(());
+        }
 
         // Start mouse event listener if enabled
-        if self.config.enable_mouse_buttons {
-            let mouse_listener = MouseListener::new(self.config.clone());
+        if config.enable_mouse_buttons {
+            let mouse_listener = MouseListener::new(Arc::clone(&self.config));
             let wm_clone = Arc::clone(&self.wm);
             let state_clone = Arc::clone(&self.state);
 
-            match mouse_listener.spawn(wm_clone, state_clone) {
-                Ok(_) => println!("Mouse button listener started"),
+            let status_clone = Arc::clone(&self.mouse_status);
+
+            match mouse_listener.spawn(wm_clone, state_clone, status_clone) {
+                Ok(_) => info!("Mouse button listener started"),
                 Err(e) => {
-                    eprintln!("Warning: Could not start mouse listener: {}", e);
-                    eprintln!(
-                        "Mouse buttons will not work. You can disable this warning by setting"
-                    );
-                    eprintln!("'enable_mouse_buttons = false' in ~/.config/nicotine/config.toml");
+                    warn!("Could not start mouse listener: {}", e);
+                    warn!("Mouse buttons will not work. You can disable this warning by setting 'enable_mouse_buttons = false' in ~/.config/nicotine/config.toml");
                 }
             }
         }
 
-        if self.config.enable_keyboard_buttons {
-            let keyboard_listener = KeyboardListener::new(self.config.clone());
+        if config.enable_keyboard_buttons {
+            let keyboard_listener = KeyboardListener::new(Arc::clone(&self.config));
             let wm_clone = Arc::clone(&self.wm);
             let state_clone = Arc::clone(&self.state);
 
-            match keyboard_listener.spawn(wm_clone, state_clone) {
-                Ok(_) => println!("Keyboard key listener started"),
+            let status_clone = Arc::clone(&self.keyboard_status);
+
+            let order_clone = Arc::clone(&self.character_order);
+
+            match keyboard_listener.spawn(wm_clone, state_clone, status_clone, order_clone) {
+                Ok(_) => info!("Keyboard key listener started"),
                 Err(e) => {
-                    eprintln!("Warning: Could not start keyboard listener: {}", e);
-                    eprintln!(
-                        "Keyboard keys will not work.  You can disable this warning by setting"
-                    );
-                    eprintln!(
-                        "'enable_keyboard_buttons = false' in ~/.config/nicotine/config.toml"
-                    );
+                    warn!("Could not start keyboard listener: {}", e);
+                    warn!("Keyboard keys will not work. You can disable this warning by setting 'enable_keyboard_buttons = false' in ~/.config/nicotine/config.toml");
                 }
             }
         }
@@ -119,10 +320,79 @@ impl Daemon {
         // Refresh window list periodically in background
         let wm_clone = Arc::clone(&self.wm);
         let state_clone = Arc::clone(&self.state);
-        std::thread::spawn(move || loop {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            if let Ok(windows) = wm_clone.get_eve_windows() {
-                state_clone.lock().unwrap().update_windows(windows);
+        let settler_clone = Arc::clone(&self.settler);
+        let shutdown_clone = Arc::clone(&self.shutdown);
+        let refresh_interval = std::time::Duration::from_millis(config.refresh_interval_ms);
+        let refresh_thread = std::thread::spawn(move || {
+            while !shutdown_clone.load(Ordering::SeqCst) {
+                std::thread::sleep(refresh_interval);
+                if let Ok(windows) = wm_clone.get_eve_windows() {
+                    let windows = settler_clone
+                        .lock()
+                        .unwrap()
+                        .observe(windows, Instant::now());
+                    state_clone.lock().unwrap().update_windows(windows);
+                }
+            }
+        });
+
+        // SIGHUP reloads the config and SIGTERM/SIGINT shut down cleanly, like a
+        // `reload`/`stop`. Accept blocks, so wake it with an empty connection.
+        let mut signals =
+            Signals::new([SIGHUP, SIGTERM, SIGINT]).context("Failed to install signal handlers")?;
+        let signals_handle = signals.handle();
+        let reload_signalled = Arc::new(AtomicBool::new(false));
+        let reload_clone = Arc::clone(&reload_signalled);
+        let shutdown_clone = Arc::clone(&self.shutdown);
+        let wake_path = socket_path.clone();
+        let signal_thread = std::thread::spawn(move || {
+            for signum in signals.forever() {
+                if signum == SIGHUP {
+                    reload_clone.store(true, Ordering::SeqCst);
+                } else {
+                    shutdown_clone.store(true, Ordering::SeqCst);
+                }
+                let _ = UnixStream::connect(&wake_path);
+            }
+        });
+
+        // Pick up edits to characters.txt without a restart
+        let order_clone = Arc::clone(&self.character_order);
+        let geometry_clone = Arc::clone(&self.character_geometry);
+        let state_clone = Arc::clone(&self.state);
+        let shutdown_clone = Arc::clone(&self.shutdown);
+        let characters_thread = std::thread::spawn(move || {
+            let path = Config::characters_path();
+            let (tx, rx) = mpsc::channel();
+            // Watch the directory: the file may not exist yet, and editors
+            // often save by replacing it
+            let dir = Config::config_dir();
+            let _ = fs::create_dir_all(&dir);
+            let _watcher = match notify::recommended_watcher(tx).and_then(|mut watcher| {
+                watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .map(|()| watcher)
+            }) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    warn!(
+                        "Can't watch {} ({}); run `nicotine characters reload` after editing it",
+                        path.display(),
+                        e
+                    );
+                    return;
+                }
+            };
+
+            while !shutdown_clone.load(Ordering::SeqCst) {
+                match rx.recv_timeout(CHARACTERS_WATCH_TIMEOUT) {
+                    Ok(event) if changes_file(&event, &path) => {
+                        while rx.recv_timeout(CHARACTERS_SETTLE).is_ok() {}
+                        Self::reload_characters(&order_clone, &geometry_clone, &state_clone);
+                    }
+                    Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
             }
         });
 
@@ -130,46 +400,244 @@ impl Daemon {
             match stream {
                 Ok(stream) => {
                     if let Err(e) = self.handle_client(stream) {
-                        eprintln!("Error handling client: {}", e);
+                        error!("Error handling client: {}", e);
                     }
                 }
                 Err(e) => {
-                    eprintln!("Connection error: {}", e);
+                    warn!("Connection error: {}", e);
                 }
             }
+
+            if reload_signalled.swap(false, Ordering::SeqCst) {
+                info!("Received SIGHUP");
+                if let Err(e) = self.reload_config() {
+                    warn!("Failed to reload config: {}", e);
+                }
+            }
+
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+
+        if self.config.read().unwrap().dim_inactive_opacity.is_some() {
+            if let Err(e) = self.wm.restore_opacity() {
+                warn!("Failed to restore client opacity: {}", e);
+            }
         }
 
+        // The input listeners block on their devices and end with the process
+        let _ = fs::remove_file(&socket_path);
+        let _ = fs::remove_file(&pid_file);
+        let _ = refresh_thread.join();
+        let _ = characters_thread.join();
+        signals_handle.close();
+        let _ = signal_thread.join();
+        info!("Daemon stopped");
+
         Ok(())
     }
 
+    /// Swap in a freshly loaded config. Listener bindings and cycling options
+    /// apply right away; devices, enabling listeners and intervals need a restart.
+    fn reload_config(&self) -> Result<()> {
+        let config = Config::load(self.profile.as_deref()).context("Failed to reload config")?;
+
+        self.state.lock().unwrap().apply_config(&config);
+        {
+            let mut status = self.mouse_status.lock().unwrap();
+            status.forward_code = config.forward_button;
+            status.backward_code = config.backward_button;
+        }
+        {
+            let mut status = self.keyboard_status.lock().unwrap();
+            status.forward_code = config.forward_key;
+            status.backward_code = config.backward_key;
+        }
+
+        let mut current = self.config.write().unwrap();
+        if current.dim_inactive_opacity.is_some() && config.dim_inactive_opacity.is_none() {
+            if let Err(e) = self.wm.restore_opacity() {
+                warn!("Failed to restore client opacity: {}", e);
+            }
+        }
+        *current = config;
+        info!("Reloaded config");
+        Ok(())
+    }
+
+    fn reload_characters(
+        character_order: &Mutex<Option<Vec<String>>>,
+        character_geometry: &Mutex<Vec<(String, WindowGeometry)>>,
+        state: &Mutex<CycleState>,
+    ) {
+        let entries = Config::load_character_entries();
+        *character_geometry.lock().unwrap() =
+            config::character_geometry(entries.as_deref().unwrap_or_default());
+        let characters = entries.as_deref().map(config::character_order);
+        match &characters {
+            Some(names) => info!("Reloaded character order ({} characters)", names.len()),
+            None => info!("characters.txt removed, sorting clients by title"),
+        }
+        // Takes effect on the next window refresh
+        state
+            .lock()
+            .unwrap()
+            .set_character_order(characters.as_deref());
+        *character_order.lock().unwrap() = characters;
+    }
+
+    /// Replay a key press into every client, then give focus back to the
+    /// window that had it
+    fn broadcast_key(&self, key: &str) -> Result<()> {
+        let keycode: u8 = key
+            .parse()
+            .with_context(|| format!("Broadcast expects an X11 keycode, got '{}'", key))?;
+
+        let original = self.wm.get_active_window().ok();
+        let windows = self.wm.get_eve_windows()?;
+        for window in &windows {
+            if let Err(e) = self.wm.send_key_to_window(window.id, keycode) {
+                warn!("Failed to broadcast to '{}': {}", window.title, e);
+            }
+        }
+
+        if let Some(original) = original {
+            self.wm.activate_window(original)?;
+        }
+        Ok(())
+    }
+
+    fn token_accepted(config: &Config, token: Option<&str>) -> bool {
+        match &config.require_confirmation_token {
+            Some(expected) => token == Some(expected.as_str()),
+            None => true,
+        }
+    }
+
+    /// Read one command line and answer `OK\n` followed by any payload (the
+    /// JSON for `status`/`input-status`), or `ERR <message>\n` if it failed
     fn handle_client(&mut self, stream: UnixStream) -> Result<()> {
         let mut reader = BufReader::new(&stream);
         let mut line = String::new();
         reader.read_line(&mut line)?;
 
-        if let Some(command) = Command::from_str(&line) {
-            match command {
-                Command::Forward => {
-                    let mut state = self.state.lock().unwrap();
+        let result = self.run_command(&line);
+        let reply = match &result {
+            Ok(payload) => format!("OK\n{}", payload),
+            Err(e) => format!("ERR {}\n", format!("{:#}", e).replace('\n', " ")),
+        };
+        // The client may not wait for the reply, e.g. a fire-and-forget `quit`
+        let _ = (&stream).write_all(reply.as_bytes());
+        result.map(drop)
+    }
 
-                    // Sync with active window first
-                    if let Ok(active) = self.wm.get_active_window() {
-                        state.sync_with_active(active);
-                    }
+    fn run_command(&mut self, line: &str) -> Result<String> {
+        // Snapshot so a reload can't change settings halfway through a command
+        let config = self.config.read().unwrap().clone();
+        let mut payload = String::new();
 
-                    state.cycle_forward(&*self.wm, self.config.minimize_inactive)?;
+        let Some((command, token)) = Command::parse_with_token(line) else {
+            anyhow::bail!("Unknown command '{}'", line.trim());
+        };
+        if command.is_destructive() && !Self::token_accepted(&config, token.as_deref()) {
+            anyhow::bail!(
+                "Rejected {:?}: missing or wrong confirmation token",
+                command
+            );
+        }
+
+        match command {
+            Command::Forward => {
+                let mut state = self.state.lock().unwrap();
+
+                // Sync with active window first
+                if let Ok(active) = self.wm.get_active_window() {
+                    state.sync_with_active(active);
                 }
-                Command::Backward => {
-                    let mut state = self.state.lock().unwrap();
 
-                    // Sync with active window first
-                    if let Ok(active) = self.wm.get_active_window() {
-                        state.sync_with_active(active);
-                    }
+                state.cycle_forward(&*self.wm, config.minimize_inactive)?;
+            }
+            Command::Backward => {
+                let mut state = self.state.lock().unwrap();
+
+                // Sync with active window first
+                if let Ok(active) = self.wm.get_active_window() {
+                    state.sync_with_active(active);
+                }
+
+                state.cycle_backward(&*self.wm, config.minimize_inactive)?;
+            }
+            Command::Switch(target) => {
+                let mut state = self.state.lock().unwrap();
+
+                // Sync with active window first
+                if let Ok(active) = self.wm.get_active_window() {
+                    state.sync_with_active(active);
+                }
+
+                state.switch_to(
+                    target,
+                    &*self.wm,
+                    config.minimize_inactive,
+                    self.character_order.lock().unwrap().as_deref(),
+                )?;
+            }
+            Command::SwitchName(name) => {
+                let mut state = self.state.lock().unwrap();
+
+                // Sync with active window first
+                if let Ok(active) = self.wm.get_active_window() {
+                    state.sync_with_active(active);
+                }
 
-                    state.cycle_backward(&*self.wm, self.config.minimize_inactive)?;
+                state.switch_to_name(&name, &*self.wm, config.minimize_inactive)?;
+            }
+            Command::NextAttention => {
+                let mut state = self.state.lock().unwrap();
+
+                // Sync with active window first
+                if let Ok(active) = self.wm.get_active_window() {
+                    state.sync_with_active(active);
+                }
+
+                state.next_attention(&*self.wm, config.minimize_inactive)?;
+            }
+            Command::Toggle => {
+                let mut state = self.state.lock().unwrap();
+
+                // Sync with active window first
+                if let Ok(active) = self.wm.get_active_window() {
+                    state.sync_with_active(active);
+                }
+
+                state.toggle_last(&*self.wm, config.minimize_inactive)?;
+            }
+            Command::Broadcast(key) => {
+                if !config.enable_broadcast {
+                    anyhow::bail!("Ignoring broadcast: set 'enable_broadcast = true' to allow it");
+                }
+                self.broadcast_key(&key)?;
+            }
+            Command::Raise => {
+                let windows = self.state.lock().unwrap().get_windows().to_vec();
+                self.wm.raise_all(&windows)?;
+            }
+            Command::Lower => {
+                let windows = self.state.lock().unwrap().get_windows().to_vec();
+                self.wm.lower_all(&windows)?;
+            }
+            Command::Stack => {
+                if !self.wm.supports_positioning() {
+                    anyhow::bail!("Positioning unsupported on this compositor");
                 }
-                Command::Switch(target) => {
+                let windows = self.state.lock().unwrap().get_windows().to_vec();
+                let overrides = self.character_geometry.lock().unwrap().clone();
+                self.wm
+                    .layout_windows(&windows, &config, config.layout_mode, &overrides)?;
+            }
+            Command::ActivateUnderCursor => {
+                if let Some(window_id) = self.wm.window_under_cursor()? {
                     let mut state = self.state.lock().unwrap();
 
                     // Sync with active window first
@@ -177,34 +645,313 @@ impl Daemon {
                         state.sync_with_active(active);
                     }
 
-                    state.switch_to(
-                        target,
-                        &*self.wm,
-                        self.config.minimize_inactive,
-                        self.character_order.as_deref(),
-                    )?;
-                }
-                Command::Refresh => {
-                    let windows = self.wm.get_eve_windows()?;
-                    self.state.lock().unwrap().update_windows(windows);
-                }
-                Command::Quit => {
-                    std::process::exit(0);
+                    state.switch_to_window(window_id, &*self.wm, config.minimize_inactive)?;
                 }
             }
+            Command::InputStatus => {
+                let status = InputStatus {
+                    mouse: self.mouse_status.lock().unwrap().clone(),
+                    keyboard: self.keyboard_status.lock().unwrap().clone(),
+                    modifier_key: config.modifier_key,
+                };
+                payload = format!("{}\n", serde_json::to_string(&status)?);
+            }
+            Command::Status => {
+                let status = {
+                    let state = self.state.lock().unwrap();
+                    DaemonStatus {
+                        window_count: state.get_windows().len(),
+                        active_index: state.get_current_index(),
+                        titles: state
+                            .get_windows()
+                            .iter()
+                            .map(|w| w.title.clone())
+                            .collect(),
+                        // A listener that found its device is running
+                        mouse_listener: self.mouse_status.lock().unwrap().device_path.is_some(),
+                        keyboard_listener: self
+                            .keyboard_status
+                            .lock()
+                            .unwrap()
+                            .device_path
+                            .is_some(),
+                    }
+                };
+                payload = format!("{}\n", serde_json::to_string(&status)?);
+            }
+            Command::Ping => {
+                payload = "pong\n".to_string();
+            }
+            Command::Refresh => {
+                let windows = self.wm.get_eve_windows()?;
+                let windows = self
+                    .settler
+                    .lock()
+                    .unwrap()
+                    .observe(windows, Instant::now());
+                self.state.lock().unwrap().update_windows(windows);
+            }
+            Command::ReloadCharacters => {
+                Self::reload_characters(
+                    &self.character_order,
+                    &self.character_geometry,
+                    &self.state,
+                );
+            }
+            Command::ReloadConfig => {
+                self.reload_config()?;
+            }
+            Command::Quit => {
+                info!("Shutting down");
+                self.shutdown.store(true, Ordering::SeqCst);
+            }
         }
 
-        Ok(())
+        Ok(payload)
     }
 }
 
-pub fn send_command(command: &str) -> Result<()> {
-    if !Path::new(SOCKET_PATH).exists() {
-        anyhow::bail!("Daemon not running. Start with: eve-multibox daemon");
+/// The daemon received a command but couldn't carry it out (an `ERR` reply)
+#[derive(Debug)]
+pub struct CommandFailed(pub String);
+
+impl std::fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Daemon: {}", self.0)
+    }
+}
+
+impl std::error::Error for CommandFailed {}
+
+/// Nobody is listening on the daemon socket, so a command was never sent
+#[derive(Debug)]
+pub struct NotRunning(&'static str);
+
+impl std::fmt::Display for NotRunning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for NotRunning {}
+
+/// Connect to the daemon's socket. A socket file nobody listens on is left
+/// behind by a crashed daemon, so it's removed.
+fn connect() -> Result<UnixStream> {
+    let socket_path = paths::socket_path()?;
+    match UnixStream::connect(&socket_path) {
+        Ok(stream) => Ok(stream),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(NotRunning("Daemon not running. Start with: nicotine start").into())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            let _ = fs::remove_file(&socket_path);
+            Err(NotRunning(
+                "Daemon not running (removed its stale socket). Start with: nicotine start",
+            )
+            .into())
+        }
+        Err(e) => Err(e).context("Failed to connect to daemon"),
     }
+}
 
-    let mut stream = UnixStream::connect(SOCKET_PATH)?;
+/// Send a command and wait for the daemon to answer it
+fn request(command: &str) -> Result<String> {
+    request_within(command, REPLY_TIMEOUT)
+}
+
+fn request_within(command: &str, timeout: Duration) -> Result<String> {
+    let mut stream = connect()?;
+    stream.set_read_timeout(Some(timeout))?;
     writeln!(stream, "{}", command)?;
     stream.flush()?;
-    Ok(())
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).with_context(|| {
+        format!(
+            "Daemon didn't answer within {}s (it may still be running the command)",
+            timeout.as_secs_f32()
+        )
+    })?;
+    parse_reply(&reply)
+}
+
+/// The payload of an `OK` reply, or the message of an `ERR` one as CommandFailed
+fn parse_reply(reply: &str) -> Result<String> {
+    if let Some(message) = reply.strip_prefix("ERR ") {
+        return Err(CommandFailed(message.trim_end().to_string()).into());
+    }
+    match reply.strip_prefix("OK\n") {
+        Some(payload) => Ok(payload.to_string()),
+        // A daemon from before replies existed sends nothing, or only the payload
+        None => Ok(reply.to_string()),
+    }
+}
+
+/// Run a command in the daemon. Fails with NotRunning if there's no daemon to
+/// ask, CommandFailed if the daemon ran it and it went wrong, or another error
+/// if the daemon was reached but didn't answer properly.
+pub fn send_command(command: &str) -> Result<()> {
+    request(command).map(drop)
+}
+
+/// Whether a daemon is listening and answering, rather than just whether its
+/// socket file exists
+pub fn is_alive() -> bool {
+    request_within("ping", PING_TIMEOUT).is_ok_and(|reply| reply.trim() == "pong")
+}
+
+/// PID of the running daemon from its pidfile, if that process is still a nicotine
+fn daemon_pid() -> Option<Pid> {
+    let pid: i32 = fs::read_to_string(paths::pid_file().ok()?)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    // Don't trust a stale pidfile whose PID has been reused by something else
+    let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    let exe = std::env::current_exe().ok()?;
+    let exe_name = exe.file_name()?.to_string_lossy();
+    // The kernel truncates comm to 15 bytes
+    let expected: String = exe_name.chars().take(15).collect();
+    (comm.trim() == expected).then_some(Pid::from_raw(pid))
+}
+
+fn wait_for_exit(pid: Option<Pid>, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        let running = match pid {
+            Some(pid) => kill(pid, None).is_ok(),
+            None => is_alive(),
+        };
+        if !running {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+/// Stop a running daemon: ask it to quit over the socket, and only signal the
+/// PID from the pidfile if it doesn't exit in time. Returns false if no daemon
+/// was running.
+pub fn stop_daemon(config: &Config) -> Result<bool> {
+    let pid = daemon_pid();
+    let asked = send_command(&with_confirmation_token("quit", config)).is_ok();
+
+    if !asked && pid.is_none() {
+        return Ok(false);
+    }
+
+    if !(asked && wait_for_exit(pid, STOP_TIMEOUT)) {
+        if let Some(pid) = pid {
+            eprintln!("Daemon didn't respond, sending SIGTERM to {}", pid);
+            let _ = kill(pid, Signal::SIGTERM);
+            if !wait_for_exit(Some(pid), STOP_TIMEOUT) {
+                kill(pid, Signal::SIGKILL).context("Failed to kill daemon")?;
+            }
+        }
+    }
+
+    fs::remove_file(paths::socket_path()?).ok();
+    fs::remove_file(paths::pid_file()?).ok();
+    Ok(true)
+}
+
+/// Send a command and return the payload of the daemon's reply
+pub fn send_command_with_reply(command: &str) -> Result<String> {
+    request(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, AccessMode, CreateKind};
+    use notify::Event;
+
+    #[test]
+    fn test_changes_file_ignores_reads_and_other_files() {
+        let path = Path::new("/home/pilot/.config/nicotine/characters.txt");
+        let event = |kind, changed: &str| Ok(Event::new(kind).add_path(changed.into()));
+
+        assert!(changes_file(
+            &event(EventKind::Modify(ModifyKind::Any), path.to_str().unwrap()),
+            path
+        ));
+        assert!(changes_file(
+            &event(EventKind::Create(CreateKind::File), path.to_str().unwrap()),
+            path
+        ));
+        assert!(!changes_file(
+            &event(
+                EventKind::Access(AccessKind::Close(AccessMode::Read)),
+                path.to_str().unwrap()
+            ),
+            path
+        ));
+        assert!(!changes_file(
+            &event(
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime)),
+                path.to_str().unwrap()
+            ),
+            path
+        ));
+        assert!(!changes_file(
+            &event(
+                EventKind::Modify(ModifyKind::Any),
+                "/home/pilot/.config/nicotine/config.toml"
+            ),
+            path
+        ));
+    }
+
+    #[test]
+    fn test_parse_with_token_splits_destructive_commands() {
+        let (command, token) = Command::parse_with_token("quit:secret\n").unwrap();
+        assert!(matches!(command, Command::Quit));
+        assert_eq!(token.as_deref(), Some("secret"));
+
+        let (command, token) = Command::parse_with_token("quit").unwrap();
+        assert!(matches!(command, Command::Quit));
+        assert_eq!(token, None);
+    }
+
+    #[test]
+    fn test_parse_with_token_leaves_other_commands_alone() {
+        let (command, token) = Command::parse_with_token("switch:3").unwrap();
+        assert!(matches!(command, Command::Switch(3)));
+        assert_eq!(token, None);
+
+        assert!(Command::parse_with_token("forward:secret").is_none());
+    }
+
+    #[test]
+    fn test_parse_reply_ok_and_err() {
+        assert_eq!(parse_reply("OK\n").unwrap(), "");
+        assert_eq!(
+            parse_reply("OK\n{\"window_count\":2}\n").unwrap(),
+            "{\"window_count\":2}\n"
+        );
+
+        let err = parse_reply("ERR No active window\n").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CommandFailed>().map(|e| e.0.as_str()),
+            Some("No active window")
+        );
+
+        // Daemons from before replies existed answer nothing
+        assert_eq!(parse_reply("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_parse_with_token_broadcast() {
+        let (command, token) = Command::parse_with_token("broadcast:67:secret").unwrap();
+        assert!(matches!(command, Command::Broadcast(ref key) if key == "67"));
+        assert_eq!(token.as_deref(), Some("secret"));
+
+        let (command, token) = Command::parse_with_token("broadcast:67").unwrap();
+        assert!(matches!(command, Command::Broadcast(ref key) if key == "67"));
+        assert_eq!(token, None);
+    }
 }
@@ -0,0 +1,234 @@
+use crate::config::{Config, X11ActivationMethod};
+use crate::input_access;
+use crate::paths;
+use crate::window_manager::{
+    backend_name, detect_display_server, detect_wayland_compositor, DisplayServer,
+    WaylandCompositor, WindowManager,
+};
+use anyhow::Result;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn report(check: &str, passed: bool, hint: &str) -> bool {
+    if passed {
+        println!("  ✓ {}", check);
+    } else {
+        println!("  ✗ {}", check);
+        println!("      {}", hint);
+    }
+    passed
+}
+
+/// First `tool` executable found in a PATH-style list of directories
+fn find_in_path(tool: &str, path: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(path)
+        .map(|dir| dir.join(tool))
+        .find(|candidate| candidate.is_file())
+}
+
+/// External programs (and why) the backend for this session shells out to
+fn required_tools(config: &Config) -> Vec<(&'static str, &'static str)> {
+    match detect_display_server() {
+        DisplayServer::X11 if config.x11_activation_method == X11ActivationMethod::Wmctrl => {
+            vec![("wmctrl", "x11_activation_method = \"wmctrl\"")]
+        }
+        DisplayServer::X11 => Vec::new(),
+        DisplayServer::Wayland => match detect_wayland_compositor() {
+            WaylandCompositor::Kde => vec![
+                ("wmctrl", "moving clients"),
+                ("xdotool", "minimizing clients"),
+            ],
+            WaylandCompositor::Gnome => vec![("gdbus", "talking to the GNOME Shell extension")],
+            _ => Vec::new(),
+        },
+    }
+}
+
+/// Check everything nicotine depends on and say how to fix what's missing.
+/// The config is loaded here so a broken one is reported like any other
+/// check; `connect` creates the backend for it. Returns Ok(true) if every
+/// check passed.
+pub fn run(
+    profile: Option<&str>,
+    connect: impl FnOnce(&Config) -> Result<Arc<dyn WindowManager>>,
+) -> Result<bool> {
+    println!("Checking the environment...");
+    let mut healthy = true;
+
+    let config = match Config::load(profile) {
+        Ok(config) => {
+            report("Config loaded", true, "");
+            config
+        }
+        Err(e) => {
+            healthy &= report(
+                &format!("Loading the config: {:#}", e),
+                false,
+                "Fix the file, or move it aside to have a fresh one generated",
+            );
+            // Carry on with the defaults so the other checks still run
+            Config::for_display(1920, 1080)
+        }
+    };
+    let config = &config;
+    let wm = connect(config);
+
+    let server = detect_display_server();
+    let detected = match server {
+        DisplayServer::X11 => "X11".to_string(),
+        DisplayServer::Wayland => {
+            format!("Wayland ({:?} compositor)", detect_wayland_compositor())
+        }
+    };
+    report(
+        &format!(
+            "Display server: {}, using the {} backend",
+            detected,
+            backend_name()
+        ),
+        true,
+        "",
+    );
+
+    match &wm {
+        Ok(_) => {
+            report("Connected to the window manager", true, "");
+        }
+        Err(e) => {
+            healthy &= report(
+                &format!("Connected to the window manager: {:#}", e),
+                false,
+                "Run nicotine from inside your desktop session (DISPLAY / WAYLAND_DISPLAY must be set)",
+            );
+        }
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    for (tool, purpose) in required_tools(config) {
+        healthy &= report(
+            &format!("{} is installed (for {})", tool, purpose),
+            find_in_path(tool, &path).is_some(),
+            &format!("Install the {} package", tool),
+        );
+    }
+
+    if server == DisplayServer::Wayland {
+        let needs_env = match detect_wayland_compositor() {
+            WaylandCompositor::Sway => Some("SWAYSOCK"),
+            WaylandCompositor::Hyprland => Some("HYPRLAND_INSTANCE_SIGNATURE"),
+            _ => None,
+        };
+        if let Some(var) = needs_env {
+            healthy &= report(
+                &format!("{} is set", var),
+                std::env::var_os(var).is_some(),
+                "Run nicotine from a terminal or keybind inside the compositor session",
+            );
+        }
+    }
+
+    // Input devices only matter when nicotine reads buttons or keys itself
+    if config.enable_mouse_buttons || config.enable_keyboard_buttons {
+        let scan = input_access::scan_event_devices();
+        healthy &= report(
+            &format!(
+                "Input devices readable ({} of {} /dev/input/event* opened)",
+                scan.opened(),
+                scan.opened() + scan.denied()
+            ),
+            scan.opened() > 0,
+            &if scan.denied() == 0 {
+                "No input devices found - is /dev/input available in this session?".to_string()
+            } else {
+                input_access::permission_hint()
+            },
+        );
+        if let Some(member) = input_access::in_input_group() {
+            healthy &= report(
+                &format!("In the '{}' group", input_access::input_group()),
+                member || scan.denied() == 0,
+                &input_access::permission_hint(),
+            );
+        }
+    }
+
+    let runtime = match paths::runtime_dir() {
+        Ok(runtime) => {
+            healthy &= report(
+                &format!("Runtime directory {} is writable", runtime.display()),
+                is_writable(&runtime),
+                "Check XDG_RUNTIME_DIR points at a directory you own",
+            );
+            Some(runtime)
+        }
+        Err(e) => {
+            healthy &= report(
+                &format!("Runtime directory: {:#}", e),
+                false,
+                "Set XDG_RUNTIME_DIR, or remove the directory so nicotine can recreate it",
+            );
+            None
+        }
+    };
+    if let Some(parent) = paths::socket_path().ok().as_deref().and_then(Path::parent) {
+        if Some(parent) != runtime.as_deref() {
+            healthy &= report(
+                &format!("Socket directory {} is writable", parent.display()),
+                is_writable(parent),
+                "Pick a --socket path in a directory you can write to",
+            );
+        }
+    }
+
+    if let Ok(wm) = &wm {
+        match wm.get_eve_windows() {
+            Ok(windows) => {
+                report(&format!("{} EVE clients detected", windows.len()), true, "");
+                if windows.is_empty() {
+                    println!("      Start a client, or adjust [window_match] if yours is running");
+                }
+            }
+            Err(e) => {
+                healthy &= report(
+                    &format!("Listing clients: {:#}", e),
+                    false,
+                    "See `nicotine logs` and the Wayland Support section of the README",
+                );
+            }
+        }
+    }
+
+    println!();
+    if healthy {
+        println!("Everything looks fine");
+    } else {
+        println!("Some checks failed; fix them and run `nicotine doctor` again");
+    }
+    Ok(healthy)
+}
+
+/// Whether a file can be created in `dir`
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".nicotine-doctor-{}", std::process::id()));
+    let created = std::fs::File::create(&probe).is_ok();
+    let _ = std::fs::remove_file(&probe);
+    created
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_in_path_searches_each_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("wmctrl"), "").unwrap();
+
+        let path = std::env::join_paths([dir.path().join("missing"), bin.clone()]).unwrap();
+        assert_eq!(find_in_path("wmctrl", &path), Some(bin.join("wmctrl")));
+        assert_eq!(find_in_path("xdotool", &path), None);
+    }
+}
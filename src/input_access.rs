@@ -0,0 +1,155 @@
+use nix::unistd::{getgroups, Gid, Group};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Group that owns input devices on most distributions
+const DEFAULT_INPUT_GROUP: &str = "input";
+
+/// Keeps count of why devices couldn't be opened while scanning /dev/input,
+/// so "no matching device" can be told apart from "not allowed to look"
+#[derive(Debug, Default)]
+pub struct DeviceScan {
+    opened: usize,
+    denied: usize,
+}
+
+impl DeviceScan {
+    pub fn record<T>(&mut self, result: &io::Result<T>) {
+        match result {
+            Ok(_) => self.opened += 1,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => self.denied += 1,
+            Err(_) => {}
+        }
+    }
+
+    pub fn opened(&self) -> usize {
+        self.opened
+    }
+
+    pub fn denied(&self) -> usize {
+        self.denied
+    }
+
+    /// The error to report when no usable device was found
+    pub fn no_device_error(&self, not_found: &str) -> anyhow::Error {
+        if self.opened == 0 && self.denied > 0 {
+            anyhow::anyhow!(permission_message(&input_group()))
+        } else {
+            anyhow::anyhow!("{}", not_found)
+        }
+    }
+}
+
+/// Drops cycle triggers that arrive within a debounce window of the last
+/// accepted one, so a bouncing button doesn't skip past the intended client
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    last: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn accept(&mut self, now: Instant, window: Duration) -> bool {
+        if let Some(last) = self.last {
+            if now.duration_since(last) < window {
+                return false;
+            }
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+fn permission_message(group: &str) -> String {
+    format!(
+        "Permission denied opening /dev/input/event*. Add yourself to the '{group}' group \
+         with `sudo usermod -aG {group} $USER` and log out and back in, or add a udev rule \
+         such as KERNEL==\"event*\", SUBSYSTEM==\"input\", TAG+=\"uaccess\""
+    )
+}
+
+/// Try opening every /dev/input/event* device for reading
+pub fn scan_event_devices() -> DeviceScan {
+    let mut scan = DeviceScan::default();
+    let Ok(entries) = std::fs::read_dir("/dev/input") else {
+        return scan;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with("event") {
+            scan.record(&std::fs::File::open(entry.path()));
+        }
+    }
+    scan
+}
+
+/// How to get permission to read input devices
+pub fn permission_hint() -> String {
+    permission_message(&input_group())
+}
+
+/// Whether this process is in the group owning the event devices (None if
+/// there are none to check)
+pub fn in_input_group() -> Option<bool> {
+    let gid = std::fs::metadata(Path::new("/dev/input/event0"))
+        .ok()?
+        .gid();
+    let groups = getgroups().ok()?;
+    Some(groups.contains(&Gid::from_raw(gid)) || nix::unistd::getegid().as_raw() == gid)
+}
+
+/// Name of the group that owns the event devices here, e.g. `input`
+pub fn input_group() -> String {
+    std::fs::metadata(Path::new("/dev/input/event0"))
+        .ok()
+        .and_then(|meta| Group::from_gid(Gid::from_raw(meta.gid())).ok().flatten())
+        .map(|group| group.name)
+        .unwrap_or_else(|| DEFAULT_INPUT_GROUP.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denied() -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::PermissionDenied))
+    }
+
+    #[test]
+    fn test_all_denied_reports_permissions() {
+        let mut scan = DeviceScan::default();
+        scan.record(&denied());
+        scan.record(&denied());
+        let message = scan.no_device_error("No mouse found").to_string();
+        assert!(message.contains("usermod -aG"), "{}", message);
+    }
+
+    #[test]
+    fn test_debouncer_drops_triggers_inside_window() {
+        let window = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+
+        assert!(debouncer.accept(start, window));
+        assert!(!debouncer.accept(start + Duration::from_millis(50), window));
+        // Measured from the last accepted trigger, not the last dropped one
+        assert!(debouncer.accept(start + Duration::from_millis(100), window));
+        assert!(!debouncer.accept(start + Duration::from_millis(199), window));
+
+        // A zero window accepts everything
+        let mut debouncer = Debouncer::default();
+        assert!(debouncer.accept(start, Duration::ZERO));
+        assert!(debouncer.accept(start, Duration::ZERO));
+    }
+
+    #[test]
+    fn test_some_opened_reports_not_found() {
+        let mut scan = DeviceScan::default();
+        scan.record(&denied());
+        scan.record(&Ok(()));
+        assert_eq!(
+            scan.no_device_error("No mouse found").to_string(),
+            "No mouse found"
+        );
+    }
+}
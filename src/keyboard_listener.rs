@@ -1,44 +1,156 @@
 use crate::config::Config;
 use crate::cycle_state::CycleState;
+use crate::daemon::ListenerStatus;
+use crate::input_access::{Debouncer, DeviceScan};
 use crate::window_manager::WindowManager;
 use anyhow::{Context, Result};
 use evdev::{Device, InputEventKind, Key};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use log::{debug, error, info, warn};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CycleKey {
+    Forward,
+    Backward,
+}
+
+/// What a key press does: the plain cycle keys, or an entry from `chords`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyAction {
+    Cycle(CycleKey),
+    Switch(usize),
+}
+
+impl KeyAction {
+    /// Parse a chord action: "forward", "backward" or "switch N"
+    fn parse(action: &str) -> Option<Self> {
+        let mut words = action.split_whitespace();
+        let parsed = match (words.next()?, words.next()) {
+            ("forward", None) => KeyAction::Cycle(CycleKey::Forward),
+            ("backward", None) => KeyAction::Cycle(CycleKey::Backward),
+            ("switch", Some(n)) => KeyAction::Switch(n.parse().ok().filter(|&n| n > 0)?),
+            _ => return None,
+        };
+        words.next().is_none().then_some(parsed)
+    }
+}
+
+/// Tracks held modifiers across events and turns presses into cycle actions.
+/// Shift is always tracked, so Shift+forward key cycles backward when both
+/// directions share a key (Tab by default), with or without `modifier_key`.
+#[derive(Default)]
+struct KeyTracker {
+    modifier_pressed: bool,
+    left_shift: bool,
+    right_shift: bool,
+    /// Every key currently held, for matching `chord_modifiers`
+    held: Vec<u16>,
+}
+
+impl KeyTracker {
+    /// Feed one key event (value 0 = release, 1 = press, 2 = repeat)
+    fn handle(
+        &mut self,
+        code: u16,
+        value: i32,
+        forward_key: u16,
+        backward_key: u16,
+        modifier_key: Option<u16>,
+    ) -> Option<CycleKey> {
+        let pressed = value != 0;
+        if code == Key::KEY_LEFTSHIFT.code() {
+            self.left_shift = pressed;
+        } else if code == Key::KEY_RIGHTSHIFT.code() {
+            self.right_shift = pressed;
+        }
+        if modifier_key == Some(code) {
+            self.modifier_pressed = pressed;
+        }
+        if !pressed {
+            self.held.retain(|&held| held != code);
+        } else if !self.held.contains(&code) {
+            self.held.push(code);
+        }
+
+        // Auto-repeat from a held key would overshoot, so only fresh presses cycle
+        if value != 1 {
+            return None;
+        }
+
+        // Check modifier + backward first, otherwise if backward == forward the modifier is ignored
+        let backward_held = self.modifier_pressed || self.left_shift || self.right_shift;
+        if code == backward_key && backward_held {
+            Some(CycleKey::Backward)
+        } else if code == forward_key {
+            Some(CycleKey::Forward)
+        } else if code == backward_key {
+            Some(CycleKey::Backward)
+        } else {
+            None
+        }
+    }
+
+    /// The chord bound to `code` if this is a fresh press with every chord
+    /// modifier held. Call after `handle` so the held keys include this event.
+    fn chord(
+        &self,
+        code: u16,
+        value: i32,
+        modifiers: &[u16],
+        chords: &BTreeMap<String, String>,
+    ) -> Option<KeyAction> {
+        if value != 1 || modifiers.is_empty() || !modifiers.iter().all(|m| self.held.contains(m)) {
+            return None;
+        }
+        let action = chords.get(&code.to_string())?;
+        let parsed = KeyAction::parse(action);
+        if parsed.is_none() {
+            warn!(
+                "Ignoring chord for key {}: unknown action '{}'",
+                code, action
+            );
+        }
+        parsed
+    }
+}
 
 pub struct KeyboardListener {
-    config: Config,
+    config: Arc<RwLock<Config>>,
 }
 
 impl KeyboardListener {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Arc<RwLock<Config>>) -> Self {
         Self { config }
     }
 
     /// Find keyboard device by looking for devices with standard keyboard keys
-    fn find_keyboard_device(configured_path: Option<&str>) -> Result<Device> {
+    fn find_keyboard_device(configured_path: Option<&str>) -> Result<(Device, PathBuf)> {
         if let Some(path_str) = configured_path {
             let path = Path::new(path_str);
             match Device::open(path) {
                 Ok(device) => {
-                    println!(
+                    info!(
                         "Using configured keyboard device {} ({})",
                         device.name().unwrap_or("Unknown"),
                         path.display()
                     );
-                    return Ok(device);
+                    return Ok((device, path.to_path_buf()));
                 }
                 Err(e) => {
-                    eprintln!(
-                        "Warning: Failed to open configured keyboard device '{}': {}",
+                    warn!(
+                        "Failed to open configured keyboard device '{}': {}",
                         path_str, e
                     );
-                    eprintln!("Falling back to automatic device detection...");
+                    warn!("Falling back to automatic device detection...");
                 }
             }
         }
 
         let devices_path = Path::new("/dev/input");
+        let mut scan = DeviceScan::default();
         for entry in std::fs::read_dir(devices_path)? {
             let entry = entry?;
             let path = entry.path();
@@ -46,18 +158,20 @@ impl KeyboardListener {
             if let Some(filename) = path.file_name() {
                 if let Some(name) = filename.to_str() {
                     if name.starts_with("event") {
-                        if let Ok(device) = Device::open(&path) {
+                        let opened = Device::open(&path);
+                        scan.record(&opened);
+                        if let Ok(device) = opened {
                             if device.supported_keys().is_some_and(|keys| {
                                 keys.contains(Key::KEY_TAB)
                                     || keys.contains(Key::KEY_LEFTSHIFT)
                                     || keys.contains(Key::KEY_Z)
                             }) {
-                                println!(
+                                info!(
                                     "Found keyboard device: {} ({})",
                                     device.name().unwrap_or("Unknown"),
                                     path.display()
                                 );
-                                return Ok(device);
+                                return Ok((device, path));
                             }
                         }
                     }
@@ -65,7 +179,7 @@ impl KeyboardListener {
             }
         }
 
-        anyhow::bail!("No keyboard device found in /dev/input")
+        Err(scan.no_device_error("No keyboard device found in /dev/input"))
     }
 
     /// Run the keyboard event listener in a background thread
@@ -73,29 +187,19 @@ impl KeyboardListener {
         &self,
         wm: Arc<dyn WindowManager>,
         state: Arc<Mutex<CycleState>>,
+        status: Arc<Mutex<ListenerStatus>>,
+        character_order: Arc<Mutex<Option<Vec<String>>>>,
     ) -> Result<std::thread::JoinHandle<()>> {
-        if !self.config.enable_keyboard_buttons {
+        if !self.config.read().unwrap().enable_keyboard_buttons {
             anyhow::bail!("Keyboard buttons are disabled in config");
         }
 
-        let forward_key = self.config.forward_key;
-        let backward_key = self.config.backward_key;
-        let modifier_key = self.config.modifier_key;
-        let keyboard_device_path = self.config.keyboard_device_path.clone();
-        let minimize_inactive = self.config.minimize_inactive;
+        let config = Arc::clone(&self.config);
 
         let handle = std::thread::spawn(move || {
-            match Self::run_listener(
-                wm,
-                state,
-                forward_key,
-                backward_key,
-                modifier_key,
-                keyboard_device_path,
-                minimize_inactive,
-            ) {
-                Ok(_) => println!("Keyboard listener stopped"),
-                Err(e) => println!("Keyboard listener error: {}", e),
+            match Self::run_listener(wm, state, status, config, character_order) {
+                Ok(_) => info!("Keyboard listener stopped"),
+                Err(e) => error!("Keyboard listener error: {}", e),
             }
         });
 
@@ -105,55 +209,100 @@ impl KeyboardListener {
     fn run_listener(
         wm: Arc<dyn WindowManager>,
         state: Arc<Mutex<CycleState>>,
-        forward_key: u16,
-        backward_key: u16,
-        modifier_key: Option<u16>,
-        keyboard_device_path: Option<String>,
-        minimize_inactive: bool,
+        status: Arc<Mutex<ListenerStatus>>,
+        config: Arc<RwLock<Config>>,
+        character_order: Arc<Mutex<Option<Vec<String>>>>,
     ) -> Result<()> {
-        let mut device = Self::find_keyboard_device(keyboard_device_path.as_deref()).context(
-            "Failed to find keyboard device. Make sure you have permission to read /dev/input/event*",
-        )?;
+        let (forward_key, backward_key, device_path) = {
+            let config = config.read().unwrap();
+            (
+                config.forward_key,
+                config.backward_key,
+                config.keyboard_device_path.clone(),
+            )
+        };
+
+        let found = Self::find_keyboard_device(device_path.as_deref())
+            .context("Failed to find keyboard device");
+
+        let (mut device, path) = match found {
+            Ok(found) => found,
+            Err(e) => {
+                status.lock().unwrap().error = Some(format!("{:#}", e));
+                return Err(e);
+            }
+        };
+
+        // Record the device so the daemon can report what it's listening to
+        {
+            let mut status = status.lock().unwrap();
+            status.device_path = Some(path.display().to_string());
+            status.device_name = device.name().map(String::from);
+        }
 
         // DON'T grab the device - we only want to passively listen to events
         // Grabbing would prevent normal keyboard usage!
 
-        println!(
+        info!(
             "Listening for keyboard keys: forward={} backward={}",
             forward_key, backward_key
         );
-        let mut modifier_pressed = false;
+        let mut tracker = KeyTracker::default();
+        let mut debouncer = Debouncer::default();
 
         loop {
             for event in device.fetch_events()? {
                 if let InputEventKind::Key(key) = event.kind() {
                     let code = key.code();
-                    //let mut modifier_pressed = false;
-                    if let Some(mod_key) = modifier_key {
-                        if code == mod_key {
-                            println!("Modifier Pressed");
-                            modifier_pressed = event.value() != 0;
-                        }
-                    }
-                    //print(code);
-                    if event.value() != 0 {
-                        // Have to check modifier + backwards first, otherwise if backward == forward it ignores the modifier flag
-                        if code == backward_key && modifier_pressed {
-                            println!("Backward + Modifier button pressed");
-                            if let Err(e) = Self::cycle_backward(&wm, &state, minimize_inactive) {
-                                eprintln!("Failed to cycle backward: {}", e);
-                            }
-                        } else if code == forward_key {
-                            println!("Forward button pressed");
+                    let value = event.value();
+                    // Read bindings per event so `nicotine reload` applies immediately
+                    let (action, minimize_inactive, debounce) = {
+                        let config = config.read().unwrap();
+                        let cycle = tracker.handle(
+                            code,
+                            value,
+                            config.forward_key,
+                            config.backward_key,
+                            config.modifier_key,
+                        );
+                        // A chord wins over the plain cycle keys it overlaps
+                        let action = tracker
+                            .chord(code, value, &config.chord_modifiers, &config.chords)
+                            .or(cycle.map(KeyAction::Cycle));
+                        (
+                            action,
+                            config.minimize_inactive,
+                            Duration::from_millis(config.cycle_debounce_ms),
+                        )
+                    };
+                    let action = action.filter(|_| debouncer.accept(Instant::now(), debounce));
+                    match action {
+                        Some(KeyAction::Cycle(CycleKey::Forward)) => {
+                            debug!("Forward button pressed");
                             if let Err(e) = Self::cycle_forward(&wm, &state, minimize_inactive) {
-                                eprintln!("Failed to cycle forward: {}", e);
+                                warn!("Failed to cycle forward: {}", e);
                             }
-                        } else if code == backward_key {
-                            println!("Backward button pressed");
+                        }
+                        Some(KeyAction::Cycle(CycleKey::Backward)) => {
+                            debug!("Backward button pressed");
                             if let Err(e) = Self::cycle_backward(&wm, &state, minimize_inactive) {
-                                eprintln!("Failed to cycle backward: {}", e);
+                                warn!("Failed to cycle backward: {}", e);
                             }
                         }
+                        Some(KeyAction::Switch(target)) => {
+                            debug!("Chord for client {} pressed", target);
+                            let order = character_order.lock().unwrap().clone();
+                            if let Err(e) = Self::switch_to(
+                                &wm,
+                                &state,
+                                target,
+                                minimize_inactive,
+                                order.as_deref(),
+                            ) {
+                                warn!("Failed to switch to client {}: {}", target, e);
+                            }
+                        }
+                        None => {}
                     }
                 }
             }
@@ -176,6 +325,23 @@ impl KeyboardListener {
         Ok(())
     }
 
+    fn switch_to(
+        wm: &Arc<dyn WindowManager>,
+        state: &Arc<Mutex<CycleState>>,
+        target: usize,
+        minimize_inactive: bool,
+        character_order: Option<&[String]>,
+    ) -> Result<()> {
+        let mut state = state.lock().unwrap();
+
+        // Sync with active window first
+        if let Ok(active) = wm.get_active_window() {
+            state.sync_with_active(active);
+        }
+
+        state.switch_to(target, &**wm, minimize_inactive, character_order)
+    }
+
     fn cycle_backward(
         wm: &Arc<dyn WindowManager>,
         state: &Arc<Mutex<CycleState>>,
@@ -192,3 +358,110 @@ impl KeyboardListener {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAB: u16 = 15;
+    const LEFT_SHIFT: u16 = 42;
+    const RIGHT_SHIFT: u16 = 54;
+    const LEFT_CTRL: u16 = 29;
+    const LEFT_ALT: u16 = 56;
+    const KEY_3: u16 = 4;
+
+    #[test]
+    fn test_shift_tab_cycles_backward_by_default() {
+        let mut tracker = KeyTracker::default();
+        let mut key = |code, value| tracker.handle(code, value, TAB, TAB, None);
+
+        assert_eq!(key(TAB, 1), Some(CycleKey::Forward));
+        assert_eq!(key(TAB, 0), None);
+
+        assert_eq!(key(LEFT_SHIFT, 1), None);
+        assert_eq!(key(TAB, 1), Some(CycleKey::Backward));
+        assert_eq!(key(TAB, 0), None);
+        assert_eq!(key(LEFT_SHIFT, 0), None);
+
+        // Releasing Shift goes back to forward
+        assert_eq!(key(TAB, 1), Some(CycleKey::Forward));
+    }
+
+    #[test]
+    fn test_auto_repeat_does_not_cycle() {
+        let mut tracker = KeyTracker::default();
+        let mut key = |code, value| tracker.handle(code, value, TAB, TAB, None);
+
+        assert_eq!(key(TAB, 1), Some(CycleKey::Forward));
+        assert_eq!(key(TAB, 2), None);
+        assert_eq!(key(TAB, 2), None);
+        assert_eq!(key(TAB, 0), None);
+
+        // Shift stays held through its own repeats
+        key(LEFT_SHIFT, 1);
+        key(LEFT_SHIFT, 2);
+        assert_eq!(key(TAB, 1), Some(CycleKey::Backward));
+    }
+
+    #[test]
+    fn test_either_shift_held_counts() {
+        let mut tracker = KeyTracker::default();
+        let mut key = |code, value| tracker.handle(code, value, TAB, TAB, None);
+
+        key(LEFT_SHIFT, 1);
+        key(RIGHT_SHIFT, 1);
+        key(LEFT_SHIFT, 0);
+        assert_eq!(key(TAB, 1), Some(CycleKey::Backward));
+        key(RIGHT_SHIFT, 0);
+        assert_eq!(key(TAB, 1), Some(CycleKey::Forward));
+    }
+
+    #[test]
+    fn test_configured_modifier_still_works() {
+        let mut tracker = KeyTracker::default();
+        let mut key = |code, value| tracker.handle(code, value, TAB, TAB, Some(LEFT_CTRL));
+
+        key(LEFT_CTRL, 1);
+        assert_eq!(key(TAB, 1), Some(CycleKey::Backward));
+        key(LEFT_CTRL, 0);
+        assert_eq!(key(TAB, 1), Some(CycleKey::Forward));
+    }
+
+    #[test]
+    fn test_chord_needs_every_modifier_held() {
+        let chords = crate::config::Config::for_display(1920, 1080).chords;
+        let modifiers = [LEFT_CTRL, LEFT_ALT];
+        let mut tracker = KeyTracker::default();
+        let mut key = |code, value| {
+            let cycle = tracker.handle(code, value, TAB, TAB, None);
+            tracker
+                .chord(code, value, &modifiers, &chords)
+                .or(cycle.map(KeyAction::Cycle))
+        };
+
+        assert_eq!(key(KEY_3, 1), None);
+        key(KEY_3, 0);
+        key(LEFT_CTRL, 1);
+        assert_eq!(key(KEY_3, 1), None);
+        key(KEY_3, 0);
+        key(LEFT_ALT, 1);
+        assert_eq!(key(KEY_3, 1), Some(KeyAction::Switch(3)));
+        assert_eq!(key(KEY_3, 2), None);
+        key(KEY_3, 0);
+
+        // Unbound keys keep their single-key meaning
+        assert_eq!(key(TAB, 1), Some(KeyAction::Cycle(CycleKey::Forward)));
+    }
+
+    #[test]
+    fn test_chord_action_parsing() {
+        assert_eq!(KeyAction::parse("switch 4"), Some(KeyAction::Switch(4)));
+        assert_eq!(
+            KeyAction::parse("backward"),
+            Some(KeyAction::Cycle(CycleKey::Backward))
+        );
+        assert_eq!(KeyAction::parse("switch 0"), None);
+        assert_eq!(KeyAction::parse("switch"), None);
+        assert_eq!(KeyAction::parse("forward 2"), None);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::paths;
+use anyhow::{Context, Result};
+use event_listener::Event;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+
+const KWIN_DEST: &str = "org.kde.KWin";
+const KWIN_SCRIPTING: &str = "org.kde.kwin.Scripting";
+const BRIDGE_PATH: &str = "/org/nicotine/KWin";
+const SCRIPT: &str = include_str!("../assets/kwin-script/nicotine.js");
+
+/// How long the script's NextActivation call is held open when nothing is
+/// asked for, well inside D-Bus's default 25s call timeout
+const POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for a freshly loaded script's first report or poll
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A client as the script reports it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScriptWindow {
+    pub id: u32,
+    pub title: String,
+    /// resourceName and resourceClass, i.e. WM_CLASS instance and class
+    pub wm_class: Vec<String>,
+    #[serde(default)]
+    pub demands_attention: bool,
+}
+
+#[derive(Default)]
+struct PollState {
+    /// NextActivation calls currently held open
+    waiting: usize,
+    /// When the last one returned
+    returned: Option<Instant>,
+}
+
+/// State shared between the D-Bus interface and the window manager
+struct Shared {
+    windows: Mutex<Option<Vec<ScriptWindow>>>,
+    requests: Mutex<VecDeque<u32>>,
+    polls: Mutex<PollState>,
+    /// Signalled on every window report, and on every poll starting or
+    /// ending, for callers waiting on a freshly loaded script
+    reported: Condvar,
+    polled: Condvar,
+    /// Wakes a held NextActivation call when a request is queued
+    requested: Event,
+    poll_timeout: Duration,
+}
+
+impl Shared {
+    fn new(poll_timeout: Duration) -> Self {
+        Self {
+            windows: Mutex::new(None),
+            requests: Mutex::new(VecDeque::new()),
+            polls: Mutex::new(PollState::default()),
+            reported: Condvar::new(),
+            polled: Condvar::new(),
+            requested: Event::new(),
+            poll_timeout,
+        }
+    }
+
+    /// Whether the script is polling: a call is held open right now, or one
+    /// returned a moment ago and the next is on its way
+    fn is_polling(&self, polls: &PollState) -> bool {
+        polls.waiting > 0
+            || polls
+                .returned
+                .is_some_and(|at| at.elapsed() < STARTUP_TIMEOUT)
+    }
+
+    fn set_polling(&self, started: bool) {
+        let mut polls = self.polls.lock().unwrap();
+        if started {
+            polls.waiting += 1;
+        } else {
+            polls.waiting -= 1;
+            polls.returned = Some(Instant::now());
+        }
+        self.polled.notify_all();
+    }
+}
+
+/// The object the script calls back into
+struct Bridge(Arc<Shared>);
+
+#[zbus::interface(name = "org.nicotine.KWin")]
+impl Bridge {
+    /// The script's window list as JSON, sent whenever it changes
+    fn windows(&self, json: &str) {
+        match serde_json::from_str(json) {
+            Ok(windows) => {
+                *self.0.windows.lock().unwrap() = Some(windows);
+                self.0.reported.notify_all();
+            }
+            Err(e) => warn!("Unreadable window list from the KWin script: {}", e),
+        }
+    }
+
+    /// Held open until a window should be activated, then answers its id.
+    /// Answers 0 after `poll_timeout` if nothing was asked for.
+    async fn next_activation(&self) -> u32 {
+        let shared = &self.0;
+        shared.set_polling(true);
+        let deadline = Instant::now() + shared.poll_timeout;
+
+        let id = loop {
+            // Listen before checking, so a request queued in between still wakes us
+            let listener = shared.requested.listen();
+            if let Some(id) = shared.requests.lock().unwrap().pop_front() {
+                break id;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break 0;
+            }
+            futures_lite::future::or(listener, async {
+                async_io::Timer::after(remaining).await;
+            })
+            .await;
+        };
+
+        shared.set_polling(false);
+        id
+    }
+}
+
+/// The bundled KWin script, loaded once and kept running. It lists clients
+/// and activates them from inside KWin, where Wayland's focus rules don't
+/// apply, talking to nicotine over the session bus.
+pub struct KWinScript {
+    connection: Connection,
+    shared: Arc<Shared>,
+    /// Per process, so a one-off command doesn't unload the daemon's script
+    name: String,
+    path: PathBuf,
+}
+
+impl KWinScript {
+    /// Serve the bridge on the session bus and load the script into KWin,
+    /// pointed at this connection
+    pub fn load() -> Result<Self> {
+        let shared = Arc::new(Shared::new(POLL_TIMEOUT));
+        let connection = Builder::session()?
+            .serve_at(BRIDGE_PATH, Bridge(Arc::clone(&shared)))?
+            .build()
+            .context("Failed to connect to the session bus")?;
+        let service = connection
+            .unique_name()
+            .context("No name on the session bus")?
+            .to_string();
+
+        let path = paths::runtime_dir()?.join(format!("kwin-{}.js", std::process::id()));
+        std::fs::write(&path, SCRIPT.replace("__SERVICE__", &service))
+            .context("Failed to write KWin script")?;
+
+        let script = Self {
+            connection,
+            shared,
+            name: format!("nicotine-{}", std::process::id()),
+            path,
+        };
+        let reply = script.scripting(
+            "loadScript",
+            &(script.path.display().to_string(), &script.name),
+        )?;
+        let script_id: i32 = reply.body().deserialize()?;
+        if script_id < 0 {
+            anyhow::bail!("KWin refused to load the nicotine script");
+        }
+
+        // Plasma 6 puts scripts under /Scripting, Plasma 5 at the root
+        let run = |object_path: String| {
+            script.connection.call_method(
+                Some(KWIN_DEST),
+                object_path.as_str(),
+                Some("org.kde.kwin.Script"),
+                "run",
+                &(),
+            )
+        };
+        run(format!("/Scripting/Script{}", script_id))
+            .or_else(|_| run(format!("/{}", script_id)))
+            .context("Failed to start the KWin script")?;
+
+        debug!("Loaded KWin script {} as {}", script.name, service);
+        Ok(script)
+    }
+
+    fn scripting<B>(&self, method: &str, body: &B) -> Result<zbus::Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.connection
+            .call_method(
+                Some(KWIN_DEST),
+                "/Scripting",
+                Some(KWIN_SCRIPTING),
+                method,
+                body,
+            )
+            .with_context(|| format!("KWin {} failed", method))
+    }
+
+    /// Whether the script is still talking to us, giving a freshly loaded
+    /// one a moment to make its first call
+    fn is_running(&self) -> bool {
+        let polls = self.shared.polls.lock().unwrap();
+        let (polls, _) = self
+            .shared
+            .polled
+            .wait_timeout_while(polls, STARTUP_TIMEOUT, |polls| {
+                !self.shared.is_polling(polls)
+            })
+            .unwrap();
+        self.shared.is_polling(&polls)
+    }
+
+    /// Clients as last reported by the script, or None if it hasn't
+    /// reported or has stopped running (KWin restarted, say)
+    pub fn windows(&self) -> Option<Vec<ScriptWindow>> {
+        if !self.is_running() {
+            return None;
+        }
+        let windows = self.shared.windows.lock().unwrap();
+        let (windows, _) = self
+            .shared
+            .reported
+            .wait_timeout_while(windows, STARTUP_TIMEOUT, |windows| windows.is_none())
+            .unwrap();
+        windows.clone()
+    }
+
+    /// Hand `window_id` to the script. Fails if the script isn't running,
+    /// so the caller can fall back to another way of activating.
+    pub fn activate(&self, window_id: u32) -> Result<()> {
+        if !self.is_running() {
+            anyhow::bail!("KWin script stopped polling");
+        }
+
+        self.shared.requests.lock().unwrap().push_back(window_id);
+        self.shared.requested.notify(1);
+        Ok(())
+    }
+}
+
+impl Drop for KWinScript {
+    fn drop(&mut self) {
+        let _ = self.scripting("unloadScript", &(&self.name,));
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future::block_on;
+
+    #[test]
+    fn test_script_has_service_placeholder() {
+        let script = SCRIPT.replace("__SERVICE__", ":1.42");
+        assert!(script.contains(r#"const service = ":1.42";"#));
+        assert!(script.contains(BRIDGE_PATH));
+        assert!(!script.contains("__SERVICE__"));
+    }
+
+    #[test]
+    fn test_script_window_list_parses() {
+        let json = r#"[{"id":73400329,"title":"EVE - Alt One","wm_class":["steam_app_8500","steam_app_8500"],"demands_attention":true},
+                       {"id":4194310,"title":"Konsole","wm_class":["konsole","org.kde.konsole"]}]"#;
+        let windows: Vec<ScriptWindow> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].id, 73400329);
+        assert_eq!(windows[0].wm_class, ["steam_app_8500", "steam_app_8500"]);
+        assert!(windows[0].demands_attention);
+        assert!(!windows[1].demands_attention);
+    }
+
+    #[test]
+    fn test_next_activation_hands_over_queued_requests() {
+        let shared = Arc::new(Shared::new(Duration::from_millis(50)));
+        let bridge = Bridge(Arc::clone(&shared));
+
+        shared.requests.lock().unwrap().push_back(42);
+        assert_eq!(block_on(bridge.next_activation()), 42);
+
+        // Nothing queued: the call gives up after the poll timeout
+        assert_eq!(block_on(bridge.next_activation()), 0);
+        assert!(shared.is_polling(&shared.polls.lock().unwrap()));
+    }
+
+    #[test]
+    fn test_next_activation_wakes_for_a_request() {
+        let shared = Arc::new(Shared::new(Duration::from_secs(5)));
+        let bridge = Bridge(Arc::clone(&shared));
+
+        let waiter = std::thread::spawn(move || block_on(bridge.next_activation()));
+        let started = Instant::now();
+        while !shared.is_polling(&shared.polls.lock().unwrap()) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        shared.requests.lock().unwrap().push_back(7);
+        shared.requested.notify(1);
+
+        assert_eq!(waiter.join().unwrap(), 7);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::Config;
+use crate::window_manager::{EveWindow, WindowManager};
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often the client list is re-read while waiting for launched clients
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Run every `launch_commands` entry, wait for that many new clients to show
+/// up and stack them. Returns Ok(false) if some never appeared in time.
+pub fn run(wm: &dyn WindowManager, config: &Config) -> Result<bool> {
+    if config.launch_commands.is_empty() {
+        anyhow::bail!("No launch_commands in the config - add one command per client");
+    }
+
+    let already_open = wm.get_eve_windows()?.len();
+    let expected = already_open + config.launch_commands.len();
+
+    for command in &config.launch_commands {
+        println!("Launching: {}", command);
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run '{}'", command))?;
+    }
+
+    println!(
+        "Waiting up to {}s for {} clients...",
+        config.launch_timeout_secs, expected
+    );
+    let deadline = Instant::now() + Duration::from_secs(config.launch_timeout_secs);
+    let mut seen = already_open;
+    let windows = loop {
+        let windows = wm.get_eve_windows()?;
+        if windows.len() != seen {
+            seen = windows.len();
+            println!("  {} of {} clients open", seen, expected);
+        }
+        if windows.len() >= expected || Instant::now() >= deadline {
+            break windows;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let complete = windows.len() >= expected;
+    if !complete {
+        println!(
+            "✗ Only {} of {} clients appeared within {}s",
+            windows.len(),
+            expected,
+            config.launch_timeout_secs
+        );
+        if let Some(characters) = Config::load_characters() {
+            let missing = missing_characters(&characters, &windows);
+            if !missing.is_empty() {
+                println!("  Never showed: {}", missing.join(", "));
+            }
+        }
+    }
+
+    if windows.is_empty() {
+        return Ok(false);
+    }
+    if wm.supports_positioning() {
+        wm.layout_windows(
+            &windows,
+            config,
+            config.layout_mode,
+            &Config::load_character_geometry(),
+        )?;
+        println!("✓ Stacked {} windows", windows.len());
+    } else {
+        println!("Positioning unsupported on this compositor - arrange clients with its own tools");
+    }
+
+    Ok(complete)
+}
+
+/// Characters from characters.txt without a client, in file order. Clients
+/// still at character select can't be told apart, so they match nobody.
+fn missing_characters<'a>(characters: &'a [String], windows: &[EveWindow]) -> Vec<&'a str> {
+    characters
+        .iter()
+        .filter(|name| !windows.iter().any(|w| w.title == **name))
+        .map(String::as_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u32, title: &str) -> EveWindow {
+        EveWindow {
+            id,
+            title: title.to_string(),
+            demands_attention: false,
+        }
+    }
+
+    #[test]
+    fn test_missing_characters_in_file_order() {
+        let characters = vec![
+            "Main".to_string(),
+            "Scout".to_string(),
+            "Hauler".to_string(),
+        ];
+        let windows = vec![window(1, "Scout"), window(2, "EVE")];
+
+        assert_eq!(
+            missing_characters(&characters, &windows),
+            vec!["Main", "Hauler"]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use env_logger::Builder;
+use log::LevelFilter;
+
+/// Environment variable selecting the log level, e.g. `NICOTINE_LOG=debug`
+const LEVEL_VAR: &str = "NICOTINE_LOG";
+
+/// Install the logger, writing to stderr (which the detached daemon points at
+/// its log file). Filters come from `NICOTINE_LOG` (or `RUST_LOG`) in
+/// env_logger's syntax and default to `info`; per-keypress messages are
+/// logged at `debug`, which `verbose` turns on.
+pub fn init(verbose: bool) {
+    let spec = std::env::var(LEVEL_VAR)
+        .or_else(|_| std::env::var("RUST_LOG"))
+        .ok();
+
+    let mut builder = configured(spec.as_deref());
+    // --verbose only ever raises the level
+    if verbose && configured(spec.as_deref()).build().filter() < LevelFilter::Debug {
+        builder.filter_level(LevelFilter::Debug);
+    }
+    let _ = builder.try_init();
+}
+
+fn configured(spec: Option<&str>) -> Builder {
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info).format_target(false);
+    if let Some(spec) = spec {
+        builder.parse_filters(spec);
+    }
+    builder
+}
@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use nix::libc::c_int;
+use nix::sys::signal::{signal, SigHandler, Signal};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// How often the log is checked for new output while following
+const POLL_MS: u64 = 200;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_sigint(_: c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Print the daemon log, then keep printing appended output if `follow` is set
+pub fn run(path: &Path, follow: bool) -> Result<()> {
+    let mut file = File::open(path).with_context(|| {
+        format!(
+            "No daemon log at {} (has `nicotine start` been run?)",
+            path.display()
+        )
+    })?;
+
+    let mut stdout = std::io::stdout();
+    let mut position = std::io::copy(&mut file, &mut stdout)?;
+    stdout.flush()?;
+
+    if !follow {
+        return Ok(());
+    }
+
+    // Stop following on Ctrl-C instead of being killed mid-write
+    unsafe { signal(Signal::SIGINT, SigHandler::Handler(handle_sigint)) }
+        .context("Failed to install Ctrl-C handler")?;
+
+    let mut buffer = Vec::new();
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(POLL_MS));
+
+        let len = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            // The daemon may be restarting; wait for the log to come back
+            Err(_) => continue,
+        };
+
+        // A new daemon truncated or recreated the log: start over from the top
+        if len < position {
+            file = File::open(path)?;
+            position = 0;
+        }
+
+        if len > position {
+            file.seek(SeekFrom::Start(position))?;
+            buffer.clear();
+            position += file.read_to_end(&mut buffer)? as u64;
+            stdout.write_all(&buffer)?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,285 +1,952 @@
+mod activation_queue;
+mod active_border;
+mod client_settle;
 mod config;
 mod cycle_state;
 mod daemon;
+mod doctor;
+mod input_access;
 mod keyboard_listener;
+mod kwin_script;
+mod launch;
+mod logger;
+mod logs;
 mod mouse_listener;
 mod overlay;
+mod paths;
+mod selftest;
+mod service;
 mod version_check;
 mod wayland_backends;
 mod window_manager;
+mod wlroots_manager;
 mod x11_manager;
 
-use anyhow::Result;
-use config::Config;
-use cycle_state::CycleState;
+use anyhow::{Context, Result};
+use config::{Config, ConfigFormat, LayoutMode};
+use cycle_state::{CycleState, PersistedState};
 use daemon::Daemon;
 use daemonize::Daemonize;
+use log::debug;
 #[allow(deprecated)]
 use nix::fcntl::{flock, FlockArg};
-use overlay::run_overlay;
+use overlay::{run_overlay, OverlayPosition};
+use serde::Serialize;
 use std::env;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::io::AsRawFd;
-use std::sync::{Arc, Mutex};
-use wayland_backends::{HyprlandManager, KWinManager, SwayManager};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use wayland_backends::{GnomeManager, HyprlandManager, KWinManager, SwayManager};
 use window_manager::{
-    detect_display_server, detect_wayland_compositor, DisplayServer, WaylandCompositor,
-    WindowManager,
+    backend_name, detect_display_server, detect_wayland_compositor, display_area, layout_targets,
+    DisplayServer, WaylandCompositor, WindowFilter, WindowManager,
 };
+use wlroots_manager::WlrootsManager;
 use x11_manager::X11Manager;
 
-fn create_window_manager() -> Result<Arc<dyn WindowManager>> {
+fn create_window_manager(config: &Config) -> Result<Arc<dyn WindowManager>> {
     let display_server = detect_display_server();
+    let filter = WindowFilter::from_config(config)?;
 
     match display_server {
         DisplayServer::X11 => {
-            println!("Detected X11 display server");
-            Ok(Arc::new(X11Manager::new()?))
+            debug!("Detected X11 display server");
+            let mut x11 = X11Manager::new(filter)?;
+            if config.active_border {
+                x11.enable_active_border(
+                    config.active_border_color,
+                    config.active_border_thickness,
+                )?;
+            }
+            x11.set_activation_retries(config.activate_retries, config.activate_retry_ms);
+            x11.set_activation_method(config.x11_activation_method);
+            Ok(Arc::new(x11))
         }
         DisplayServer::Wayland => {
             let compositor = detect_wayland_compositor();
-            println!(
+            debug!(
                 "Detected Wayland display server with {:?} compositor",
                 compositor
             );
 
             match compositor {
                 WaylandCompositor::Kde => {
-                    println!("Using KDE/KWin backend");
-                    Ok(Arc::new(KWinManager::new()?))
+                    debug!("Using KDE/KWin backend");
+                    Ok(Arc::new(KWinManager::new(filter)?))
                 }
                 WaylandCompositor::Sway => {
-                    println!("Using Sway backend");
-                    Ok(Arc::new(SwayManager::new()?))
+                    debug!("Using Sway backend");
+                    Ok(Arc::new(SwayManager::new(filter)?))
                 }
                 WaylandCompositor::Hyprland => {
-                    println!("Using Hyprland backend");
-                    Ok(Arc::new(HyprlandManager::new()?))
+                    debug!("Using Hyprland backend");
+                    Ok(Arc::new(HyprlandManager::new(filter)?))
                 }
                 WaylandCompositor::Gnome => {
-                    anyhow::bail!("GNOME Shell is not yet supported due to restrictive window management APIs")
+                    debug!("Using GNOME Shell backend");
+                    Ok(Arc::new(GnomeManager::new(filter)?))
                 }
                 WaylandCompositor::Other => {
-                    anyhow::bail!(
-                        "Unknown Wayland compositor. Supported: KDE Plasma, Sway, Hyprland"
-                    )
+                    // Any wlroots-based compositor advertising foreign-toplevel management
+                    let wlroots = WlrootsManager::new(filter).context(
+                        "Unknown Wayland compositor. Supported: KDE Plasma, Sway, Hyprland, GNOME, \
+                         and wlroots compositors with wlr-foreign-toplevel-management",
+                    )?;
+                    debug!("Using generic wlroots backend");
+                    Ok(Arc::new(wlroots))
                 }
             }
         }
     }
 }
 
+/// Run a cycle action without the daemon, building fresh state from the window manager.
+/// With `persist`, the state is restored from and saved back to the state file so
+/// consecutive invocations continue where the last one left off.
+/// Skipped if another direct-mode invocation holds the cycle lock (see --verbose).
+fn run_direct(
+    wm: &dyn WindowManager,
+    config: &Config,
+    persist: bool,
+    action: impl FnOnce(&mut CycleState) -> Result<()>,
+) -> Result<()> {
+    let Some(_lock) = acquire_cycle_lock(&paths::cycle_lock_file()?) else {
+        return Ok(());
+    };
+
+    let mut state = cycle_state(config);
+    let windows = wm.get_eve_windows()?;
+
+    if windows.is_empty() {
+        return Ok(());
+    }
+
+    let state_file = paths::state_file()?;
+    if persist {
+        state.restore(&PersistedState::load(&state_file), windows);
+    } else {
+        state.update_windows(windows);
+    }
+
+    // Sync with current active window
+    if let Ok(active) = wm.get_active_window() {
+        state.sync_with_active(active);
+    }
+
+    action(&mut state)?;
+
+    if persist {
+        state.snapshot().save(&state_file)?;
+    }
+
+    Ok(())
+
+    // Lock is automatically released when file is dropped
+}
+
+/// Cycle state for a fresh process, ordered by characters.txt when there is one
+fn cycle_state(config: &Config) -> CycleState {
+    let mut state = CycleState::from_config(config);
+    state.set_character_order(Config::load_characters().as_deref());
+    state
+}
+
+/// How long a direct-mode command waits for the previous one to finish
+const CYCLE_LOCK_TIMEOUT: Duration = Duration::from_millis(200);
+const CYCLE_LOCK_POLL: Duration = Duration::from_millis(10);
+/// A holder this old is assumed stuck (e.g. on an X call that never returns)
+const CYCLE_LOCK_STALE: Duration = Duration::from_secs(5);
+
+/// Take the direct-mode cycle lock, waiting briefly for another invocation to
+/// finish. None means skip this cycle. A lock held for longer than
+/// CYCLE_LOCK_STALE is ignored, so one hung process doesn't stop cycling.
+fn acquire_cycle_lock(path: &Path) -> Option<File> {
+    let mut file = match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(path)
+    {
+        Ok(f) => f,
+        Err(e) => {
+            debug!("Skipped cycle: can't open {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let deadline = Instant::now() + CYCLE_LOCK_TIMEOUT;
+    loop {
+        #[allow(deprecated)]
+        if flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock).is_ok() {
+            // The holder's pid, and via the mtime when it took the lock
+            let _ = file.set_len(0);
+            let _ = write!(file, "{}", std::process::id());
+            return Some(file);
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(CYCLE_LOCK_POLL);
+    }
+
+    let holder = std::fs::read_to_string(path).unwrap_or_default();
+    let held_for = file
+        .metadata()
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|since| since.elapsed().ok())
+        .unwrap_or_default();
+    if held_for >= CYCLE_LOCK_STALE {
+        debug!(
+            "Cycle lock held by pid {} for {:.1}s, assuming it's stuck and cycling anyway",
+            holder.trim(),
+            held_for.as_secs_f32()
+        );
+        return Some(file);
+    }
+
+    debug!(
+        "Skipped cycle: pid {} is still cycling (held the lock for {}ms)",
+        holder.trim(),
+        held_for.as_millis()
+    );
+    None
+}
+
+/// How long `start` waits for the daemon to bind its socket before giving up
+const DAEMON_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Commands handled by main itself; aliases can never override these
+const BUILTIN_COMMANDS: &[&str] = &[
+    "start",
+    "daemon",
+    "overlay",
+    "stack",
+    "restack",
+    "launch",
+    "cycle-forward",
+    "forward",
+    "f",
+    "cycle-backward",
+    "backward",
+    "b",
+    "next-attention",
+    "toggle",
+    "raise",
+    "lower",
+    "activate-under-cursor",
+    "broadcast",
+    "characters",
+    "monitors",
+    "reload",
+    "input-status",
+    "status",
+    "list",
+    "logs",
+    "stop",
+    "profile",
+    "init-config",
+    "install-service",
+    "selftest",
+    "doctor",
+    "version",
+    "switch",
+    "switch-name",
+];
+
+/// Hand a command to the daemon. True when the daemon carried it out; false
+/// when there's no daemon to ask (or `daemonless`), so the caller should do it
+/// directly. Once the command reached a daemon, any failure (including no
+/// answer in time) is an error rather than a fallback, so it never runs twice.
+fn try_daemon(command: &str, daemonless: bool) -> Result<bool> {
+    if daemonless {
+        return Ok(false);
+    }
+    match daemon::send_command(command) {
+        Ok(()) => Ok(true),
+        Err(e) if e.is::<daemon::NotRunning>() => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn is_builtin_command(command: &str) -> bool {
+    BUILTIN_COMMANDS.contains(&command) || command.parse::<usize>().is_ok()
+}
+
+fn print_listener_status(name: &str, status: &daemon::ListenerStatus) {
+    if !status.enabled {
+        println!("{} listener: disabled", name);
+        return;
+    }
+
+    match (&status.device_path, &status.error) {
+        (Some(path), _) => println!(
+            "{} listener: {} ({})",
+            name,
+            status.device_name.as_deref().unwrap_or("Unknown"),
+            path
+        ),
+        (None, Some(error)) => println!("{} listener: failed - {}", name, error),
+        (None, None) => println!("{} listener: starting...", name),
+    }
+    println!(
+        "  forward={} backward={}",
+        status.forward_code, status.backward_code
+    );
+}
+
+/// One detected client as printed by `list --json`
+#[derive(Serialize)]
+struct ListedWindow {
+    index: usize,
+    id: u32,
+    title: String,
+    active: bool,
+    demands_attention: bool,
+}
+
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    // --profile <name> runs with profiles/<name>.toml instead of config.toml
+    let profile = match args.iter().position(|a| a == "--profile") {
+        Some(i) => {
+            let name = args
+                .get(i + 1)
+                .cloned()
+                .context("--profile needs a profile name")?;
+            args.drain(i..i + 2);
+            Some(name)
+        }
+        None => None,
+    };
+
+    // --socket <path> talks to (or runs) a daemon on a socket other than the default
+    if let Some(i) = args.iter().position(|a| a == "--socket") {
+        let path = args
+            .get(i + 1)
+            .cloned()
+            .context("--socket needs a socket path")?;
+        args.drain(i..i + 2);
+        paths::set_socket_path(path.into());
+    }
+
+    // --verbose logs extra detail, e.g. why a direct-mode cycle was skipped
+    let verbose = args.iter().position(|a| a == "--verbose");
+    if let Some(i) = verbose {
+        args.remove(i);
+    }
+    logger::init(verbose.is_some());
+
+    // Answered before loading the config or connecting, so it works even when those fail
+    if matches!(
+        args.get(1).map(|s| s.as_str()),
+        Some("version" | "--version" | "-V")
+    ) {
+        println!("nicotine {}", version_check::CURRENT_VERSION);
+        println!("Display server: {:?}", detect_display_server());
+        println!("Backend: {}", backend_name());
+        return Ok(());
+    }
+
+    // Runs with or without a working config or backend, since those are its checks
+    if args.get(1).map(|s| s.as_str()) == Some("doctor") {
+        let healthy = doctor::run(profile.as_deref(), create_window_manager)?;
+        std::process::exit(if healthy { 0 } else { 1 });
+    }
+
+    let mut config = Config::load(profile.as_deref())?;
+
+    // Expand user aliases before dispatch
+    if let Some(command) = args.get(1).cloned() {
+        let expanded = config.resolve_alias(&command, is_builtin_command)?;
+        args.splice(1..2, expanded);
+    }
     let command = args.get(1).map(|s| s.as_str()).unwrap_or("");
 
-    let config = Config::load()?;
-    let wm = create_window_manager()?;
+    // Re-apply the last used (or configured) profile on start unless asked not
+    // to, before connecting so the backend gets the profile's settings too
+    let mut startup_profile = None;
+    if command == "start" && profile.is_none() && !args.iter().any(|a| a == "--no-profile") {
+        if let Some(name) = config.startup_profile() {
+            match Config::load_profile(&name) {
+                Ok(loaded) => {
+                    config = loaded;
+                    startup_profile = Some(name);
+                }
+                Err(e) => eprintln!("Warning: {}", e),
+            }
+        }
+    }
+
+    let wm = create_window_manager(&config)?;
+    let daemonless = config.daemonless || args.iter().any(|a| a == "--no-daemon");
 
     match command {
         "start" => {
             println!("Starting Nicotine 🚬");
 
-            // Check for updates (non-blocking, silent on errors)
-            if let Ok(Some((new_version, url))) = version_check::check_for_updates() {
-                version_check::print_update_notification(&new_version, &url);
+            if let Some(name) = &startup_profile {
+                println!("Applying profile '{}'", name);
+                if let Ok(windows) = wm.get_eve_windows() {
+                    let _ = wm.layout_windows(
+                        &windows,
+                        &config,
+                        config.layout_mode,
+                        &Config::load_character_geometry(),
+                    );
+                }
             }
+            let daemon_profile = startup_profile.or_else(|| profile.clone());
 
-            // Daemonize the process (safe Rust wrapper)
-            let daemonize = Daemonize::new().working_directory("/tmp").umask(0o027);
+            // --overlay/--no-overlay override show_overlay for this run only
+            let show_overlay = if args.iter().any(|a| a == "--no-overlay") {
+                false
+            } else if args.iter().any(|a| a == "--overlay") {
+                true
+            } else {
+                config.show_overlay
+            };
 
-            match daemonize.start() {
-                Ok(_) => {
-                    // We're now in the daemon process
-                    // Start daemon in background thread
-                    let wm_daemon = Arc::clone(&wm);
-                    let config_daemon = config.clone();
-                    let daemon_thread = std::thread::spawn(move || {
-                        let mut daemon = Daemon::new(wm_daemon, config_daemon);
-                        if let Err(e) = daemon.run() {
-                            eprintln!("Daemon error: {}", e);
-                        }
-                    });
+            // Check for updates (at most daily, silent on errors)
+            if !config.disable_update_check {
+                if let Ok(Some((new_version, url))) = version_check::check_for_updates() {
+                    version_check::print_update_notification(&new_version, &url);
+                }
+            }
 
-                    // Wait a bit for daemon to initialize
-                    std::thread::sleep(std::time::Duration::from_millis(100));
+            // --foreground stays attached, for supervisors and debugging in a terminal
+            if !args.iter().any(|a| a == "--foreground") {
+                // Daemonize the process (safe Rust wrapper), keeping its output in the log
+                let log = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(config.log_path()?)
+                    .context("Failed to open daemon log")?;
+                let daemonize = Daemonize::new()
+                    .working_directory("/tmp")
+                    .umask(0o027)
+                    .stdout(log.try_clone()?)
+                    .stderr(log);
 
-                    if config.show_overlay {
-                        // Run overlay in main thread
-                        let state = Arc::new(Mutex::new(CycleState::new()));
-                        if let Ok(windows) = wm.get_eve_windows() {
-                            state.lock().unwrap().update_windows(windows);
-                        }
+                if let Err(e) = daemonize.start() {
+                    eprintln!("Failed to daemonize: {}", e);
+                    std::process::exit(1);
+                }
+            }
 
-                        if let Err(e) =
-                            run_overlay(wm, state, config.overlay_x, config.overlay_y, config)
-                        {
-                            eprintln!("Overlay error: {}", e);
-                            std::process::exit(1);
+            // We're now in the daemon process (or still in the foreground)
+            // Start daemon in background thread; it handles Ctrl-C/SIGTERM
+            let wm_daemon = Arc::clone(&wm);
+            let config_daemon = config.clone();
+            let position = OverlayPosition::default();
+            let position_daemon = position.clone();
+            let overlay_profile = daemon_profile.clone();
+            let (ready_tx, ready_rx) = mpsc::channel();
+            let daemon_thread = std::thread::spawn(move || {
+                let saved = (config_daemon.overlay_x, config_daemon.overlay_y);
+                let saved_profile = daemon_profile.clone();
+                let mut daemon = Daemon::new(wm_daemon, config_daemon, daemon_profile);
+                daemon.notify_ready(ready_tx);
+                match daemon.run() {
+                    // Quitting the daemon also closes the overlay sharing this process
+                    Ok(()) => {
+                        if let Err(e) = position_daemon.save(saved_profile.as_deref(), saved) {
+                            eprintln!("Failed to save overlay position: {:#}", e);
                         }
-                    } else {
-                        // No overlay - just keep daemon running
-                        println!("Overlay disabled - daemon running in background");
-                        daemon_thread.join().unwrap();
+                        std::process::exit(0)
                     }
+                    Err(e) => eprintln!("Daemon error: {:#}", e),
                 }
-                Err(e) => {
-                    eprintln!("Failed to daemonize: {}", e);
+            });
+
+            // Don't show the overlay until the daemon's socket is up
+            match ready_rx.recv_timeout(DAEMON_READY_TIMEOUT) {
+                Ok(()) => {}
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    eprintln!(
+                        "Daemon didn't start within {}s",
+                        DAEMON_READY_TIMEOUT.as_secs()
+                    );
+                    std::process::exit(1);
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    // The daemon thread has already reported why
+                    eprintln!("Daemon failed to start");
+                    std::process::exit(1);
+                }
+            }
+
+            if show_overlay {
+                // Run overlay in main thread
+                let state = Arc::new(Mutex::new(cycle_state(&config)));
+                if let Ok(windows) = wm.get_eve_windows() {
+                    state.lock().unwrap().update_windows(windows);
+                }
+
+                let (x, y) = (config.overlay_x, config.overlay_y);
+                if let Err(e) = run_overlay(wm, state, x, y, config, position, overlay_profile) {
+                    eprintln!("Overlay error: {}", e);
                     std::process::exit(1);
                 }
+            } else {
+                // No overlay - just keep daemon running
+                println!("Overlay disabled - daemon running");
+                daemon_thread.join().unwrap();
             }
         }
 
         "daemon" => {
             println!("Starting EVE Multibox daemon...");
-            let mut daemon = Daemon::new(wm, config);
+            let mut daemon = Daemon::new(wm, config, profile);
             daemon.run()?;
         }
 
         "overlay" => {
             println!("Starting EVE Multibox Overlay...");
-            let state = Arc::new(Mutex::new(CycleState::new()));
+            let state = Arc::new(Mutex::new(cycle_state(&config)));
 
             // Initialize windows
             if let Ok(windows) = wm.get_eve_windows() {
                 state.lock().unwrap().update_windows(windows);
             }
 
-            if let Err(e) = run_overlay(wm, state, config.overlay_x, config.overlay_y, config) {
+            let (x, y) = (config.overlay_x, config.overlay_y);
+            if let Err(e) =
+                run_overlay(wm, state, x, y, config, OverlayPosition::default(), profile)
+            {
                 eprintln!("Overlay error: {}", e);
                 std::process::exit(1);
             }
         }
 
-        "stack" => {
+        "stack" | "restack" => {
+            // restack reuses the daemon's connection when one is running; --layout
+            // and --dry-run are handled here
+            if command == "restack" && args.len() == 2 && try_daemon("stack", daemonless)? {
+                return Ok(());
+            }
+
+            let layout = match args.iter().position(|a| a == "--layout") {
+                Some(i) => {
+                    let name = args.get(i + 1).map(|s| s.as_str()).unwrap_or("");
+                    LayoutMode::from_name(name).with_context(|| {
+                        format!(
+                            "Unknown layout '{}' (expected stack, grid, columns or cascade)",
+                            name
+                        )
+                    })?
+                }
+                None => config.layout_mode,
+            };
+
+            // --dry-run prints where each client would go without touching it
+            if args.iter().any(|a| a == "--dry-run") {
+                let windows = wm.get_eve_windows()?;
+                let area = match wm.stack_monitor_area(&config)? {
+                    Some(area) => area,
+                    None => display_area(&config),
+                };
+                println!(
+                    "{:?} layout of {} clients in {}x{}+{}+{}:",
+                    layout,
+                    windows.len(),
+                    area.width,
+                    area.height,
+                    area.x,
+                    area.y
+                );
+                let overrides = Config::load_character_geometry();
+                for (window, target) in windows
+                    .iter()
+                    .zip(layout_targets(&windows, &overrides, &config, layout, area))
+                {
+                    println!(
+                        "  {:<24} x={} y={} width={} height={}",
+                        window.title, target.x, target.y, target.width, target.height
+                    );
+                }
+                return Ok(());
+            }
+
+            if !wm.supports_positioning() {
+                println!("Positioning unsupported on this compositor - arrange clients with its own tools");
+                return Ok(());
+            }
+
             println!("Stacking EVE windows...");
             let windows = wm.get_eve_windows()?;
 
-            println!(
-                "Centering {} EVE clients ({}x{}) on {}x{} display",
-                windows.len(),
-                config.eve_width,
-                config.eve_height_adjusted(),
-                config.display_width,
-                config.display_height
-            );
+            if layout == LayoutMode::Stack {
+                println!(
+                    "Centering {} EVE clients ({}x{}) on {}x{} display",
+                    windows.len(),
+                    config.eve_width,
+                    config.eve_height_adjusted(),
+                    config.display_width,
+                    config.display_height
+                );
+            } else {
+                println!(
+                    "Arranging {} EVE clients in {:?} layout on {}x{} display",
+                    windows.len(),
+                    layout,
+                    config.display_width,
+                    config.display_height
+                );
+            }
 
-            wm.stack_windows(&windows, &config)?;
+            wm.layout_windows(
+                &windows,
+                &config,
+                layout,
+                &Config::load_character_geometry(),
+            )?;
 
             println!("✓ Stacked {} windows", windows.len());
         }
 
+        "launch" => {
+            if !launch::run(&*wm, &config)? {
+                std::process::exit(1);
+            }
+        }
+
         "cycle-forward" | "forward" | "f" => {
             // Try daemon first
-            if daemon::send_command("forward").is_ok() {
+            if try_daemon("forward", daemonless)? {
                 return Ok(());
             }
 
             // Fallback to direct mode
+            run_direct(&*wm, &config, daemonless, |state| {
+                state.cycle_forward(&*wm, config.minimize_inactive)
+            })?;
+        }
 
-            // Try to acquire lock, exit immediately if already running
-            let lock_file = "/tmp/nicotine-cycle.lock";
-            let file = match OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .mode(0o644)
-                .open(lock_file)
-            {
-                Ok(f) => f,
-                Err(_) => return Ok(()), // Can't get lock, skip
-            };
+        "cycle-backward" | "backward" | "b" => {
+            // Try daemon first
+            if try_daemon("backward", daemonless)? {
+                return Ok(());
+            }
+
+            // Fallback to direct mode
+            run_direct(&*wm, &config, daemonless, |state| {
+                state.cycle_backward(&*wm, config.minimize_inactive)
+            })?;
+        }
 
-            // Try to lock (non-blocking)
-            #[allow(deprecated)]
-            if flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock).is_err() {
-                return Ok(()); // Already running, skip this cycle
+        "next-attention" => {
+            // Try daemon first
+            if try_daemon("next-attention", daemonless)? {
+                return Ok(());
             }
 
-            let mut state = CycleState::new();
-            let windows = wm.get_eve_windows()?;
+            // Fallback to direct mode
+            run_direct(&*wm, &config, daemonless, |state| {
+                state.next_attention(&*wm, config.minimize_inactive)
+            })?;
+        }
 
-            if windows.is_empty() {
+        "toggle" => {
+            // Try daemon first
+            if try_daemon("toggle", daemonless)? {
                 return Ok(());
             }
 
-            state.update_windows(windows);
+            // Fallback to direct mode
+            run_direct(&*wm, &config, daemonless, |state| {
+                state.toggle_last(&*wm, config.minimize_inactive)
+            })?;
+        }
 
-            // Sync with current active window
-            if let Ok(active) = wm.get_active_window() {
-                state.sync_with_active(active);
+        "raise" | "lower" => {
+            if !wm.supports_restacking() {
+                println!("Raising/lowering unsupported on this compositor");
+                return Ok(());
             }
 
-            state.cycle_forward(&*wm, config.minimize_inactive)?;
+            // Try daemon first
+            if try_daemon(command, daemonless)? {
+                return Ok(());
+            }
 
-            // Lock is automatically released when file is dropped
+            let windows = wm.get_eve_windows()?;
+            if command == "raise" {
+                wm.raise_all(&windows)?;
+            } else {
+                wm.lower_all(&windows)?;
+            }
         }
 
-        "cycle-backward" | "backward" | "b" => {
+        "activate-under-cursor" => {
             // Try daemon first
-            if daemon::send_command("backward").is_ok() {
+            if try_daemon("activate-under-cursor", daemonless)? {
                 return Ok(());
             }
 
             // Fallback to direct mode
-
-            // Try to acquire lock, exit immediately if already running
-            let lock_file = "/tmp/nicotine-cycle.lock";
-            let file = match OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .mode(0o644)
-                .open(lock_file)
-            {
-                Ok(f) => f,
-                Err(_) => return Ok(()), // Can't get lock, skip
+            let Some(window_id) = wm.window_under_cursor()? else {
+                return Ok(());
             };
+            run_direct(&*wm, &config, daemonless, |state| {
+                state.switch_to_window(window_id, &*wm, config.minimize_inactive)
+            })?;
+        }
 
-            // Try to lock (non-blocking)
-            #[allow(deprecated)]
-            if flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock).is_err() {
-                return Ok(()); // Already running, skip this cycle
+        "switch-name" => {
+            let name = args[2..].join(" ");
+            if name.trim().is_empty() {
+                eprintln!("Usage: nicotine switch-name <character name>");
+                std::process::exit(1);
             }
 
-            let mut state = CycleState::new();
-            let windows = wm.get_eve_windows()?;
-
-            if windows.is_empty() {
+            // Try daemon first
+            if try_daemon(&format!("switch-name:{}", name), daemonless)? {
                 return Ok(());
             }
 
-            state.update_windows(windows);
+            // Fallback to direct mode
+            run_direct(&*wm, &config, daemonless, |state| {
+                state.switch_to_name(&name, &*wm, config.minimize_inactive)
+            })?;
+        }
+
+        "input-status" => {
+            let reply = daemon::send_command_with_reply("input-status")?;
+            let status: daemon::InputStatus =
+                serde_json::from_str(reply.trim()).context("Unexpected reply from daemon")?;
 
-            // Sync with current active window
-            if let Ok(active) = wm.get_active_window() {
-                state.sync_with_active(active);
+            print_listener_status("Mouse", &status.mouse);
+            print_listener_status("Keyboard", &status.keyboard);
+            if let Some(modifier) = status.modifier_key {
+                println!("Backward modifier key: {}", modifier);
             }
+        }
 
-            state.cycle_backward(&*wm, config.minimize_inactive)?;
+        "status" => {
+            let reply = daemon::send_command_with_reply("status")?;
+            let status: daemon::DaemonStatus =
+                serde_json::from_str(reply.trim()).context("Unexpected reply from daemon")?;
 
-            // Lock is automatically released when file is dropped
+            println!("Daemon: running");
+            println!("Clients: {}", status.window_count);
+            for (i, title) in status.titles.iter().enumerate() {
+                let marker = if i == status.active_index { ">" } else { " " };
+                println!("  {} {:>2}  {}", marker, i + 1, title);
+            }
+            let running = |started: bool| if started { "running" } else { "not running" };
+            println!("Mouse listener: {}", running(status.mouse_listener));
+            println!("Keyboard listener: {}", running(status.keyboard_listener));
         }
 
         "stop" => {
             println!("Stopping Nicotine...");
 
-            // Kill all nicotine processes
-            let _ = std::process::Command::new("pkill")
-                .arg("-9")
-                .arg("nicotine")
-                .output();
-
-            println!("✓ Nicotine stopped");
+            if daemon::stop_daemon(&config)? {
+                println!("✓ Nicotine stopped");
+            } else {
+                println!("Nicotine isn't running");
+            }
 
-            // Clean up socket and lock files
-            let _ = std::fs::remove_file("/tmp/nicotine.sock");
-            let _ = std::fs::remove_file("/tmp/nicotine-cycle.lock");
+            // Clean up the direct-mode lock file
+            let _ = std::fs::remove_file(paths::cycle_lock_file()?);
         }
 
+        "profile" => match (args.get(2).map(|s| s.as_str()), args.get(3)) {
+            (Some("list"), _) => {
+                let names = Config::list_profiles()?;
+                if names.is_empty() {
+                    println!("No profiles in ~/.config/nicotine/profiles");
+                }
+                let last = Config::last_profile();
+                for name in names {
+                    let marker = if last.as_deref() == Some(name.as_str()) {
+                        "  (last used)"
+                    } else {
+                        ""
+                    };
+                    println!("{}{}", name, marker);
+                }
+            }
+            (Some("use"), Some(name)) => {
+                let path = Config::use_profile(name)?;
+                println!("✓ Copied profile '{}' to {}", name, path.display());
+                println!("Run `nicotine reload` to apply it to a running daemon");
+            }
+            (Some("load"), Some(name)) => {
+                let profile = Config::load_profile(name)?;
+                let windows = wm.get_eve_windows()?;
+                wm.layout_windows(
+                    &windows,
+                    &profile,
+                    profile.layout_mode,
+                    &Config::load_character_geometry(),
+                )?;
+                Config::record_last_profile(name)?;
+                println!("✓ Loaded profile '{}' ({} windows)", name, windows.len());
+            }
+            _ => {
+                println!("Usage: nicotine profile load|use <name>");
+                println!("       nicotine profile list");
+            }
+        },
+
         "init-config" => {
-            Config::save_default()?;
+            let format = match args.iter().position(|a| a == "--format") {
+                Some(i) => {
+                    let name = args.get(i + 1).map(|s| s.as_str()).unwrap_or("");
+                    ConfigFormat::from_name(name).ok_or_else(|| {
+                        anyhow::anyhow!("Unknown config format '{}' (use toml or json)", name)
+                    })?
+                }
+                None => ConfigFormat::Toml,
+            };
+            Config::save_default(format)?;
+        }
+
+        "install-service" => {
+            let force = args.iter().any(|a| a == "--force");
+            let path = service::install(force)?;
+            println!("✓ Wrote {}", path.display());
+            println!("Enable it with:");
+            println!("  systemctl --user daemon-reload");
+            println!("  systemctl --user enable --now nicotine");
+            println!(
+                "The unit runs the daemon without the overlay; use `nicotine overlay` for that."
+            );
+        }
+
+        "list" => {
+            let windows = wm.get_eve_windows()?;
+            let active = wm.get_active_window().ok();
+
+            let listed: Vec<ListedWindow> = windows
+                .into_iter()
+                .enumerate()
+                .map(|(i, window)| ListedWindow {
+                    index: i + 1,
+                    id: window.id,
+                    active: Some(window.id) == active,
+                    title: window.title,
+                    demands_attention: window.demands_attention,
+                })
+                .collect();
+
+            if args.iter().any(|a| a == "--json") {
+                println!("{}", serde_json::to_string_pretty(&listed)?);
+            } else if listed.is_empty() {
+                println!("No EVE clients detected");
+            } else {
+                for window in &listed {
+                    let marker = if window.active { ">" } else { " " };
+                    println!(
+                        "{} {:>2}  0x{:08x}  {}",
+                        marker, window.index, window.id, window.title
+                    );
+                }
+            }
+        }
+
+        "broadcast" => match args.get(2) {
+            Some(key) => {
+                let command =
+                    daemon::with_confirmation_token(&format!("broadcast:{}", key), &config);
+                daemon::send_command(&command).context("Broadcasting needs a running daemon")?;
+            }
+            None => {
+                eprintln!("Usage: nicotine broadcast <keycode>   (X11 keycode, e.g. 67 for F1)");
+                std::process::exit(1);
+            }
+        },
+
+        "characters" => {
+            if args.get(2).map(|s| s.as_str()) == Some("reload") {
+                daemon::send_command("reload-characters")?;
+                println!("✓ Asked the daemon to reload characters.txt");
+                return Ok(());
+            }
+
+            let Some(characters) = Config::load_characters() else {
+                println!(
+                    "No {} - targets follow title order",
+                    Config::characters_path().display()
+                );
+                return Ok(());
+            };
+
+            let windows = wm.get_eve_windows()?;
+            let geometries = Config::load_character_geometry();
+            for (i, name) in characters.iter().enumerate() {
+                let status = if windows.iter().any(|w| w.title == *name) {
+                    "running"
+                } else {
+                    "no matching client"
+                };
+                match geometries.iter().find(|(n, _)| n == name) {
+                    Some((_, g)) => println!(
+                        "{:>2}  {}  ({}, placed at {}x{}{:+}{:+})",
+                        i + 1,
+                        name,
+                        status,
+                        g.width,
+                        g.height,
+                        g.x,
+                        g.y
+                    ),
+                    None => println!("{:>2}  {}  ({})", i + 1, name, status),
+                }
+            }
+
+            let unlisted: Vec<&str> = windows
+                .iter()
+                .filter(|w| !characters.contains(&w.title))
+                .map(|w| w.title.as_str())
+                .collect();
+            if !unlisted.is_empty() {
+                println!();
+                println!("Running but not in characters.txt: {}", unlisted.join(", "));
+            }
+        }
+
+        "reload" => {
+            daemon::send_command("reload-config")?;
+            println!("✓ Asked the daemon to reload its config");
+        }
+
+        "monitors" => {
+            let monitors = wm.monitors()?;
+            if monitors.is_empty() {
+                println!("This backend can't list monitors");
+            }
+            for monitor in &monitors {
+                let marker = if config.stack_monitor.as_deref() == Some(monitor.name.as_str()) {
+                    "  (stack_monitor)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{}  {}x{}+{}+{}{}",
+                    monitor.name, monitor.width, monitor.height, monitor.x, monitor.y, marker
+                );
+            }
+        }
+
+        "logs" => {
+            let follow = args.iter().any(|a| a == "-f" || a == "--follow");
+            logs::run(&config.log_path()?, follow)?;
+        }
+
+        "selftest" => {
+            if !selftest::run(&*wm, &config)? {
+                std::process::exit(1);
+            }
         }
 
         // Handle switch command or numeric shorthand
@@ -294,48 +961,23 @@ fn main() -> Result<()> {
 
             if let Some(target) = target {
                 // Try daemon first
-                if daemon::send_command(&format!("switch:{}", target)).is_ok() {
+                if try_daemon(&format!("switch:{}", target), daemonless)? {
                     return Ok(());
                 }
 
                 // Fallback to direct mode
-                let lock_file = "/tmp/nicotine-cycle.lock";
-                let file = match OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .mode(0o644)
-                    .open(lock_file)
-                {
-                    Ok(f) => f,
-                    Err(_) => return Ok(()),
-                };
-
-                #[allow(deprecated)]
-                if flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock).is_err() {
-                    return Ok(());
-                }
-
-                let mut state = CycleState::new();
-                let windows = wm.get_eve_windows()?;
-
-                if windows.is_empty() {
-                    return Ok(());
-                }
-
-                state.update_windows(windows);
-
-                if let Ok(active) = wm.get_active_window() {
-                    state.sync_with_active(active);
-                }
-
                 let character_order = Config::load_characters();
-                state.switch_to(
-                    target,
-                    &*wm,
-                    config.minimize_inactive,
-                    character_order.as_deref(),
-                )?;
+                run_direct(&*wm, &config, daemonless, |state| {
+                    state.switch_to(
+                        target,
+                        &*wm,
+                        config.minimize_inactive,
+                        character_order.as_deref(),
+                    )
+                })?;
+            } else if cmd == "switch" {
+                eprintln!("Usage: nicotine switch <N>   (clients are numbered from 1)");
+                std::process::exit(1);
             } else {
                 println!();
                 println!("🚬 N I C O T I N E 🚬");
@@ -345,13 +987,59 @@ fn main() -> Result<()> {
                 println!();
                 println!("Usage:");
                 println!("  nicotine start         - Start everything (daemon + overlay)");
-                println!("  nicotine stop          - Stop all Nicotine processes");
-                println!("  nicotine stack         - Stack all EVE windows");
+                println!(
+                    "                           --no-overlay/--overlay overrides show_overlay"
+                );
+                println!("                           --no-profile skips auto-applying a profile");
+                println!(
+                    "                           --foreground stays attached instead of daemonizing"
+                );
+                println!("  nicotine stop          - Stop the daemon and its overlay");
+                println!("  nicotine stack         - Stack all EVE windows (--layout grid|columns|cascade, --dry-run)");
+                println!("  nicotine restack       - Stack through the running daemon (falls back to stack)");
+                println!("  nicotine launch        - Start the clients in launch_commands, then stack them");
                 println!("  nicotine forward       - Cycle forward");
                 println!("  nicotine backward      - Cycle backward");
                 println!("  nicotine switch N      - Switch to client N (targeted cycling)");
+                println!("  nicotine switch-name C - Switch to character C's client");
+                println!("  nicotine next-attention - Jump to the next client demanding attention");
+                println!("  nicotine toggle        - Go back to the previously used client");
+                println!("  nicotine raise/lower   - Put every client above/below other windows");
+                println!(
+                    "  nicotine activate-under-cursor - Switch to the client under the mouse (X11)"
+                );
                 println!("  nicotine N             - Shorthand for switch N");
-                println!("  nicotine init-config   - Create default config.toml");
+                println!("  nicotine broadcast K   - Send X11 keycode K to every client");
+                println!("  nicotine characters    - Show characters.txt and which clients match");
+                println!("  nicotine monitors      - List connected monitors");
+                println!("  nicotine reload        - Make the daemon re-read config.toml");
+                println!(
+                    "                           cycle commands take --no-daemon to skip the daemon"
+                );
+                println!("  nicotine init-config   - Create default config (--format json|toml)");
+                println!("  nicotine install-service - Write a systemd user unit for the daemon (--force)");
+                println!("  nicotine profile load P - Apply profile P (re-applied on start)");
+                println!("  nicotine profile use P - Make profile P the main config.toml");
+                println!("  nicotine profile list  - List saved profiles");
+                println!("  nicotine selftest      - Verify cycling works end-to-end");
+                println!(
+                    "  nicotine doctor        - Check devices, tools and paths nicotine needs"
+                );
+                println!("  nicotine input-status  - Show which input devices the daemon uses");
+                println!(
+                    "  nicotine status        - Show the running daemon's clients and listeners"
+                );
+                println!("  nicotine list [--json] - List detected EVE clients");
+                println!("  nicotine logs [-f]     - Print (or follow) the daemon log");
+                println!("  nicotine version       - Show the version and which backend is in use");
+                println!();
+                println!(
+                    "Any command takes --profile P to run with profile P instead of config.toml"
+                );
+                println!(
+                    "--socket PATH to use a daemon on another socket (run several side by side)"
+                );
+                println!("and --verbose to log more detail to stderr");
                 println!();
                 println!("Advanced:");
                 println!("  nicotine daemon        - Start daemon only");
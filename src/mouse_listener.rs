@@ -1,41 +1,92 @@
-use crate::config::Config;
+use crate::config::{Config, Tilt};
 use crate::cycle_state::CycleState;
+use crate::daemon::ListenerStatus;
+use crate::input_access::{Debouncer, DeviceScan};
 use crate::window_manager::WindowManager;
 use anyhow::{Context, Result};
-use evdev::{Device, InputEventKind, Key};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use evdev::{Device, InputEventKind, Key, RelativeAxisType};
+use log::{debug, error, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 pub struct MouseListener {
-    config: Config,
+    config: Arc<RwLock<Config>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trigger {
+    Forward,
+    Backward,
+}
+
+/// Which way a mouse event cycles under the current bindings, if at all. Only
+/// button presses count, not releases or repeats.
+fn trigger_for(kind: InputEventKind, value: i32, config: &Config) -> Option<Trigger> {
+    let trigger = match kind {
+        InputEventKind::Key(key) if value == 1 => {
+            if key.code() == config.forward_button {
+                Trigger::Forward
+            } else if key.code() == config.backward_button {
+                Trigger::Backward
+            } else {
+                return None;
+            }
+        }
+        InputEventKind::RelAxis(RelativeAxisType::REL_HWHEEL) if value != 0 => {
+            let tilt = if value > 0 { Tilt::Right } else { Tilt::Left };
+            if config.forward_tilt == Some(tilt) {
+                Trigger::Forward
+            } else if config.backward_tilt == Some(tilt) {
+                Trigger::Backward
+            } else {
+                return None;
+            }
+        }
+        _ => return None,
+    };
+
+    Some(match (trigger, config.invert_mouse_cycling) {
+        (Trigger::Forward, true) => Trigger::Backward,
+        (Trigger::Backward, true) => Trigger::Forward,
+        (trigger, false) => trigger,
+    })
 }
 
 impl MouseListener {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Arc<RwLock<Config>>) -> Self {
         Self { config }
     }
 
-    /// Find mouse device by looking for devices with BTN_SIDE or BTN_EXTRA capabilities
+    /// Find mouse device by looking for devices with the configured buttons (or
+    /// BTN_SIDE/BTN_EXTRA), or a tilt wheel when tilt is bound
     /// If a device path is provided in the config, it will be used directly
-    fn find_mouse_device(configured_path: Option<&str>) -> Result<Device> {
+    fn find_mouse_device(config: &Config) -> Result<(Device, PathBuf)> {
         // Try configured path first
-        if let Some(path_str) = configured_path {
+        if let Some(path_str) = config.mouse_device_path.as_deref() {
             let path = Path::new(path_str);
             match Device::open(path) {
                 Ok(device) => {
-                    println!(
+                    info!(
                         "Using configured mouse device: {} ({})",
                         device.name().unwrap_or("Unknown"),
                         path.display()
                     );
-                    return Ok(device);
+                    // Still use it (the buttons may be remapped), but a wrong pin is a common mistake
+                    if !Self::has_triggers(&device, config) {
+                        warn!(
+                            "'{}' doesn't report the configured buttons or tilt; check mouse_device_path with evtest",
+                            path_str
+                        );
+                    }
+                    return Ok((device, path.to_path_buf()));
                 }
                 Err(e) => {
-                    eprintln!(
-                        "Warning: Failed to open configured mouse device '{}': {}",
+                    warn!(
+                        "Failed to open configured mouse device '{}': {}",
                         path_str, e
                     );
-                    eprintln!("Falling back to automatic device detection...");
+                    warn!("Falling back to automatic device detection...");
                 }
             }
         }
@@ -43,6 +94,7 @@ impl MouseListener {
         // Fall back to automatic detection
         let devices_path = Path::new("/dev/input");
 
+        let mut scan = DeviceScan::default();
         for entry in std::fs::read_dir(devices_path)? {
             let entry = entry?;
             let path = entry.path();
@@ -50,17 +102,16 @@ impl MouseListener {
             if let Some(filename) = path.file_name() {
                 if let Some(name) = filename.to_str() {
                     if name.starts_with("event") {
-                        if let Ok(device) = Device::open(&path) {
-                            // Check if device has mouse side buttons
-                            if device.supported_keys().is_some_and(|keys| {
-                                keys.contains(Key::BTN_SIDE) || keys.contains(Key::BTN_EXTRA)
-                            }) {
-                                println!(
+                        let opened = Device::open(&path);
+                        scan.record(&opened);
+                        if let Ok(device) = opened {
+                            if Self::has_triggers(&device, config) {
+                                info!(
                                     "Found mouse device: {} ({})",
                                     device.name().unwrap_or("Unknown"),
                                     path.display()
                                 );
-                                return Ok(device);
+                                return Ok((device, path));
                             }
                         }
                     }
@@ -68,7 +119,24 @@ impl MouseListener {
             }
         }
 
-        anyhow::bail!("No mouse device with side buttons found in /dev/input")
+        Err(scan.no_device_error("No mouse device with side buttons found in /dev/input"))
+    }
+
+    fn has_triggers(device: &Device, config: &Config) -> bool {
+        let buttons = [
+            Key::BTN_SIDE,
+            Key::BTN_EXTRA,
+            Key::new(config.forward_button),
+            Key::new(config.backward_button),
+        ];
+        let has_buttons = device
+            .supported_keys()
+            .is_some_and(|keys| buttons.iter().any(|&button| keys.contains(button)));
+        let tilt_bound = config.forward_tilt.is_some() || config.backward_tilt.is_some();
+        let has_tilt = device
+            .supported_relative_axes()
+            .is_some_and(|axes| axes.contains(RelativeAxisType::REL_HWHEEL));
+        has_buttons || (tilt_bound && has_tilt)
     }
 
     /// Run the mouse event listener in a background thread
@@ -76,29 +144,21 @@ impl MouseListener {
         &self,
         wm: Arc<dyn WindowManager>,
         state: Arc<Mutex<CycleState>>,
+        status: Arc<Mutex<ListenerStatus>>,
     ) -> Result<std::thread::JoinHandle<()>> {
-        if !self.config.enable_mouse_buttons {
+        if !self.config.read().unwrap().enable_mouse_buttons {
             anyhow::bail!("Mouse buttons are disabled in config");
         }
 
-        let forward_button = self.config.forward_button;
-        let backward_button = self.config.backward_button;
-        let mouse_device_path = self.config.mouse_device_path.clone();
-        let minimize_inactive = self.config.minimize_inactive;
-
-        let handle = std::thread::spawn(move || {
-            match Self::run_listener(
-                wm,
-                state,
-                forward_button,
-                backward_button,
-                mouse_device_path,
-                minimize_inactive,
-            ) {
-                Ok(_) => println!("Mouse listener stopped"),
-                Err(e) => eprintln!("Mouse listener error: {}", e),
-            }
-        });
+        let config = Arc::clone(&self.config);
+
+        let handle =
+            std::thread::spawn(
+                move || match Self::run_listener(wm, state, status, config) {
+                    Ok(_) => info!("Mouse listener stopped"),
+                    Err(e) => error!("Mouse listener error: {}", e),
+                },
+            );
 
         Ok(handle)
     }
@@ -106,40 +166,73 @@ impl MouseListener {
     fn run_listener(
         wm: Arc<dyn WindowManager>,
         state: Arc<Mutex<CycleState>>,
-        forward_button: u16,
-        backward_button: u16,
-        mouse_device_path: Option<String>,
-        minimize_inactive: bool,
+        status: Arc<Mutex<ListenerStatus>>,
+        config: Arc<RwLock<Config>>,
     ) -> Result<()> {
-        let mut device = Self::find_mouse_device(mouse_device_path.as_deref()).context(
-            "Failed to find mouse device. Make sure you have permission to read /dev/input/event*",
-        )?;
+        let (forward_button, backward_button, found) = {
+            let config = config.read().unwrap();
+            (
+                config.forward_button,
+                config.backward_button,
+                Self::find_mouse_device(&config).context("Failed to find mouse device"),
+            )
+        };
+
+        let (mut device, path) = match found {
+            Ok(found) => found,
+            Err(e) => {
+                status.lock().unwrap().error = Some(format!("{:#}", e));
+                return Err(e);
+            }
+        };
+
+        // Record the device so the daemon can report what it's listening to
+        {
+            let mut status = status.lock().unwrap();
+            status.device_path = Some(path.display().to_string());
+            status.device_name = device.name().map(String::from);
+        }
 
         // DON'T grab the device - we only want to passively listen to events
         // Grabbing would prevent normal mouse usage!
 
-        println!(
+        info!(
             "Listening for mouse buttons: forward={}, backward={}",
             forward_button, backward_button
         );
 
+        let mut debouncer = Debouncer::default();
+
         loop {
             for event in device.fetch_events()? {
-                if let InputEventKind::Key(key) = event.kind() {
-                    let code = key.code();
-
-                    // Only handle button press (value 1), ignore release (value 0)
-                    if event.value() == 1 {
-                        if code == forward_button {
-                            println!("Forward button pressed");
-                            if let Err(e) = Self::cycle_forward(&wm, &state, minimize_inactive) {
-                                eprintln!("Failed to cycle forward: {}", e);
-                            }
-                        } else if code == backward_button {
-                            println!("Backward button pressed");
-                            if let Err(e) = Self::cycle_backward(&wm, &state, minimize_inactive) {
-                                eprintln!("Failed to cycle backward: {}", e);
-                            }
+                // Read bindings per event so `nicotine reload` applies immediately
+                let (trigger, minimize_inactive, debounce) = {
+                    let config = config.read().unwrap();
+                    (
+                        trigger_for(event.kind(), event.value(), &config),
+                        config.minimize_inactive,
+                        Duration::from_millis(config.cycle_debounce_ms),
+                    )
+                };
+
+                let Some(trigger) = trigger else {
+                    continue;
+                };
+                if !debouncer.accept(Instant::now(), debounce) {
+                    continue;
+                }
+
+                match trigger {
+                    Trigger::Forward => {
+                        debug!("Forward button pressed");
+                        if let Err(e) = Self::cycle_forward(&wm, &state, minimize_inactive) {
+                            warn!("Failed to cycle forward: {}", e);
+                        }
+                    }
+                    Trigger::Backward => {
+                        debug!("Backward button pressed");
+                        if let Err(e) = Self::cycle_backward(&wm, &state, minimize_inactive) {
+                            warn!("Failed to cycle backward: {}", e);
                         }
                     }
                 }
@@ -179,3 +272,42 @@ impl MouseListener {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hwheel() -> InputEventKind {
+        InputEventKind::RelAxis(RelativeAxisType::REL_HWHEEL)
+    }
+
+    #[test]
+    fn test_buttons_and_tilt_trigger_cycling() {
+        let mut config = Config::for_display(1920, 1080);
+        config.forward_button = 277; // BTN_FORWARD
+        config.forward_tilt = Some(Tilt::Right);
+        config.backward_tilt = Some(Tilt::Left);
+
+        let forward = InputEventKind::Key(Key::new(277));
+        assert_eq!(trigger_for(forward, 1, &config), Some(Trigger::Forward));
+        // Releases and repeats don't cycle
+        assert_eq!(trigger_for(forward, 0, &config), None);
+        assert_eq!(trigger_for(forward, 2, &config), None);
+
+        assert_eq!(trigger_for(hwheel(), 1, &config), Some(Trigger::Forward));
+        assert_eq!(trigger_for(hwheel(), -1, &config), Some(Trigger::Backward));
+        let wheel = InputEventKind::RelAxis(RelativeAxisType::REL_WHEEL);
+        assert_eq!(trigger_for(wheel, 1, &config), None);
+
+        config.invert_mouse_cycling = true;
+        assert_eq!(trigger_for(forward, 1, &config), Some(Trigger::Backward));
+        assert_eq!(trigger_for(hwheel(), -1, &config), Some(Trigger::Forward));
+    }
+
+    #[test]
+    fn test_tilt_unbound_by_default() {
+        let config = Config::for_display(1920, 1080);
+        assert_eq!(trigger_for(hwheel(), 1, &config), None);
+        assert_eq!(trigger_for(hwheel(), -1, &config), None);
+    }
+}
@@ -1,18 +1,214 @@
+use crate::client_settle::ClientSettler;
+use crate::config::Config;
 use crate::cycle_state::CycleState;
-use crate::window_manager::WindowManager;
+use crate::daemon;
+use crate::window_manager::{
+    display_area, layout_targets, EveWindow, WindowGeometry, WindowManager,
+};
 use eframe::egui;
+use log::warn;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 pub struct OverlayApp {
     wm: Arc<dyn WindowManager>,
     state: Arc<Mutex<CycleState>>,
-    config: crate::config::Config,
+    config: Config,
     drag_start_window_pos: Option<egui::Pos2>,
     drag_accumulated: egui::Vec2,
     overlay_window_id: Option<u32>,
     last_sync: Instant,
+    settler: ClientSettler,
     last_index: usize,
+    saved_size: egui::Vec2,
+    pending_size: Option<(egui::Vec2, Instant)>,
+    /// Client positions and status, looked up off the UI thread
+    report: Arc<Mutex<ClientReport>>,
+    /// Client whose close button was pressed, waiting for confirmation
+    pending_close: Option<u32>,
+    /// Whether the daemon answered its last ping
+    daemon_alive: Arc<AtomicBool>,
+    position: OverlayPosition,
+    /// Profile the config came from, so drags and resizes are saved to it
+    profile: Option<String>,
+}
+
+/// Where the overlay window is right now, shared with the daemon so it can
+/// save a position the overlay didn't (the compositor moved it, say) on exit
+#[derive(Clone, Default)]
+pub struct OverlayPosition(Arc<Mutex<Option<egui::Pos2>>>);
+
+impl OverlayPosition {
+    /// Save the position to the config (or `profile`) if it's moved away from `saved`
+    pub fn save(&self, profile: Option<&str>, saved: (f32, f32)) -> anyhow::Result<()> {
+        match *self.0.lock().unwrap() {
+            Some(position) if (position - egui::pos2(saved.0, saved.1)).length() >= 1.0 => {
+                Config::update_overlay_position(profile, position.x, position.y)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where the clients are and which need attention, as last looked up
+#[derive(Default)]
+struct ClientReport {
+    /// Client positions for the mini-map
+    minimap: Vec<(u32, String, WindowGeometry)>,
+    /// Clients that are minimized or have drifted off their layout spot
+    status: HashMap<u32, ClientStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientStatus {
+    Minimized,
+    OffStack,
+}
+
+impl ClientStatus {
+    fn marker(self) -> &'static str {
+        match self {
+            ClientStatus::Minimized => " ▾",
+            ClientStatus::OffStack => " ↔",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            ClientStatus::Minimized => "Minimized",
+            ClientStatus::OffStack => "Off the stack area - RESTACK to put it back",
+        }
+    }
+}
+
+/// Space kept free below the client list for the restack button
+const BUTTON_AREA_HEIGHT: f32 = 44.0;
+
+/// Smallest size the overlay can be resized to
+const MIN_OVERLAY_SIZE: [f32; 2] = [180.0, 200.0];
+
+/// How long the size must stay unchanged before a resize is saved
+const RESIZE_SAVE_DELAY_MS: u128 = 1000;
+
+/// Height of the mini-map drawn above the restack button
+const MINIMAP_HEIGHT: f32 = 90.0;
+
+/// Client names longer than this are cut off in the list
+const MAX_TITLE_CHARS: usize = 20;
+/// How often the overlay checks that the daemon is still answering
+const DAEMON_PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Background lookup of client geometry and status for the overlay
+struct ClientRefresh {
+    wm: Arc<dyn WindowManager>,
+    state: Arc<Mutex<CycleState>>,
+    config: Config,
+    report: Arc<Mutex<ClientReport>>,
+    /// Per-character geometry from characters.txt, re-read when the file changes
+    character_geometry: Vec<(String, WindowGeometry)>,
+    characters_modified: Option<SystemTime>,
+}
+
+impl ClientRefresh {
+    fn run(mut self) {
+        loop {
+            self.refresh();
+            std::thread::sleep(Duration::from_millis(self.config.refresh_interval_ms));
+        }
+    }
+
+    fn refresh(&mut self) {
+        let modified = characters_modified();
+        if modified != self.characters_modified {
+            self.characters_modified = modified;
+            self.character_geometry = Config::load_character_geometry();
+        }
+
+        let windows = self.state.lock().unwrap().get_windows().to_vec();
+        let ids: Vec<u32> = windows.iter().map(|w| w.id).collect();
+        let geometries = self.wm.window_geometries(&ids).unwrap_or_default();
+
+        let minimap = if self.config.show_minimap {
+            windows
+                .iter()
+                .filter_map(|w| Some((w.id, w.title.clone(), *geometries.get(&w.id)?)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let status = self.client_status(&windows, &geometries);
+
+        *self.report.lock().unwrap() = ClientReport { minimap, status };
+    }
+
+    /// Check each client against where the configured layout would put it
+    fn client_status(
+        &self,
+        windows: &[EveWindow],
+        geometries: &HashMap<u32, WindowGeometry>,
+    ) -> HashMap<u32, ClientStatus> {
+        let config = &self.config;
+        let positioning = self.wm.supports_positioning();
+        let area = match self.wm.stack_monitor_area(config) {
+            Ok(Some(area)) => area,
+            _ => display_area(config),
+        };
+        let targets = layout_targets(
+            windows,
+            &self.character_geometry,
+            config,
+            config.layout_mode,
+            area,
+        );
+
+        windows
+            .iter()
+            .zip(targets)
+            .filter_map(|(window, target)| {
+                if self.wm.is_minimized(window.id).ok().flatten() == Some(true) {
+                    return Some((window.id, ClientStatus::Minimized));
+                }
+                let geometry = geometries.get(&window.id)?;
+                let drifted =
+                    positioning && !geometry.matches(&target, config.restack_tolerance_px);
+                drifted.then_some((window.id, ClientStatus::OffStack))
+            })
+            .collect()
+    }
+}
+
+fn characters_modified() -> Option<SystemTime> {
+    std::fs::metadata(Config::characters_path())
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// Read a font file, checking egui will be able to parse it: a font it can't
+/// read panics inside epaint
+fn read_font(path: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    check_font(&bytes)?;
+    Ok(bytes)
+}
+
+fn check_font(bytes: &[u8]) -> anyhow::Result<()> {
+    ab_glyph::FontRef::try_from_slice(bytes)?;
+    Ok(())
+}
+
+/// Compact overlay height: one line of text plus padding
+fn compact_height(config: &Config) -> f32 {
+    config.overlay_font_size.max(1.0) + 16.0
+}
+
+/// The first `max` characters of `s`, never splitting a multibyte character
+fn truncate_chars(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
 }
 
 impl OverlayApp {
@@ -20,7 +216,9 @@ impl OverlayApp {
         cc: &eframe::CreationContext<'_>,
         wm: Arc<dyn WindowManager>,
         state: Arc<Mutex<CycleState>>,
-        config: crate::config::Config,
+        config: Config,
+        position: OverlayPosition,
+        profile: Option<String>,
     ) -> Self {
         let mut fonts = egui::FontDefinitions::default();
 
@@ -31,6 +229,27 @@ impl OverlayApp {
             )),
         );
 
+        // A user font goes first; the embedded one still covers glyphs it lacks
+        let user_font =
+            config
+                .overlay_font_path
+                .as_deref()
+                .and_then(|path| match read_font(path) {
+                    Ok(bytes) => {
+                        fonts
+                            .font_data
+                            .insert("user_font".to_owned(), egui::FontData::from_owned(bytes));
+                        Some("user_font".to_owned())
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to load overlay font '{}', using the default: {}",
+                            path, e
+                        );
+                        None
+                    }
+                });
+
         fonts.font_data.insert(
             "logo_font".to_owned(),
             egui::FontData::from_static(include_bytes!("../assets/fonts/Marlboro.ttf")),
@@ -48,6 +267,16 @@ impl OverlayApp {
             .or_default()
             .insert(0, "jetbrains_mono".to_owned());
 
+        if let Some(name) = user_font {
+            for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+                fonts
+                    .families
+                    .entry(family)
+                    .or_default()
+                    .insert(0, name.clone());
+            }
+        }
+
         fonts
             .families
             .entry(egui::FontFamily::Name("logo".into()))
@@ -56,6 +285,44 @@ impl OverlayApp {
 
         cc.egui_ctx.set_fonts(fonts);
 
+        let font_size = config.overlay_font_size.max(1.0);
+        cc.egui_ctx.style_mut(|style| {
+            for text_style in [egui::TextStyle::Body, egui::TextStyle::Monospace] {
+                if let Some(font) = style.text_styles.get_mut(&text_style) {
+                    font.size = font_size;
+                }
+            }
+        });
+
+        let saved_size = egui::vec2(config.overlay_width, config.overlay_height);
+
+        // Settle windows the same way as the daemon so both sides agree on indices
+        let mut settler = ClientSettler::new(config.client_settle_ms);
+        if let Ok(windows) = wm.get_eve_windows() {
+            settler.seed(windows, Instant::now());
+        }
+
+        // Geometry and minimized lookups can be an IPC round trip or a process
+        // spawn per client, so they're kept off the UI thread too
+        let report = Arc::new(Mutex::new(ClientReport::default()));
+        let refresh = ClientRefresh {
+            wm: Arc::clone(&wm),
+            state: Arc::clone(&state),
+            config: config.clone(),
+            report: Arc::clone(&report),
+            character_geometry: Vec::new(),
+            characters_modified: None,
+        };
+        std::thread::spawn(move || refresh.run());
+
+        // Pinging can block briefly, so keep it off the UI thread
+        let daemon_alive = Arc::new(AtomicBool::new(false));
+        let alive_clone = Arc::clone(&daemon_alive);
+        std::thread::spawn(move || loop {
+            alive_clone.store(daemon::is_alive(), Ordering::Relaxed);
+            std::thread::sleep(DAEMON_PING_INTERVAL);
+        });
+
         Self {
             wm,
             state,
@@ -64,15 +331,187 @@ impl OverlayApp {
             drag_accumulated: egui::Vec2::ZERO,
             overlay_window_id: None,
             last_sync: Instant::now(),
+            settler,
             last_index: 0,
+            saved_size,
+            pending_size: None,
+            report,
+            pending_close: None,
+            daemon_alive,
+            position,
+            profile,
+        }
+    }
+
+    /// Draw the display scaled down with each client as a labeled rectangle.
+    /// Clicking a rectangle activates that client.
+    fn show_minimap(&self, ui: &mut egui::Ui, active_id: Option<u32>, colors: MinimapColors) {
+        let (area, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), MINIMAP_HEIGHT),
+            egui::Sense::click(),
+        );
+
+        let display = egui::vec2(
+            self.config.display_width as f32,
+            self.config.display_height as f32,
+        );
+        let scale = (area.width() / display.x).min(area.height() / display.y);
+        let screen = egui::Rect::from_center_size(area.center(), display * scale);
+
+        let painter = ui.painter_at(area);
+        painter.rect_stroke(screen, 0.0, egui::Stroke::new(1.0, colors.outline));
+
+        let report = self.report.lock().unwrap();
+        let rects: Vec<(u32, &str, egui::Rect)> = report
+            .minimap
+            .iter()
+            .map(|(id, title, geometry)| {
+                let min = screen.min + egui::vec2(geometry.x as f32, geometry.y as f32) * scale;
+                let size = egui::vec2(geometry.width as f32, geometry.height as f32) * scale;
+                (*id, title.as_str(), egui::Rect::from_min_size(min, size))
+            })
+            .collect();
+
+        for &(id, title, rect) in &rects {
+            let fill = if Some(id) == active_id {
+                colors.active
+            } else {
+                colors.inactive
+            };
+            painter.rect_filled(rect, 1.0, fill);
+            painter.rect_stroke(rect, 1.0, egui::Stroke::new(1.0, colors.outline));
+
+            let label: String = title.chars().take(8).collect();
+            ui.painter_at(rect.intersect(area)).text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                label,
+                egui::FontId::proportional(9.0),
+                colors.label,
+            );
         }
+
+        if response.clicked() {
+            let pointer = response.interact_pointer_pos();
+            // Later rectangles are drawn on top, so they win overlapping clicks
+            let hit = pointer.and_then(|pos| rects.iter().rev().find(|(_, _, r)| r.contains(pos)));
+            if let Some(&(id, _, _)) = hit {
+                self.switch_to_window(id);
+            }
+        }
+    }
+
+    /// Activate a client picked in the overlay, off the UI thread
+    fn switch_to_window(&self, id: u32) {
+        let wm_clone = Arc::clone(&self.wm);
+        let state_clone = Arc::clone(&self.state);
+        let minimize_inactive = self.config.minimize_inactive;
+        std::thread::spawn(move || {
+            // Go through the cycle state so minimize_inactive and the current index apply here too
+            let mut state = state_clone.lock().unwrap();
+            if let Ok(active) = wm_clone.get_active_window() {
+                state.sync_with_active(active);
+            }
+            let target = state.get_windows().iter().position(|w| w.id == id);
+            let result = match target {
+                Some(index) => state.switch_to(index + 1, &*wm_clone, minimize_inactive, None),
+                None => wm_clone.activate_window(id),
+            };
+            if let Err(e) = result {
+                warn!("Failed to activate window: {}", e);
+            }
+        });
     }
+
+    /// Close a client from the overlay, off the UI thread
+    fn close_window(&self, id: u32) {
+        let wm_clone = Arc::clone(&self.wm);
+        std::thread::spawn(move || {
+            if let Err(e) = wm_clone.close_window(id) {
+                warn!("Failed to close window: {}", e);
+            }
+        });
+    }
+
+    /// Click-through layout: just the current client's name, no controls
+    fn show_compact(
+        &self,
+        ctx: &egui::Context,
+        background: egui::Color32,
+        border: egui::Color32,
+        text: egui::Color32,
+    ) {
+        let title = {
+            let state = self.state.lock().unwrap();
+            state
+                .current_window()
+                .map(|w| truncate_chars(&w.title, MAX_TITLE_CHARS).to_string())
+        };
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame::none()
+                    .fill(background)
+                    .inner_margin(egui::Margin::symmetric(8.0, 0.0))
+                    .stroke(egui::Stroke::new(2.0, border)),
+            )
+            .show(ctx, |ui| {
+                ui.centered_and_justified(|ui| {
+                    ui.label(
+                        egui::RichText::new(title.as_deref().unwrap_or("No clients"))
+                            .size(self.config.overlay_font_size.max(1.0))
+                            .strong()
+                            .color(text),
+                    );
+                });
+            });
+    }
+
+    /// Persist the overlay size once the user has finished resizing it
+    fn track_resize(&mut self, ctx: &egui::Context) {
+        let Some(mut size) = ctx.input(|i| i.viewport().inner_rect).map(|r| r.size()) else {
+            return;
+        };
+        // The height follows the client count, so only a user's width change is kept
+        if self.config.overlay_auto_height {
+            size.y = self.saved_size.y;
+        }
+
+        let changed = (size - self.saved_size).length() >= 1.0;
+        match self.pending_size {
+            Some((pending, since)) if (size - pending).length() < 1.0 => {
+                if since.elapsed().as_millis() >= RESIZE_SAVE_DELAY_MS {
+                    self.pending_size = None;
+                    self.saved_size = size;
+                    if let Err(e) =
+                        Config::update_overlay_size(self.profile.as_deref(), size.x, size.y)
+                    {
+                        warn!("Failed to save overlay size: {}", e);
+                    }
+                }
+            }
+            _ if changed => self.pending_size = Some((size, Instant::now())),
+            _ => self.pending_size = None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MinimapColors {
+    active: egui::Color32,
+    inactive: egui::Color32,
+    outline: egui::Color32,
+    label: egui::Color32,
 }
 
 impl eframe::App for OverlayApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Request repaint for smooth updates
-        ctx.request_repaint();
+        // Redraw at the configured rate so index changes show up without input
+        let fps = self.config.overlay_fps.max(1);
+        ctx.request_repaint_after(std::time::Duration::from_secs(1) / fps);
+
+        if let Some(rect) = ctx.input(|i| i.viewport().outer_rect) {
+            *self.position.0.lock().unwrap() = Some(rect.min);
+        }
 
         // Read current index from file (instant, no process spawning)
         if let Some(index) = CycleState::read_index_from_file() {
@@ -85,37 +524,41 @@ impl eframe::App for OverlayApp {
 
         // Periodic full sync for window list updates (new clients, etc)
         let now = Instant::now();
-        if now.duration_since(self.last_sync).as_millis() >= 500 {
+        if now.duration_since(self.last_sync).as_millis()
+            >= u128::from(self.config.refresh_interval_ms)
+        {
             self.last_sync = now;
 
             if let Ok(windows) = self.wm.get_eve_windows() {
+                let windows = self.settler.observe(windows, now);
                 let mut state = self.state.lock().unwrap();
                 state.update_windows(windows);
-
-                // Resize window based on client count
-                let client_count = state.get_windows().len();
-                let base_height = 320.0_f32;
-                let per_client = 20.0_f32;
-                let min_clients = 10;
-                let extra_clients = client_count.saturating_sub(min_clients);
-                let target_height = base_height + (extra_clients as f32 * per_client);
-
-                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(
-                    220.0,
-                    target_height,
-                )));
             }
         }
 
-        let red = egui::Color32::from_rgb(196, 30, 58);
-        let gold = egui::Color32::from_rgb(180, 155, 105);
-        let cream = egui::Color32::from_rgb(252, 250, 242);
-        let black = egui::Color32::from_rgb(30, 30, 30);
+        let theme = &self.config.overlay;
+        let rgb = |[r, g, b]: [u8; 3]| egui::Color32::from_rgb(r, g, b);
+        let [r, g, b, a] = theme.background_rgba;
+        let background = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+        let red = rgb(theme.accent_rgb);
+        let gold = rgb(theme.border_rgb);
+        // Text on the accent color uses the background color, but always opaque
+        let cream = rgb([r, g, b]);
+        let black = rgb(theme.text_rgb);
+        let amber = rgb(theme.attention_rgb);
+
+        // The window ignores the mouse, so there's nothing to click, drag or resize
+        if self.config.overlay_click_through {
+            self.show_compact(ctx, background, gold, red);
+            return;
+        }
+        self.track_resize(ctx);
 
+        let mut fit_height = None;
         let _panel_response = egui::CentralPanel::default()
             .frame(
                 egui::Frame::none()
-                    .fill(cream)
+                    .fill(background)
                     .rounding(0.0)
                     .inner_margin(0.0)
                     .stroke(egui::Stroke::new(2.0, gold)),
@@ -129,6 +572,29 @@ impl eframe::App for OverlayApp {
                     red,
                 );
 
+                // Daemon status dot in the corner of the bar
+                let alive = self.daemon_alive.load(Ordering::Relaxed);
+                let dot = rect.right_top() + egui::vec2(-14.0, 14.0);
+                let fill = if alive {
+                    egui::Color32::from_rgb(46, 160, 67)
+                } else {
+                    red
+                };
+                ui.painter()
+                    .circle(dot, 5.0, fill, egui::Stroke::new(1.5, cream));
+                let dot_rect = egui::Rect::from_center_size(dot, egui::vec2(14.0, 14.0));
+                // interact rather than allocate, so the header layout isn't shifted
+                ui.interact(
+                    dot_rect,
+                    ui.id().with("daemon_status"),
+                    egui::Sense::hover(),
+                )
+                .on_hover_text(if alive {
+                    "Daemon running"
+                } else {
+                    "Daemon not running"
+                });
+
                 // NICOTINE text in red bar
                 ui.add_space(10.0);
                 ui.vertical_centered(|ui| {
@@ -142,37 +608,130 @@ impl eframe::App for OverlayApp {
 
                 ui.add_space(16.0);
 
-                // Client list
-                egui::Frame::none()
-                    .inner_margin(egui::Margin::symmetric(16.0, 0.0))
+                // Client list (scrolls when the overlay is too short to show everyone)
+                let minimap_height = if self.config.show_minimap {
+                    MINIMAP_HEIGHT + 8.0
+                } else {
+                    0.0
+                };
+                let list_height =
+                    (ui.available_height() - BUTTON_AREA_HEIGHT - minimap_height).max(0.0);
+                let mut clicked = None;
+                let mut close_clicked = None;
+                let mut close_confirmed = None;
+                let mut close_cancelled = false;
+                let list = egui::ScrollArea::vertical()
+                    .max_height(list_height)
+                    .auto_shrink([false, true])
                     .show(ui, |ui| {
-                        let state = self.state.lock().unwrap();
-                        let windows = state.get_windows();
-                        let current_index = state.get_current_index();
-
-                        for (i, window) in windows.iter().enumerate() {
-                            let is_active = i == current_index;
-                            let display_title = &window.title[..window.title.len().min(20)];
-
-                            let text_color = if is_active { red } else { black };
-                            let prefix = if is_active { "▸ " } else { "  " };
-
-                            ui.colored_label(
-                                text_color,
-                                egui::RichText::new(format!("{}{}", prefix, display_title))
-                                    .size(13.0)
-                                    .strong(),
-                            );
-                            ui.add_space(2.0);
-                        }
-
-                        if windows.is_empty() {
-                            ui.add_space(10.0);
-                            ui.vertical_centered(|ui| {
-                                ui.colored_label(gold, "No clients");
+                        egui::Frame::none()
+                            .inner_margin(egui::Margin::symmetric(16.0, 0.0))
+                            .show(ui, |ui| {
+                                let state = self.state.lock().unwrap();
+                                let windows = state.get_windows();
+                                let current_index = state.get_current_index();
+
+                                for (i, window) in windows.iter().enumerate() {
+                                    let is_active = i == current_index;
+                                    let display_title =
+                                        truncate_chars(&window.title, MAX_TITLE_CHARS);
+
+                                    let text_color = if is_active {
+                                        red
+                                    } else if window.demands_attention {
+                                        amber
+                                    } else {
+                                        black
+                                    };
+                                    let prefix = if is_active {
+                                        "▸ "
+                                    } else if window.demands_attention {
+                                        "! "
+                                    } else {
+                                        "  "
+                                    };
+
+                                    let status =
+                                        self.report.lock().unwrap().status.get(&window.id).copied();
+                                    let marker = status.map_or("", ClientStatus::marker);
+
+                                    // Clicking an entry switches to that client
+                                    let text = egui::RichText::new(format!(
+                                        "{}{}{}",
+                                        prefix, display_title, marker
+                                    ))
+                                    .size(self.config.overlay_font_size.max(1.0))
+                                    .strong()
+                                    .color(text_color);
+                                    ui.horizontal(|ui| {
+                                        let mut response = ui
+                                            .add(egui::Label::new(text).sense(egui::Sense::click()))
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand);
+                                        if let Some(status) = status {
+                                            response = response.on_hover_text(status.description());
+                                        }
+                                        if response.clicked() {
+                                            clicked = Some(window.id);
+                                        }
+
+                                        ui.with_layout(
+                                            egui::Layout::right_to_left(egui::Align::Center),
+                                            |ui| {
+                                                let close = egui::Button::new(
+                                                    egui::RichText::new("×").color(black),
+                                                )
+                                                .frame(false);
+                                                if ui
+                                                    .add(close)
+                                                    .on_hover_text("Close this client")
+                                                    .clicked()
+                                                {
+                                                    close_clicked = Some(window.id);
+                                                }
+                                            },
+                                        );
+                                    });
+
+                                    if self.pending_close == Some(window.id) {
+                                        ui.horizontal(|ui| {
+                                            ui.colored_label(red, "Close this client?");
+                                            if ui.small_button("Close").clicked() {
+                                                close_confirmed = Some(window.id);
+                                            }
+                                            if ui.small_button("Cancel").clicked() {
+                                                close_cancelled = true;
+                                            }
+                                        });
+                                    }
+                                    ui.add_space(2.0);
+                                }
+
+                                if windows.is_empty() {
+                                    ui.add_space(10.0);
+                                    ui.vertical_centered(|ui| {
+                                        ui.colored_label(gold, "No clients");
+                                    });
+                                }
                             });
-                        }
                     });
+                if let Some(id) = clicked {
+                    self.switch_to_window(id);
+                }
+                if let Some(id) = close_clicked {
+                    if self.config.confirm_close {
+                        self.pending_close = Some(id);
+                    } else {
+                        self.close_window(id);
+                    }
+                }
+                if let Some(id) = close_confirmed {
+                    self.close_window(id);
+                }
+                if close_confirmed.is_some() || close_cancelled {
+                    self.pending_close = None;
+                }
+                // Everything but the list keeps its height; the list gets exactly its rows
+                fit_height = Some(ctx.screen_rect().height() - list_height + list.content_size.y);
 
                 // Bottom button
                 ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
@@ -183,23 +742,62 @@ impl eframe::App for OverlayApp {
                             .fill(red)
                             .rounding(2.0);
 
-                    if ui.add(button).clicked() {
+                    let restack = self.wm.supports_positioning() && ui.add(button).clicked();
+                    if restack {
                         let wm_clone = Arc::clone(&self.wm);
                         let config = self.config.clone();
                         std::thread::spawn(move || {
+                            let overrides = Config::load_character_geometry();
                             if let Ok(windows) = wm_clone.get_eve_windows() {
-                                let _ = wm_clone.stack_windows(&windows, &config);
+                                let _ = wm_clone.layout_windows(
+                                    &windows,
+                                    &config,
+                                    config.layout_mode,
+                                    &overrides,
+                                );
                             }
                         });
                     }
 
                     ui.add_space(6.0);
+
+                    if self.config.show_minimap {
+                        let active_id = {
+                            let state = self.state.lock().unwrap();
+                            state.current_window().map(|w| w.id)
+                        };
+                        let colors = MinimapColors {
+                            active: red,
+                            inactive: black,
+                            outline: gold,
+                            label: cream,
+                        };
+                        egui::Frame::none()
+                            .inner_margin(egui::Margin::symmetric(16.0, 0.0))
+                            .show(ui, |ui| self.show_minimap(ui, active_id, colors));
+                        ui.add_space(8.0);
+                    }
                 });
             });
 
+        if self.config.overlay_auto_height {
+            if let Some(height) = fit_height {
+                let current = ctx.screen_rect().size();
+                let height = height.max(MIN_OVERLAY_SIZE[1]);
+                if (height - current.y).abs() >= 1.0 {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(
+                        current.x, height,
+                    )));
+                }
+            }
+        }
+
         // Handle dragging with middle mouse button
-        // Note: Overlay dragging is X11-only. On Wayland, use your compositor's window
-        // management features to position the overlay window.
+        // Note: Backends that can't position windows leave placing the overlay
+        // to the compositor's own window management
+        if !self.wm.supports_positioning() {
+            return;
+        }
         let middle_down = ctx.input(|i| i.pointer.button_down(egui::PointerButton::Middle));
 
         if middle_down {
@@ -234,13 +832,32 @@ impl eframe::App for OverlayApp {
                 }
             }
 
-            ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+            if self.overlay_window_id.is_some() {
+                ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+            }
         } else {
+            // Remember where the overlay was dropped, once per drag
+            if let (Some(start_window), Some(_)) =
+                (self.drag_start_window_pos, self.overlay_window_id)
+            {
+                if self.drag_accumulated.length() > 0.0 {
+                    let position = start_window + self.drag_accumulated;
+                    if let Err(e) = Config::update_overlay_position(
+                        self.profile.as_deref(),
+                        position.x,
+                        position.y,
+                    ) {
+                        warn!("Failed to save overlay position: {}", e);
+                    }
+                }
+            }
+
             // Reset drag state when button is released
             self.drag_start_window_pos = None;
             self.drag_accumulated = egui::Vec2::ZERO;
 
-            if ctx.input(|i| i.pointer.hover_pos()).is_some() {
+            // Only advertise dragging once the backend has found our own window
+            if self.overlay_window_id.is_some() && ctx.input(|i| i.pointer.hover_pos()).is_some() {
                 ctx.set_cursor_icon(egui::CursorIcon::Grab);
             }
         }
@@ -252,17 +869,30 @@ pub fn run_overlay(
     state: Arc<Mutex<CycleState>>,
     overlay_x: f32,
     overlay_y: f32,
-    config: crate::config::Config,
+    config: Config,
+    position: OverlayPosition,
+    profile: Option<String>,
 ) -> Result<(), eframe::Error> {
+    let click_through = config.overlay_click_through;
+    let (size, min_size) = if click_through {
+        let compact = [config.overlay_width, compact_height(&config)];
+        (compact, compact)
+    } else {
+        (
+            [config.overlay_width, config.overlay_height],
+            MIN_OVERLAY_SIZE,
+        )
+    };
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([220.0, 320.0])
-            .with_min_inner_size([220.0, 320.0])
+            .with_inner_size(size)
+            .with_min_inner_size(min_size)
             .with_position([overlay_x, overlay_y])
             .with_decorations(false)
             .with_always_on_top()
             .with_transparent(true)
-            .with_resizable(true),
+            .with_resizable(!click_through)
+            .with_mouse_passthrough(click_through),
         ..Default::default()
     };
 
@@ -284,7 +914,37 @@ pub fn run_overlay(
                     }
                 }
             });
-            Ok(Box::new(OverlayApp::new(cc, wm, state, config)))
+            Ok(Box::new(OverlayApp::new(
+                cc, wm, state, config, position, profile,
+            )))
         }),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_font_rejects_non_fonts() {
+        let font = include_bytes!("../assets/fonts/JetBrainsMono-Regular.ttf");
+        assert!(check_font(font).is_ok());
+        assert!(check_font(b"not a font").is_err());
+    }
+
+    #[test]
+    fn test_truncate_chars_respects_multibyte_boundaries() {
+        assert_eq!(truncate_chars("Alpha", 20), "Alpha");
+        assert_eq!(truncate_chars("Alpha Bravo", 5), "Alpha");
+        // Byte 20 falls inside a character for both of these
+        assert_eq!(
+            truncate_chars("星之彼方的探索者和他的朋友们", 5),
+            "星之彼方的"
+        );
+        assert_eq!(truncate_chars("🚀🚀🚀🚀🚀🚀 Pilot", 6), "🚀🚀🚀🚀🚀🚀");
+        assert_eq!(
+            truncate_chars("Пилот Космического Корабля", 20),
+            "Пилот Космического К"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use nix::unistd::getuid;
+use std::fs::DirBuilder;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Socket chosen with `--socket`, so several daemons can run side by side
+static SOCKET_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Per-user directory for runtime files: `$XDG_RUNTIME_DIR/nicotine`, or
+/// `/tmp/nicotine-$UID` when that isn't set. Created with mode 0700.
+pub fn runtime_dir() -> Result<PathBuf> {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime) if !runtime.is_empty() => {
+            let dir = PathBuf::from(runtime).join("nicotine");
+            let _ = DirBuilder::new().recursive(true).mode(0o700).create(&dir);
+            Ok(dir)
+        }
+        _ => {
+            let dir = PathBuf::from(format!("/tmp/nicotine-{}", getuid()));
+            let _ = DirBuilder::new().mode(0o700).create(&dir);
+            check_private(&dir, getuid().as_raw())?;
+            Ok(dir)
+        }
+    }
+}
+
+/// Anyone can create `/tmp/nicotine-$UID` first, so only use it if it's a
+/// directory `uid` owns that nobody else can get into
+fn check_private(dir: &Path, uid: u32) -> Result<()> {
+    let metadata = std::fs::symlink_metadata(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    if !metadata.is_dir() {
+        anyhow::bail!("{} is not a directory", dir.display());
+    }
+    if metadata.uid() != uid {
+        anyhow::bail!("{} is owned by another user", dir.display());
+    }
+    if metadata.mode() & 0o777 != 0o700 {
+        anyhow::bail!(
+            "{} has mode {:o}, expected 700",
+            dir.display(),
+            metadata.mode() & 0o777
+        );
+    }
+    Ok(())
+}
+
+/// Use `path` as the daemon socket for the rest of this process
+pub fn set_socket_path(path: PathBuf) {
+    let _ = SOCKET_OVERRIDE.set(path);
+}
+
+/// Unix socket the daemon listens for commands on
+pub fn socket_path() -> Result<PathBuf> {
+    match SOCKET_OVERRIDE.get() {
+        Some(path) => Ok(path.clone()),
+        None => Ok(runtime_dir()?.join("nicotine.sock")),
+    }
+}
+
+/// Holds the daemon's pid, for `stop` and `SIGHUP`. Kept next to an
+/// overridden socket so each daemon has its own.
+pub fn pid_file() -> Result<PathBuf> {
+    match SOCKET_OVERRIDE.get() {
+        Some(socket) => Ok(socket.with_extension("pid")),
+        None => Ok(runtime_dir()?.join("nicotine.pid")),
+    }
+}
+
+/// Serializes daemonless cycle commands fired in quick succession
+pub fn cycle_lock_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("cycle.lock"))
+}
+
+/// Where the detached daemon writes its output
+pub fn log_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("nicotine.log"))
+}
+
+/// Cycle state shared between daemonless invocations
+pub fn state_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("cycle-state.json"))
+}
+
+/// Index of the current client, for the overlay's highlight
+pub fn index_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join("cycle-index"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_check_private_wants_an_owned_0700_directory() {
+        let parent = tempfile::tempdir().unwrap();
+        let dir = parent.path().join("nicotine");
+        DirBuilder::new().mode(0o700).create(&dir).unwrap();
+        let uid = getuid().as_raw();
+
+        assert!(check_private(&dir, uid).is_ok());
+        assert!(check_private(&dir, uid + 1).is_err());
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check_private(&dir, uid).is_err());
+
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(check_private(&file, uid).is_err());
+    }
+}
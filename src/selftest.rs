@@ -0,0 +1,80 @@
+use crate::config::Config;
+use crate::cycle_state::CycleState;
+use crate::window_manager::WindowManager;
+use anyhow::Result;
+use std::time::Duration;
+
+/// Time given to the window manager to apply an activation before checking it
+const SETTLE_MS: u64 = 300;
+
+fn report(step: &str, passed: bool) -> bool {
+    if passed {
+        println!("  ✓ {}", step);
+    } else {
+        println!("  ✗ {}", step);
+    }
+    passed
+}
+
+/// Cycle forward and back through the real window manager, verifying each
+/// activation actually landed. Returns Ok(true) if every step passed.
+pub fn run(wm: &dyn WindowManager, config: &Config) -> Result<bool> {
+    println!("Running cycle self-test...");
+
+    let windows = wm.get_eve_windows()?;
+    let count = windows.len();
+    if !report(
+        &format!("Detected {} EVE clients (need at least 2)", count),
+        count >= 2,
+    ) {
+        return Ok(false);
+    }
+
+    let original_active = wm.get_active_window()?;
+
+    // Grouping options are left off so the expected targets are deterministic
+    let mut state = CycleState::new();
+    state.update_windows(windows);
+    state.sync_with_active(original_active);
+
+    let start_index = state.get_current_index();
+    let start = state.get_windows()[start_index].clone();
+    let next = state.get_windows()[(start_index + 1) % count].clone();
+
+    report(
+        &format!("Starting from '{}' (0x{:08x})", start.title, start.id),
+        true,
+    );
+
+    state.cycle_forward(wm, config.minimize_inactive)?;
+    std::thread::sleep(Duration::from_millis(SETTLE_MS));
+    let active = wm.get_active_window()?;
+    let forward_ok = report(
+        &format!(
+            "Forward cycle activated '{}' (expected 0x{:08x}, got 0x{:08x})",
+            next.title, next.id, active
+        ),
+        active == next.id,
+    );
+
+    state.cycle_backward(wm, config.minimize_inactive)?;
+    std::thread::sleep(Duration::from_millis(SETTLE_MS));
+    let active = wm.get_active_window()?;
+    let backward_ok = report(
+        &format!(
+            "Backward cycle restored '{}' (expected 0x{:08x}, got 0x{:08x})",
+            start.title, start.id, active
+        ),
+        active == start.id,
+    );
+
+    let passed = forward_ok && backward_ok;
+    println!();
+    if passed {
+        println!("✓ Cycling works");
+    } else {
+        println!("✗ Cycling doesn't work - activations are not reaching the window manager");
+    }
+
+    Ok(passed)
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Session variables the backends need to find the display server or compositor.
+/// A user service doesn't inherit them from the desktop, so they're captured here.
+const SESSION_VARS: &[&str] = &[
+    "DISPLAY",
+    "XAUTHORITY",
+    "WAYLAND_DISPLAY",
+    "XDG_SESSION_TYPE",
+    "XDG_CURRENT_DESKTOP",
+    "SWAYSOCK",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "DBUS_SESSION_BUS_ADDRESS",
+];
+
+pub fn unit_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context("Could not determine config directory")?;
+    Ok(config_dir.join("systemd/user/nicotine.service"))
+}
+
+/// Write a systemd user unit running `nicotine daemon` with the current session's
+/// environment. Returns where it was written.
+pub fn install(force: bool) -> Result<PathBuf> {
+    let path = unit_path()?;
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists (pass --force to overwrite it)",
+            path.display()
+        );
+    }
+
+    let exe = std::env::current_exe().context("Could not find the nicotine executable")?;
+    let env: Vec<(String, String)> = SESSION_VARS
+        .iter()
+        .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+        .collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, unit_file(&exe, &env))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn unit_file(exe: &Path, env: &[(String, String)]) -> String {
+    let mut unit = String::from(
+        "[Unit]\n\
+         Description=Nicotine EVE Online multiboxing daemon\n\
+         PartOf=graphical-session.target\n\
+         After=graphical-session.target\n\
+         \n\
+         [Service]\n",
+    );
+    unit.push_str(&format!(
+        "ExecStart={} daemon\n",
+        escape(&exe.display().to_string())
+    ));
+    unit.push_str("ExecReload=/bin/kill -HUP $MAINPID\n");
+    unit.push_str("Restart=on-failure\n");
+    unit.push_str("RestartSec=2\n");
+    if !env.is_empty() {
+        unit.push_str(
+            "# Captured by `nicotine install-service`; re-run it if your session changes\n",
+        );
+    }
+    for (name, value) in env {
+        unit.push_str(&format!(
+            "Environment=\"{}={}\"\n",
+            name,
+            escape(value).replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    unit.push_str("\n[Install]\nWantedBy=graphical-session.target\n");
+    unit
+}
+
+/// systemd expands `%` specifiers in unit values
+fn escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_file_runs_daemon_with_session_env() {
+        let env = vec![
+            ("DISPLAY".to_string(), ":0".to_string()),
+            (
+                "XAUTHORITY".to_string(),
+                "/run/user/1000/%x \"auth\"".to_string(),
+            ),
+        ];
+        let unit = unit_file(Path::new("/usr/bin/nicotine"), &env);
+
+        assert!(unit.contains("ExecStart=/usr/bin/nicotine daemon\n"));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.contains("After=graphical-session.target\n"));
+        assert!(unit.contains("Environment=\"DISPLAY=:0\"\n"));
+        assert!(unit.contains("Environment=\"XAUTHORITY=/run/user/1000/%%x \\\"auth\\\"\"\n"));
+        assert!(unit.ends_with("WantedBy=graphical-session.target\n"));
+    }
+}
@@ -1,21 +1,93 @@
+use crate::config::Config;
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const GITHUB_API_URL: &str = "https://api.github.com/repos/isomerc/nicotine/releases/latest";
 const TIMEOUT_SECS: u64 = 5;
+/// GitHub is asked at most this often; in between the cached answer is used
+const CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct GithubRelease {
     tag_name: String,
     html_url: String,
 }
 
-/// Checks GitHub for a newer release version
+/// The last time GitHub was asked, and what it said (None if the check failed)
+#[derive(Debug, Serialize, Deserialize)]
+struct UpdateCache {
+    checked_at: u64,
+    release: Option<GithubRelease>,
+}
+
+/// Checks GitHub for a newer release version, at most once per day
 /// Returns Ok(Some((new_version, url))) if an update is available
 /// Returns Ok(None) if current version is up to date or on error
 pub fn check_for_updates() -> Result<Option<(String, String)>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let release = match read_cache() {
+        Some(cache) if is_fresh(cache.checked_at, now) => cache.release,
+        _ => {
+            let fetched = fetch_latest_release();
+            // Failed checks are recorded too, so being offline doesn't mean retrying every start
+            write_cache(&UpdateCache {
+                checked_at: now,
+                release: fetched.as_ref().ok().cloned().flatten(),
+            });
+            fetched?
+        }
+    };
+
+    let Some(release) = release else {
+        return Ok(None);
+    };
+
+    // Extract version from tag (e.g., "v0.2.1" -> "0.2.1")
+    let latest_version = release.tag_name.trim_start_matches('v');
+
+    // Compare versions
+    if is_newer_version(latest_version, CURRENT_VERSION)? {
+        Ok(Some((latest_version.to_string(), release.html_url)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn cache_path() -> PathBuf {
+    Config::config_dir().join("update-check.json")
+}
+
+fn read_cache() -> Option<UpdateCache> {
+    let contents = std::fs::read_to_string(cache_path()).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_cache(cache: &UpdateCache) {
+    let path = cache_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string(cache) {
+        let _ = std::fs::write(path, json);
+    }
+}
+
+/// Whether a check made at `checked_at` is recent enough to reuse at `now`.
+/// A timestamp in the future (clock changed) counts as stale.
+fn is_fresh(checked_at: u64, now: u64) -> bool {
+    now >= checked_at && now - checked_at < CHECK_INTERVAL_SECS
+}
+
+/// Fetches the latest release from GitHub; Ok(None) on HTTP errors
+fn fetch_latest_release() -> Result<Option<GithubRelease>> {
     // Build HTTP client with timeout
     let client = reqwest::blocking::Client::builder()
         .timeout(Duration::from_secs(TIMEOUT_SECS))
@@ -37,14 +109,40 @@ pub fn check_for_updates() -> Result<Option<(String, String)>> {
         .json()
         .context("Failed to parse GitHub API response")?;
 
-    // Extract version from tag (e.g., "v0.2.1" -> "0.2.1")
-    let latest_version = release.tag_name.trim_start_matches('v');
+    Ok(Some(release))
+}
 
-    // Compare versions
-    if is_newer_version(latest_version, CURRENT_VERSION)? {
-        Ok(Some((latest_version.to_string(), release.html_url)))
-    } else {
-        Ok(None)
+/// A semantic version. Build metadata is dropped, since it doesn't affect precedence.
+#[derive(Debug, PartialEq, Eq)]
+struct Version {
+    core: (u32, u32, u32),
+    pre: Vec<PreRelease>,
+}
+
+/// One dot-separated pre-release identifier; numeric ones sort before alphanumeric
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PreRelease {
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core.cmp(&other.core).then_with(|| {
+            // A pre-release is older than the release it leads up to
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            }
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -57,9 +155,31 @@ fn is_newer_version(latest: &str, current: &str) -> Result<bool> {
     Ok(latest_parts > current_parts)
 }
 
-/// Parses a version string like "0.2.1" into (major, minor, patch)
-fn parse_version(version: &str) -> Result<(u32, u32, u32)> {
-    let parts: Vec<&str> = version.split('.').collect();
+/// Parses a version string like "0.2.1", "0.3.0-rc.1" or "0.3.0+linux"
+fn parse_version(version: &str) -> Result<Version> {
+    let without_build = version.split_once('+').map_or(version, |(v, _)| v);
+    let (core, pre) = match without_build.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (without_build, None),
+    };
+
+    let pre = match pre {
+        Some(pre) => pre
+            .split('.')
+            .map(|id| {
+                if id.is_empty() {
+                    anyhow::bail!("Invalid pre-release in version: {}", version);
+                }
+                Ok(match id.parse::<u64>() {
+                    Ok(n) => PreRelease::Numeric(n),
+                    Err(_) => PreRelease::Alpha(id.to_string()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => Vec::new(),
+    };
+
+    let parts: Vec<&str> = core.split('.').collect();
 
     if parts.len() != 3 {
         anyhow::bail!("Invalid version format: {}", version);
@@ -75,7 +195,10 @@ fn parse_version(version: &str) -> Result<(u32, u32, u32)> {
         .parse::<u32>()
         .context("Failed to parse patch version")?;
 
-    Ok((major, minor, patch))
+    Ok(Version {
+        core: (major, minor, patch),
+        pre,
+    })
 }
 
 /// Prints an update notification to the user
@@ -103,9 +226,50 @@ mod tests {
 
     #[test]
     fn test_parse_version() {
-        assert_eq!(parse_version("0.2.1").unwrap(), (0, 2, 1));
-        assert_eq!(parse_version("1.0.0").unwrap(), (1, 0, 0));
-        assert_eq!(parse_version("10.20.30").unwrap(), (10, 20, 30));
+        assert_eq!(parse_version("0.2.1").unwrap().core, (0, 2, 1));
+        assert_eq!(parse_version("1.0.0").unwrap().core, (1, 0, 0));
+        assert_eq!(parse_version("10.20.30").unwrap().core, (10, 20, 30));
+    }
+
+    #[test]
+    fn test_parse_version_with_prerelease_and_build() {
+        let version = parse_version("0.3.0-rc.1+linux").unwrap();
+        assert_eq!(version.core, (0, 3, 0));
+        assert_eq!(
+            version.pre,
+            vec![PreRelease::Alpha("rc".into()), PreRelease::Numeric(1)]
+        );
+
+        assert!(parse_version("0.3.0-").is_err());
+        assert!(parse_version("0.3-rc1").is_err());
+    }
+
+    #[test]
+    fn test_prerelease_is_older_than_release() {
+        assert!(is_newer_version("0.3.0", "0.3.0-rc1").unwrap());
+        assert!(!is_newer_version("0.3.0-rc1", "0.3.0").unwrap());
+        assert!(is_newer_version("0.3.0-rc1", "0.2.9").unwrap());
+
+        // Identifiers compare numerically when numeric, and numeric sorts first
+        assert!(is_newer_version("0.3.0-rc.10", "0.3.0-rc.2").unwrap());
+        assert!(is_newer_version("0.3.0-rc.1", "0.3.0-rc").unwrap());
+        assert!(is_newer_version("0.3.0-beta", "0.3.0-1").unwrap());
+    }
+
+    #[test]
+    fn test_cache_is_fresh_for_a_day() {
+        let checked_at = 1_700_000_000;
+        assert!(is_fresh(checked_at, checked_at));
+        assert!(is_fresh(checked_at, checked_at + CHECK_INTERVAL_SECS - 1));
+        assert!(!is_fresh(checked_at, checked_at + CHECK_INTERVAL_SECS));
+        assert!(!is_fresh(checked_at, checked_at - 60));
+    }
+
+    #[test]
+    fn test_build_metadata_is_ignored() {
+        assert!(!is_newer_version("0.3.0+linux", "0.3.0").unwrap());
+        assert!(!is_newer_version("0.3.0", "0.3.0+build.5").unwrap());
+        assert!(is_newer_version("0.3.1+linux", "0.3.0").unwrap());
     }
 
     #[test]
@@ -1,28 +1,62 @@
 use crate::config::Config;
-use crate::window_manager::{EveWindow, WindowManager};
+use crate::kwin_script::KWinScript;
+use crate::window_manager::{
+    detect_wayland_compositor, is_placed_at, EveWindow, WaylandCompositor, WindowFilter,
+    WindowGeometry, WindowManager,
+};
+use crate::wlroots_manager;
 use anyhow::{Context, Result};
+use log::debug;
+use serde::Deserialize;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 // ============================================================================
-// KDE Plasma / KWin Backend (via wmctrl through XWayland)
+// KDE Plasma / KWin Backend (listing and activation via a KWin script, wmctrl through XWayland)
 // ============================================================================
 
-pub struct KWinManager;
+pub struct KWinManager {
+    filter: WindowFilter,
+    /// Loaded on first use and kept running; None if KWin's scripting
+    /// interface isn't reachable, in which case wmctrl/kdotool do the work
+    script: OnceLock<Option<KWinScript>>,
+}
 
 impl KWinManager {
-    pub fn new() -> Result<Self> {
+    pub fn new(filter: WindowFilter) -> Result<Self> {
         Command::new("wmctrl")
             .arg("-m")
             .output()
             .context("wmctrl not found. Install wmctrl package")?;
 
-        Ok(Self)
+        Ok(Self {
+            filter,
+            script: OnceLock::new(),
+        })
     }
 
-    fn get_all_windows(&self) -> Result<Vec<(String, String)>> {
+    fn script(&self) -> Option<&KWinScript> {
+        self.script
+            .get_or_init(|| match KWinScript::load() {
+                Ok(script) => Some(script),
+                Err(e) => {
+                    debug!("KWin scripting unavailable, using wmctrl/kdotool: {:#}", e);
+                    None
+                }
+            })
+            .as_ref()
+    }
+
+    /// List windows as (hex id, WM_CLASS "instance.class", title)
+    fn get_all_windows(&self) -> Result<Vec<(String, String, String)>> {
         let output = Command::new("wmctrl")
-            .arg("-l")
+            .arg("-lx")
             .output()
             .context("Failed to execute wmctrl")?;
 
@@ -35,10 +69,11 @@ impl KWinManager {
 
         for line in lines.lines() {
             let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
+            if parts.len() >= 5 {
                 let window_id = parts[0];
-                let title = parts[3..].join(" ");
-                windows.push((window_id.to_string(), title));
+                let wm_class = parts[2];
+                let title = parts[4..].join(" ");
+                windows.push((window_id.to_string(), wm_class.to_string(), title));
             }
         }
 
@@ -66,11 +101,26 @@ impl KWinManager {
 
 impl WindowManager for KWinManager {
     fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
+        if let Some(windows) = self.script().and_then(KWinScript::windows) {
+            return Ok(windows
+                .into_iter()
+                .filter_map(|window| {
+                    let wm_class: Vec<&str> = window.wm_class.iter().map(String::as_str).collect();
+                    Some(EveWindow {
+                        id: window.id,
+                        title: self.filter.display_title(&window.title, &wm_class)?,
+                        demands_attention: window.demands_attention,
+                    })
+                })
+                .collect());
+        }
+
         let windows = self.get_all_windows()?;
         let mut eve_windows = Vec::new();
 
-        for (id_str, title) in windows {
-            if title.starts_with("EVE - ") && !title.contains("Launcher") {
+        for (id_str, wm_class, title) in windows {
+            let wm_class: Vec<&str> = wm_class.splitn(2, '.').collect();
+            if let Some(title) = self.filter.display_title(&title, &wm_class) {
                 // Parse hex window ID (e.g., "0x06e00008") to u32
                 let id = if let Some(hex) = id_str.strip_prefix("0x") {
                     u32::from_str_radix(hex, 16).unwrap_or(0)
@@ -81,7 +131,8 @@ impl WindowManager for KWinManager {
                 if id != 0 {
                     eve_windows.push(EveWindow {
                         id,
-                        title: title.trim_start_matches("EVE - ").to_string(),
+                        title,
+                        demands_attention: false,
                     });
                 }
             }
@@ -91,6 +142,13 @@ impl WindowManager for KWinManager {
     }
 
     fn activate_window(&self, window_id: u32) -> Result<()> {
+        if let Some(script) = self.script() {
+            match script.activate(window_id) {
+                Ok(()) => return Ok(()),
+                Err(e) => debug!("KWin script activation failed, falling back: {}", e),
+            }
+        }
+
         let hex_id = format!("0x{:08x}", window_id);
 
         if let Some(title) = self.get_window_title_by_id(&hex_id) {
@@ -113,12 +171,23 @@ impl WindowManager for KWinManager {
     }
 
     fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
-        let x = ((config.display_width - config.eve_width) / 2) as i32;
-        let y = 0;
+        let x = config.eve_x();
+        let y = config.eve_y();
         let width = config.eve_width;
-        let height = config.display_height - config.panel_height;
-
+        let height = config.eve_height_adjusted();
+        let target = WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        let current = self.current_geometries(windows);
         for window in windows {
+            if is_placed_at(&current, window.id, target, config.restack_tolerance_px) {
+                continue;
+            }
+
             // Convert u32 to hex format for wmctrl
             let hex_id = format!("0x{:08x}", window.id);
 
@@ -135,6 +204,38 @@ impl WindowManager for KWinManager {
         Ok(())
     }
 
+    fn supports_positioning(&self) -> bool {
+        // wmctrl can move the XWayland windows EVE runs in
+        true
+    }
+
+    fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
+        Command::new("wmctrl")
+            .arg("-i")
+            .arg("-r")
+            .arg(format!("0x{:08x}", window_id))
+            .arg("-e")
+            .arg(format!("0,{},{},-1,-1", x, y))
+            .output()
+            .context("Failed to move window")?;
+        Ok(())
+    }
+
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        Command::new("wmctrl")
+            .arg("-i")
+            .arg("-r")
+            .arg(format!("0x{:08x}", window_id))
+            .arg("-e")
+            .arg(format!(
+                "0,{},{},{},{}",
+                geometry.x, geometry.y, geometry.width, geometry.height
+            ))
+            .output()
+            .context("Failed to move window")?;
+        Ok(())
+    }
+
     fn get_active_window(&self) -> Result<u32> {
         // Use xdotool to get active window (works through XWayland)
         let output = Command::new("xdotool")
@@ -151,9 +252,16 @@ impl WindowManager for KWinManager {
     }
 
     fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
+        if let Some(windows) = self.script().and_then(KWinScript::windows) {
+            return Ok(windows
+                .into_iter()
+                .find(|window| window.title == title)
+                .map(|window| window.id));
+        }
+
         let windows = self.get_all_windows()?;
 
-        for (id_str, window_title) in windows {
+        for (id_str, _, window_title) in windows {
             if window_title == title {
                 // Parse hex window ID (e.g., "0x06e00008") to u32
                 let id = if let Some(hex) = id_str.strip_prefix("0x") {
@@ -171,6 +279,16 @@ impl WindowManager for KWinManager {
         Ok(None)
     }
 
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        let hex_id = format!("0x{:08x}", window_id);
+        // wmctrl -c sends a polite close request, like the title bar button
+        Command::new("wmctrl")
+            .args(["-i", "-c", &hex_id])
+            .output()
+            .context("Failed to close window")?;
+        Ok(())
+    }
+
     fn minimize_window(&self, window_id: u32) -> Result<()> {
         let hex_id = format!("0x{:08x}", window_id);
         Command::new("xdotool")
@@ -192,38 +310,81 @@ impl WindowManager for KWinManager {
 }
 
 // ============================================================================
-// Sway Backend (via swaymsg)
+// Sway Backend (via the i3/sway IPC socket)
 // ============================================================================
 
-pub struct SwayManager;
+const SWAY_IPC_MAGIC: &[u8; 6] = b"i3-ipc";
+const SWAY_RUN_COMMAND: u32 = 0;
+const SWAY_GET_OUTPUTS: u32 = 3;
+const SWAY_GET_TREE: u32 = 4;
+/// How long to wait for Sway to answer a message
+const SWAY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Send one IPC message and read its reply. Messages are framed as the magic
+/// string, then payload length and message type as native-endian u32s.
+fn sway_ipc(socket: &Path, message_type: u32, payload: &str) -> Result<Value> {
+    let mut stream = UnixStream::connect(socket).context("Failed to connect to Sway")?;
+    stream.set_read_timeout(Some(SWAY_TIMEOUT))?;
+
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(SWAY_IPC_MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message)?;
+
+    let mut header = [0u8; 14];
+    stream
+        .read_exact(&mut header)
+        .context("Failed to read Sway reply")?;
+    if &header[..6] != SWAY_IPC_MAGIC {
+        anyhow::bail!("Unexpected reply from Sway IPC socket");
+    }
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+
+    let mut body = vec![0u8; len];
+    stream
+        .read_exact(&mut body)
+        .context("Failed to read Sway reply")?;
+    serde_json::from_slice(&body).context("Failed to parse Sway reply")
+}
+
+pub struct SwayManager {
+    filter: WindowFilter,
+    socket: PathBuf,
+}
 
 impl SwayManager {
-    pub fn new() -> Result<Self> {
-        // Verify swaymsg is available
-        Command::new("swaymsg")
-            .arg("--version")
-            .output()
-            .context("swaymsg not found. Make sure you're running Sway")?;
+    pub fn new(filter: WindowFilter) -> Result<Self> {
+        let socket = std::env::var_os("SWAYSOCK")
+            .map(PathBuf::from)
+            .context("SWAYSOCK isn't set. Make sure you're running Sway")?;
+        sway_ipc(&socket, SWAY_GET_TREE, "")?;
 
-        Ok(Self)
+        Ok(Self { filter, socket })
     }
 
-    fn get_all_windows(&self) -> Result<Vec<Value>> {
-        let output = Command::new("swaymsg")
-            .arg("-t")
-            .arg("get_tree")
-            .output()
-            .context("Failed to execute swaymsg")?;
-
-        if !output.status.success() {
-            anyhow::bail!(
-                "swaymsg failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+    /// Run a sway command, e.g. `[con_id=4] focus`; fails if any part of it did
+    fn run_command(&self, command: &str) -> Result<()> {
+        let reply = sway_ipc(&self.socket, SWAY_RUN_COMMAND, command)?;
+        let failed = reply
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|result| result.get("success").and_then(|s| s.as_bool()) != Some(true));
+
+        if let Some(result) = failed {
+            let error = result
+                .get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("unknown error");
+            anyhow::bail!("Sway refused `{}`: {}", command, error);
         }
+        Ok(())
+    }
 
-        let tree: Value =
-            serde_json::from_slice(&output.stdout).context("Failed to parse swaymsg output")?;
+    fn get_all_windows(&self) -> Result<Vec<Value>> {
+        let tree = sway_ipc(&self.socket, SWAY_GET_TREE, "")?;
 
         let mut windows = Vec::new();
         Self::extract_windows(&tree, &mut windows);
@@ -269,6 +430,22 @@ impl SwayManager {
     fn get_window_id(window: &Value) -> Option<u32> {
         window.get("id").and_then(|i| i.as_u64()).map(|i| i as u32)
     }
+
+    /// Native clients report an app_id, XWayland clients their X11 WM_CLASS
+    fn get_window_classes(window: &Value) -> Vec<&str> {
+        let mut classes = Vec::new();
+        if let Some(app_id) = window.get("app_id").and_then(|a| a.as_str()) {
+            classes.push(app_id);
+        }
+        if let Some(properties) = window.get("window_properties") {
+            for key in ["class", "instance"] {
+                if let Some(class) = properties.get(key).and_then(|c| c.as_str()) {
+                    classes.push(class);
+                }
+            }
+        }
+        classes
+    }
 }
 
 impl WindowManager for SwayManager {
@@ -278,11 +455,14 @@ impl WindowManager for SwayManager {
 
         for window in windows {
             if let Some(title) = Self::get_window_title(&window) {
-                if title.starts_with("EVE - ") && !title.contains("Launcher") {
+                let classes = Self::get_window_classes(&window);
+                if let Some(title) = self.filter.display_title(&title, &classes) {
                     if let Some(id) = Self::get_window_id(&window) {
+                        let urgent = window.get("urgent").and_then(|u| u.as_bool());
                         eve_windows.push(EveWindow {
                             id,
-                            title: title.trim_start_matches("EVE - ").to_string(),
+                            title,
+                            demands_attention: urgent.unwrap_or(false),
                         });
                     }
                 }
@@ -293,46 +473,82 @@ impl WindowManager for SwayManager {
     }
 
     fn activate_window(&self, window_id: u32) -> Result<()> {
-        let output = Command::new("swaymsg")
-            .arg(format!("[con_id={}] focus", window_id))
-            .output()
-            .context("Failed to activate window")?;
+        self.run_command(&format!("[con_id={}] focus", window_id))
+            .context("Failed to activate window")
+    }
 
-        if !output.status.success() {
-            anyhow::bail!(
-                "Failed to activate window: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+    fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
+        let x = config.eve_x();
+        let y = config.eve_y();
+        let width = config.eve_width as i32;
+        let height = config.eve_height_adjusted() as i32;
+        let target = WindowGeometry {
+            x,
+            y,
+            width: width as u32,
+            height: height as u32,
+        };
+
+        let current = self.current_geometries(windows);
+        for window in windows {
+            if is_placed_at(&current, window.id, target, config.restack_tolerance_px) {
+                continue;
+            }
+
+            // Sway uses floating mode for positioning
+            self.run_command(&format!("[con_id={}] floating enable", window.id))?;
+            self.run_command(&format!("[con_id={}] move position {} {}", window.id, x, y))?;
+            self.run_command(&format!(
+                "[con_id={}] resize set {} {}",
+                window.id, width, height
+            ))?;
         }
 
         Ok(())
     }
 
-    fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
-        let x = ((config.display_width - config.eve_width) / 2) as i32;
-        let y = 0;
-        let width = config.eve_width as i32;
-        let height = (config.display_height - config.panel_height) as i32;
+    fn supports_positioning(&self) -> bool {
+        true
+    }
 
-        for window in windows {
-            // Sway uses floating mode for positioning
-            Command::new("swaymsg")
-                .arg(format!("[con_id={}] floating enable", window.id))
-                .output()?;
+    fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
+        self.run_command(&format!(
+            "[con_id={}] floating enable, move position {} {}",
+            window_id, x, y
+        ))
+        .context("Failed to move window")
+    }
 
-            Command::new("swaymsg")
-                .arg(format!("[con_id={}] move position {} {}", window.id, x, y))
-                .output()?;
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        self.run_command(&format!(
+            "[con_id={}] floating enable, move position {} {}, resize set {} {}",
+            window_id, geometry.x, geometry.y, geometry.width, geometry.height
+        ))
+        .context("Failed to move window")
+    }
 
-            Command::new("swaymsg")
-                .arg(format!(
-                    "[con_id={}] resize set {} {}",
-                    window.id, width, height
-                ))
-                .output()?;
-        }
+    fn get_window_geometry(&self, window_id: u32) -> Result<Option<WindowGeometry>> {
+        Ok(self.window_geometries(&[window_id])?.remove(&window_id))
+    }
 
-        Ok(())
+    /// One tree fetch for all of them
+    fn window_geometries(&self, window_ids: &[u32]) -> Result<HashMap<u32, WindowGeometry>> {
+        Ok(self
+            .get_all_windows()?
+            .iter()
+            .filter_map(|w| {
+                let id = Self::get_window_id(w).filter(|id| window_ids.contains(id))?;
+                let rect = w.get("rect")?;
+                let field = |key: &str| rect.get(key).and_then(|v| v.as_i64());
+                let geometry = WindowGeometry {
+                    x: field("x")? as i32,
+                    y: field("y")? as i32,
+                    width: field("width")? as u32,
+                    height: field("height")? as u32,
+                };
+                Some((id, geometry))
+            })
+            .collect())
     }
 
     fn get_active_window(&self) -> Result<u32> {
@@ -367,59 +583,144 @@ impl WindowManager for SwayManager {
         Ok(None)
     }
 
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        self.run_command(&format!("[con_id={}] kill", window_id))
+            .context("Failed to close window")
+    }
+
     fn minimize_window(&self, window_id: u32) -> Result<()> {
-        Command::new("swaymsg")
-            .arg(format!("[con_id={}] move scratchpad", window_id))
-            .output()
-            .context("Failed to minimize window")?;
-        Ok(())
+        self.run_command(&format!("[con_id={}] move scratchpad", window_id))
+            .context("Failed to minimize window")
     }
 
     fn restore_window(&self, window_id: u32) -> Result<()> {
         // Show from scratchpad restores it
-        Command::new("swaymsg")
-            .arg(format!("[con_id={}] scratchpad show", window_id))
-            .output()
-            .context("Failed to restore window")?;
-        Ok(())
+        self.run_command(&format!("[con_id={}] scratchpad show", window_id))
+            .context("Failed to restore window")
     }
 }
 
 // ============================================================================
-// Hyprland Backend (via hyprctl)
+// Hyprland Backend (via the Hyprland IPC socket)
 // ============================================================================
 
-pub struct HyprlandManager;
+/// How long to wait for Hyprland to answer a request
+const HYPRLAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Hyprland's request socket: `$XDG_RUNTIME_DIR/hypr/<signature>/.socket.sock`,
+/// or `/tmp/hypr/<signature>/.socket.sock` on releases before 0.40
+pub fn hyprland_socket_path() -> Result<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .context("HYPRLAND_INSTANCE_SIGNATURE isn't set. Make sure you're running Hyprland")?;
+
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let candidates = runtime
+        .into_iter()
+        .chain([PathBuf::from("/tmp")])
+        .map(|dir| dir.join("hypr").join(&signature).join(".socket.sock"));
+
+    let mut first = None;
+    for path in candidates {
+        if path.exists() {
+            return Ok(path);
+        }
+        first.get_or_insert(path);
+    }
+    anyhow::bail!(
+        "Hyprland socket not found at {}",
+        first.unwrap_or_default().display()
+    )
+}
+
+/// Send one request over the socket and read the reply; Hyprland closes the
+/// connection once it has answered
+fn hyprland_request(socket: &Path, request: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(socket).context("Failed to connect to Hyprland")?;
+    stream.set_read_timeout(Some(HYPRLAND_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+
+    let mut reply = String::new();
+    stream
+        .read_to_string(&mut reply)
+        .context("Failed to read Hyprland reply")?;
+    Ok(reply)
+}
 
-impl HyprlandManager {
-    pub fn new() -> Result<Self> {
-        // Verify hyprctl is available
-        Command::new("hyprctl")
-            .arg("version")
-            .output()
-            .context("hyprctl not found. Make sure you're running Hyprland")?;
+/// A client's Hyprland address, like "0x55d3f8a1b2c0", and the window id
+/// nicotine uses for it. Addresses are 64-bit pointers, so the id folds the
+/// high half into the low one; see [`HyprlandManager::address`] for the way back.
+fn hyprland_window_id(window: &Value) -> Option<(u32, &str)> {
+    let address = window.get("address")?.as_str()?;
+    let pointer = u64::from_str_radix(address.strip_prefix("0x")?, 16).ok()?;
+    Some(((pointer ^ (pointer >> 32)) as u32, address))
+}
+
+pub struct HyprlandManager {
+    filter: WindowFilter,
+    socket: PathBuf,
+    /// Address of every client seen, by window id
+    addresses: Mutex<HashMap<u32, String>>,
+}
 
-        Ok(Self)
+impl HyprlandManager {
+    pub fn new(filter: WindowFilter) -> Result<Self> {
+        let socket = hyprland_socket_path()?;
+        hyprland_request(&socket, "version")?;
+
+        Ok(Self {
+            filter,
+            socket,
+            addresses: Mutex::new(HashMap::new()),
+        })
     }
 
-    fn get_all_windows(&self) -> Result<Vec<Value>> {
-        let output = Command::new("hyprctl")
-            .arg("clients")
-            .arg("-j")
-            .output()
-            .context("Failed to execute hyprctl")?;
+    /// Window id of a client from a Hyprland reply, remembering its address
+    fn window_id(&self, window: &Value) -> Option<u32> {
+        let (id, address) = hyprland_window_id(window)?;
+        self.addresses
+            .lock()
+            .unwrap()
+            .insert(id, address.to_string());
+        Some(id)
+    }
 
-        if !output.status.success() {
-            anyhow::bail!(
-                "hyprctl failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+    /// Address to hand dispatchers for `window_id`, listing the clients
+    /// again if it's one we haven't seen
+    fn address(&self, window_id: u32) -> Result<String> {
+        if let Some(address) = self.addresses.lock().unwrap().get(&window_id) {
+            return Ok(address.clone());
         }
+        for window in self.get_all_windows()? {
+            self.window_id(&window);
+        }
+        self.addresses
+            .lock()
+            .unwrap()
+            .get(&window_id)
+            .cloned()
+            .with_context(|| format!("No Hyprland client with id {}", window_id))
+    }
 
-        let windows: Vec<Value> =
-            serde_json::from_slice(&output.stdout).context("Failed to parse hyprctl output")?;
+    /// Run a request whose reply is JSON (`j/` asks Hyprland for JSON output)
+    fn query(&self, request: &str) -> Result<Value> {
+        let reply = hyprland_request(&self.socket, &format!("j/{}", request))?;
+        serde_json::from_str(&reply).context("Failed to parse Hyprland reply")
+    }
 
-        Ok(windows)
+    /// Run a dispatcher, e.g. `focuswindow address:0x1234`
+    fn dispatch(&self, args: &str) -> Result<()> {
+        let reply = hyprland_request(&self.socket, &format!("dispatch {}", args))?;
+        if reply.trim() != "ok" {
+            anyhow::bail!("Hyprland refused `{}`: {}", args, reply.trim());
+        }
+        Ok(())
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<Value>> {
+        match self.query("clients")? {
+            Value::Array(windows) => Ok(windows),
+            _ => anyhow::bail!("Unexpected reply to Hyprland clients request"),
+        }
     }
 }
 
@@ -430,19 +731,16 @@ impl WindowManager for HyprlandManager {
 
         for window in windows {
             if let Some(title) = window.get("title").and_then(|t| t.as_str()) {
-                if title.starts_with("EVE - ") && !title.contains("Launcher") {
-                    // Hyprland uses hex addresses, we'll hash it to a u32
-                    if let Some(address) = window.get("address").and_then(|a| a.as_str()) {
-                        // Convert hex address like "0x12345678" to u32
-                        let id = if let Some(hex) = address.strip_prefix("0x") {
-                            u32::from_str_radix(hex, 16).unwrap_or(0)
-                        } else {
-                            0
-                        };
-
+                let classes: Vec<&str> = ["class", "initialClass"]
+                    .iter()
+                    .filter_map(|key| window.get(key).and_then(|c| c.as_str()))
+                    .collect();
+                if let Some(title) = self.filter.display_title(title, &classes) {
+                    if let Some(id) = self.window_id(&window) {
                         eve_windows.push(EveWindow {
                             id,
-                            title: title.trim_start_matches("EVE - ").to_string(),
+                            title,
+                            demands_attention: false,
                         });
                     }
                 }
@@ -453,127 +751,631 @@ impl WindowManager for HyprlandManager {
     }
 
     fn activate_window(&self, window_id: u32) -> Result<()> {
-        // Convert u32 back to hex address
-        let address = format!("0x{:x}", window_id);
-
-        let output = Command::new("hyprctl")
-            .arg("dispatch")
-            .arg("focuswindow")
-            .arg(format!("address:{}", address))
-            .output()
-            .context("Failed to activate window")?;
-
-        if !output.status.success() {
-            anyhow::bail!(
-                "Failed to activate window: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-
-        Ok(())
+        self.dispatch(&format!("focuswindow address:{}", self.address(window_id)?))
+            .context("Failed to activate window")
     }
 
     fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
-        let x = ((config.display_width - config.eve_width) / 2) as i32;
-        let y = 0;
+        let x = config.eve_x();
+        let y = config.eve_y();
         let width = config.eve_width as i32;
-        let height = (config.display_height - config.panel_height) as i32;
-
+        let height = config.eve_height_adjusted() as i32;
+        let target = WindowGeometry {
+            x,
+            y,
+            width: width as u32,
+            height: height as u32,
+        };
+
+        let current = self.current_geometries(windows);
         for window in windows {
-            let address = format!("0x{:x}", window.id);
+            if is_placed_at(&current, window.id, target, config.restack_tolerance_px) {
+                continue;
+            }
+
+            let address = self.address(window.id)?;
 
             // Enable floating
-            Command::new("hyprctl")
-                .arg("dispatch")
-                .arg("togglefloating")
-                .arg(format!("address:{}", address))
-                .output()?;
+            self.dispatch(&format!("togglefloating address:{}", address))?;
 
             // Move window
-            Command::new("hyprctl")
-                .arg("dispatch")
-                .arg("movewindowpixel")
-                .arg(format!("exact {} {},address:{}", x, y, address))
-                .output()?;
+            self.dispatch(&format!(
+                "movewindowpixel exact {} {},address:{}",
+                x, y, address
+            ))?;
 
             // Resize window
-            Command::new("hyprctl")
-                .arg("dispatch")
-                .arg("resizewindowpixel")
-                .arg(format!("exact {} {},address:{}", width, height, address))
-                .output()?;
+            self.dispatch(&format!(
+                "resizewindowpixel exact {} {},address:{}",
+                width, height, address
+            ))?;
         }
 
         Ok(())
     }
 
-    fn get_active_window(&self) -> Result<u32> {
-        let output = Command::new("hyprctl")
-            .arg("activewindow")
-            .arg("-j")
-            .output()
-            .context("Failed to get active window")?;
+    fn supports_positioning(&self) -> bool {
+        true
+    }
 
-        let window: Value =
-            serde_json::from_slice(&output.stdout).context("Failed to parse hyprctl output")?;
+    fn supports_restacking(&self) -> bool {
+        true
+    }
 
-        if let Some(address) = window.get("address").and_then(|a| a.as_str()) {
-            let id = if let Some(hex) = address.strip_prefix("0x") {
-                u32::from_str_radix(hex, 16).unwrap_or(0)
-            } else {
-                0
-            };
-            return Ok(id);
+    /// Only reorders floating windows; tiled ones don't overlap anyway
+    fn raise_all(&self, windows: &[EveWindow]) -> Result<()> {
+        for window in windows {
+            self.dispatch(&format!(
+                "alterzorder top,address:{}",
+                self.address(window.id)?
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn lower_all(&self, windows: &[EveWindow]) -> Result<()> {
+        for window in windows {
+            self.dispatch(&format!(
+                "alterzorder bottom,address:{}",
+                self.address(window.id)?
+            ))?;
         }
+        Ok(())
+    }
 
-        anyhow::bail!("Failed to get active window ID")
+    fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
+        self.dispatch(&format!(
+            "movewindowpixel exact {} {},address:{}",
+            x,
+            y,
+            self.address(window_id)?
+        ))
+        .context("Failed to move window")
+    }
+
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        let address = self.address(window_id)?;
+
+        self.dispatch(&format!("setfloating address:{}", address))?;
+        self.dispatch(&format!(
+            "movewindowpixel exact {} {},address:{}",
+            geometry.x, geometry.y, address
+        ))?;
+        self.dispatch(&format!(
+            "resizewindowpixel exact {} {},address:{}",
+            geometry.width, geometry.height, address
+        ))?;
+
+        Ok(())
+    }
+
+    fn get_window_geometry(&self, window_id: u32) -> Result<Option<WindowGeometry>> {
+        Ok(self.window_geometries(&[window_id])?.remove(&window_id))
+    }
+
+    /// One clients fetch for all of them
+    fn window_geometries(&self, window_ids: &[u32]) -> Result<HashMap<u32, WindowGeometry>> {
+        Ok(self
+            .get_all_windows()?
+            .iter()
+            .filter_map(|window| {
+                let id = self
+                    .window_id(window)
+                    .filter(|id| window_ids.contains(id))?;
+                let pair = |key: &str| {
+                    let values = window.get(key)?.as_array()?;
+                    Some((values.first()?.as_i64()?, values.get(1)?.as_i64()?))
+                };
+                let (x, y) = pair("at")?;
+                let (width, height) = pair("size")?;
+                let geometry = WindowGeometry {
+                    x: x as i32,
+                    y: y as i32,
+                    width: width as u32,
+                    height: height as u32,
+                };
+                Some((id, geometry))
+            })
+            .collect())
+    }
+
+    fn get_active_window(&self) -> Result<u32> {
+        let window = self
+            .query("activewindow")
+            .context("Failed to get active window")?;
+
+        self.window_id(&window)
+            .context("Failed to get active window ID")
     }
 
     fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
         let windows = self.get_all_windows()?;
 
-        for window in windows {
-            if let Some(window_title) = window.get("title").and_then(|t| t.as_str()) {
-                if window_title == title {
-                    if let Some(address) = window.get("address").and_then(|a| a.as_str()) {
-                        let id = if let Some(hex) = address.strip_prefix("0x") {
-                            u32::from_str_radix(hex, 16).unwrap_or(0)
-                        } else {
-                            0
-                        };
-                        return Ok(Some(id));
-                    }
+        Ok(windows
+            .iter()
+            .find(|w| w.get("title").and_then(|t| t.as_str()) == Some(title))
+            .and_then(|window| self.window_id(window)))
+    }
+
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        self.dispatch(&format!("closewindow address:{}", self.address(window_id)?))
+            .context("Failed to close window")
+    }
+
+    fn minimize_window(&self, window_id: u32) -> Result<()> {
+        self.dispatch(&format!(
+            "movetoworkspacesilent special,address:{}",
+            self.address(window_id)?
+        ))
+        .context("Failed to minimize window")
+    }
+
+    fn restore_window(&self, window_id: u32) -> Result<()> {
+        // Move back to current workspace
+        self.dispatch(&format!(
+            "movetoworkspace e+0,address:{}",
+            self.address(window_id)?
+        ))
+        .context("Failed to restore window")
+    }
+}
+
+// ============================================================================
+// Display size detection
+// ============================================================================
+
+/// Resolution of the focused output, or the first one if none is focused
+fn focused_output_size(
+    outputs: &Value,
+    size: impl Fn(&Value) -> Option<(u32, u32)>,
+) -> Option<(u32, u32)> {
+    let outputs = outputs.as_array()?;
+    let focused = outputs
+        .iter()
+        .find(|output| output.get("focused").and_then(|f| f.as_bool()) == Some(true));
+    focused.into_iter().chain(outputs.iter()).find_map(size)
+}
+
+fn json_size(value: &Value) -> Option<(u32, u32)> {
+    let width = value.get("width")?.as_u64()? as u32;
+    let height = value.get("height")?.as_u64()? as u32;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Logical size of a Sway output. `current_mode` is in physical pixels, while
+/// `rect` already accounts for scaling, like window positions do.
+fn sway_output_size(output: &Value) -> Option<(u32, u32)> {
+    json_size(output.get("rect")?)
+}
+
+/// Logical size of a Hyprland monitor, whose `width`/`height` are physical
+fn hyprland_monitor_size(monitor: &Value) -> Option<(u32, u32)> {
+    let (width, height) = json_size(monitor)?;
+    let scale = monitor
+        .get("scale")
+        .and_then(Value::as_f64)
+        .filter(|scale| *scale > 0.0)
+        .unwrap_or(1.0);
+    let logical = |pixels: u32| (f64::from(pixels) / scale).round() as u32;
+    Some((logical(width), logical(height)))
+}
+
+/// Resolution of the focused output on a Wayland session: asked of Sway or
+/// Hyprland over their IPC, otherwise the first wl_output
+pub fn wayland_display_size() -> Option<(u32, u32)> {
+    match detect_wayland_compositor() {
+        WaylandCompositor::Sway => {
+            let socket = PathBuf::from(std::env::var_os("SWAYSOCK")?);
+            let outputs = sway_ipc(&socket, SWAY_GET_OUTPUTS, "").ok()?;
+            focused_output_size(&outputs, sway_output_size)
+        }
+        WaylandCompositor::Hyprland => {
+            let socket = hyprland_socket_path().ok()?;
+            let reply = hyprland_request(&socket, "j/monitors").ok()?;
+            let monitors: Value = serde_json::from_str(&reply).ok()?;
+            focused_output_size(&monitors, hyprland_monitor_size)
+        }
+        _ => wlroots_manager::output_monitors()
+            .ok()?
+            .into_iter()
+            .find(|monitor| monitor.width > 0 && monitor.height > 0)
+            .map(|monitor| (monitor.width, monitor.height)),
+    }
+}
+
+// ============================================================================
+// GNOME Shell Backend (via the bundled extension's D-Bus interface)
+// ============================================================================
+
+const GNOME_DEST: &str = "org.gnome.Shell";
+const GNOME_OBJECT_PATH: &str = "/org/nicotine/Windows";
+const GNOME_INTERFACE: &str = "org.nicotine.Windows";
+
+/// A window as reported by the extension's ListWindows
+#[derive(Deserialize)]
+struct GnomeWindow {
+    id: u32,
+    title: String,
+    wm_class: String,
+    #[serde(default)]
+    demands_attention: bool,
+}
+
+pub struct GnomeManager {
+    filter: WindowFilter,
+}
+
+impl GnomeManager {
+    pub fn new(filter: WindowFilter) -> Result<Self> {
+        let manager = Self { filter };
+
+        // Verify the extension is installed and enabled
+        manager.get_all_windows().context(
+            "Nicotine GNOME Shell extension not responding. Install and enable it (see README)",
+        )?;
+
+        Ok(manager)
+    }
+
+    /// Call a method on the extension, returning gdbus's printed reply
+    fn call(&self, method: &str, args: &[String]) -> Result<String> {
+        gdbus_call(
+            GNOME_DEST,
+            GNOME_OBJECT_PATH,
+            &format!("{}.{}", GNOME_INTERFACE, method),
+            args,
+        )
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<GnomeWindow>> {
+        let reply = self.call("ListWindows", &[])?;
+        let json = parse_gvariant_string(&reply)?;
+        serde_json::from_str(&json).context("Failed to parse window list from GNOME extension")
+    }
+}
+
+/// Call a session bus method with gdbus, returning its printed reply
+fn gdbus_call(dest: &str, object_path: &str, method: &str, args: &[String]) -> Result<String> {
+    let output = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            dest,
+            "--object-path",
+            object_path,
+            "--method",
+            method,
+        ])
+        .args(args)
+        .output()
+        .context("Failed to execute gdbus")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "gdbus {} failed: {}",
+            method,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Extract the string from a gdbus reply like `('text',)`, undoing GVariant escapes
+fn parse_gvariant_string(reply: &str) -> Result<String> {
+    let inner = reply
+        .strip_prefix('(')
+        .context("Unexpected gdbus reply")?
+        .trim_start();
+    let mut chars = inner.chars();
+    let quote = match chars.next() {
+        Some(q @ ('\'' | '"')) => q,
+        _ => anyhow::bail!("Unexpected gdbus reply: {}", reply),
+    };
+
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    value.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
                 }
+                Some('U') => {
+                    let hex: String = chars.by_ref().take(8).collect();
+                    value.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+                }
+                Some(other) => value.push(other),
+                None => break,
+            },
+            c if c == quote => return Ok(value),
+            c => value.push(c),
+        }
+    }
+
+    anyhow::bail!("Unterminated string in gdbus reply")
+}
+
+impl WindowManager for GnomeManager {
+    fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
+        let mut eve_windows = Vec::new();
+
+        for window in self.get_all_windows()? {
+            if let Some(title) = self
+                .filter
+                .display_title(&window.title, &[window.wm_class.as_str()])
+            {
+                eve_windows.push(EveWindow {
+                    id: window.id,
+                    title,
+                    demands_attention: window.demands_attention,
+                });
             }
         }
 
-        Ok(None)
+        Ok(eve_windows)
+    }
+
+    fn activate_window(&self, window_id: u32) -> Result<()> {
+        self.call("ActivateWindow", &[window_id.to_string()])?;
+        Ok(())
+    }
+
+    fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
+        let x = config.eve_x();
+        let y = config.eve_y();
+        let width = config.eve_width;
+        let height = config.eve_height_adjusted();
+        let target = WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        let current = self.current_geometries(windows);
+        for window in windows {
+            if is_placed_at(&current, window.id, target, config.restack_tolerance_px) {
+                continue;
+            }
+
+            self.call(
+                "MoveResize",
+                &[
+                    window.id.to_string(),
+                    x.to_string(),
+                    y.to_string(),
+                    width.to_string(),
+                    height.to_string(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn supports_positioning(&self) -> bool {
+        true
+    }
+
+    fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
+        self.call(
+            "Move",
+            &[window_id.to_string(), x.to_string(), y.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        self.call(
+            "MoveResize",
+            &[
+                window_id.to_string(),
+                geometry.x.to_string(),
+                geometry.y.to_string(),
+                geometry.width.to_string(),
+                geometry.height.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_active_window(&self) -> Result<u32> {
+        // Reply looks like `(uint32 42,)`
+        let reply = self.call("GetFocused", &[])?;
+        reply
+            .trim_start_matches('(')
+            .trim_end_matches(",)")
+            .trim_start_matches("uint32")
+            .trim()
+            .parse()
+            .context("Failed to parse focused window from GNOME extension")
+    }
+
+    fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
+        Ok(self
+            .get_all_windows()?
+            .into_iter()
+            .find(|w| w.title == title)
+            .map(|w| w.id))
+    }
+
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        self.call("Close", &[window_id.to_string()])?;
+        Ok(())
     }
 
     fn minimize_window(&self, window_id: u32) -> Result<()> {
-        let address = format!("0x{:x}", window_id);
-        Command::new("hyprctl")
-            .args([
-                "dispatch",
-                "movetoworkspacesilent",
-                &format!("special,address:{}", address),
-            ])
-            .output()
-            .context("Failed to minimize window")?;
+        self.call("Minimize", &[window_id.to_string()])?;
         Ok(())
     }
 
     fn restore_window(&self, window_id: u32) -> Result<()> {
-        let address = format!("0x{:x}", window_id);
-        // Move back to current workspace
-        Command::new("hyprctl")
-            .args([
-                "dispatch",
-                "movetoworkspace",
-                &format!("e+0,address:{}", address),
-            ])
-            .output()
-            .context("Failed to restore window")?;
+        self.call("Unminimize", &[window_id.to_string()])?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyprland_request_reads_reply_until_close() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join(".socket.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 64];
+            let len = stream.read(&mut request).unwrap();
+            stream
+                .write_all(
+                    br#"[{"address":"0x1a2b","title":"EVE - Alpha","class":"steam_app_8500"}]"#,
+                )
+                .unwrap();
+            String::from_utf8_lossy(&request[..len]).into_owned()
+        });
+
+        let reply = hyprland_request(&socket, "j/clients").unwrap();
+        assert_eq!(server.join().unwrap(), "j/clients");
+
+        let windows: Vec<Value> = serde_json::from_str(&reply).unwrap();
+        assert_eq!(hyprland_window_id(&windows[0]), Some((0x1a2b, "0x1a2b")));
+    }
+
+    #[test]
+    fn test_hyprland_window_id_takes_full_width_addresses() {
+        let window = |address: &str| serde_json::json!({ "address": address });
+
+        let first = window("0x55d3f8a1b2c0");
+        let (id, address) = hyprland_window_id(&first).unwrap();
+        assert_eq!(id, 0xf8a1b2c0 ^ 0x55d3);
+        assert_eq!(address, "0x55d3f8a1b2c0");
+
+        // Clients a page apart on the heap still get different ids
+        let second = window("0x55d3f8a1c2c0");
+        assert_ne!(hyprland_window_id(&second).unwrap().0, id);
+        assert_eq!(hyprland_window_id(&window("55d3f8a1b2c0")), None);
+    }
+
+    #[test]
+    fn test_hyprland_dispatches_to_the_full_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join(".socket.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for reply in [
+                &br#"[{"address":"0x55d3f8a1b2c0","title":"EVE - Alpha"}]"#[..],
+                b"ok",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 128];
+                let len = stream.read(&mut request).unwrap();
+                stream.write_all(reply).unwrap();
+                requests.push(String::from_utf8_lossy(&request[..len]).into_owned());
+            }
+            requests
+        });
+
+        let manager = HyprlandManager {
+            filter: WindowFilter::default(),
+            socket,
+            addresses: Mutex::new(HashMap::new()),
+        };
+        manager.close_window(0xf8a1b2c0 ^ 0x55d3).unwrap();
+        assert_eq!(
+            server.join().unwrap(),
+            ["j/clients", "dispatch closewindow address:0x55d3f8a1b2c0"]
+        );
+    }
+
+    #[test]
+    fn test_sway_ipc_frames_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("sway-ipc.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut header = [0u8; 14];
+            stream.read_exact(&mut header).unwrap();
+            let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+            let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+            let mut payload = vec![0u8; len as usize];
+            stream.read_exact(&mut payload).unwrap();
+
+            let reply = br#"[{"success":true}]"#;
+            let mut message = SWAY_IPC_MAGIC.to_vec();
+            message.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+            message.extend_from_slice(&kind.to_ne_bytes());
+            message.extend_from_slice(reply);
+            stream.write_all(&message).unwrap();
+            (kind, String::from_utf8(payload).unwrap())
+        });
+
+        let reply = sway_ipc(&socket, SWAY_RUN_COMMAND, "[con_id=7] focus").unwrap();
+        assert_eq!(
+            server.join().unwrap(),
+            (SWAY_RUN_COMMAND, "[con_id=7] focus".to_string())
+        );
+        assert_eq!(reply[0]["success"], Value::Bool(true));
+    }
+
+    #[test]
+    fn test_focused_output_size_prefers_focused() {
+        let monitors: Value = serde_json::from_str(
+            r#"[{"name":"DP-1","width":1920,"height":1080,"scale":1.0,"focused":false},
+                {"name":"DP-2","width":3840,"height":2160,"scale":1.0,"focused":true}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            focused_output_size(&monitors, hyprland_monitor_size),
+            Some((3840, 2160))
+        );
+
+        // Without focus, the first output wins
+        let outputs: Value = serde_json::from_str(
+            r#"[{"name":"HDMI-A-1","rect":{"x":0,"y":0,"width":3440,"height":1440}},
+                {"name":"DP-1","rect":{"x":3440,"y":0,"width":1920,"height":1080}}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            focused_output_size(&outputs, sway_output_size),
+            Some((3440, 1440))
+        );
+    }
+
+    #[test]
+    fn test_scaled_output_size_is_logical() {
+        // A 4K output at 2x: Sway's rect is logical, its current_mode physical
+        let output: Value = serde_json::from_str(
+            r#"{"rect":{"x":0,"y":0,"width":1920,"height":1080},
+                "current_mode":{"width":3840,"height":2160},"scale":2.0}"#,
+        )
+        .unwrap();
+        assert_eq!(sway_output_size(&output), Some((1920, 1080)));
+
+        let monitor: Value =
+            serde_json::from_str(r#"{"width":3840,"height":2160,"scale":1.5}"#).unwrap();
+        assert_eq!(hyprland_monitor_size(&monitor), Some((2560, 1440)));
+    }
+
+    #[test]
+    fn test_parse_gvariant_string_unescapes() {
+        assert_eq!(
+            parse_gvariant_string(r#"('[{"title":"EVE - O\'Neil"}]',)"#).unwrap(),
+            r#"[{"title":"EVE - O'Neil"}]"#
+        );
+        assert_eq!(parse_gvariant_string(r#"("it's",)"#).unwrap(), "it's");
+        assert_eq!(parse_gvariant_string(r"('café',)").unwrap(), "café");
+        assert!(parse_gvariant_string("(uint32 5,)").is_err());
+    }
+}
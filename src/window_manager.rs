@@ -1,10 +1,330 @@
-use crate::config::Config;
-use anyhow::Result;
+use crate::config::{Config, LayoutMode};
+use anyhow::{Context, Result};
+use log::warn;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Title EVE uses for a client that hasn't picked a character yet
+pub const CHARACTER_SELECT_TITLE: &str = "EVE";
 
 #[derive(Debug, Clone)]
 pub struct EveWindow {
     pub id: u32,
     pub title: String,
+    /// Urgency hint (_NET_WM_STATE_DEMANDS_ATTENTION) is set
+    pub demands_attention: bool,
+}
+
+impl EveWindow {
+    /// Whether this client is still at the character select screen
+    pub fn is_character_select(&self) -> bool {
+        self.title == CHARACTER_SELECT_TITLE
+    }
+}
+
+/// On-screen position and size of a window, in root window coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    /// Whether two geometries are the same to within `tolerance` pixels on every edge
+    pub fn matches(&self, other: &WindowGeometry, tolerance: u32) -> bool {
+        self.x.abs_diff(other.x) <= tolerance
+            && self.y.abs_diff(other.y) <= tolerance
+            && self.width.abs_diff(other.width) <= tolerance
+            && self.height.abs_diff(other.height) <= tolerance
+    }
+}
+
+/// A connected output and where it sits on the desktop
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Monitor {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Monitor {
+    /// The part of this monitor clients can be laid out in, leaving room for a panel
+    pub fn usable_area(&self, config: &Config) -> WindowGeometry {
+        WindowGeometry {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height.saturating_sub(config.panel_height),
+        }
+    }
+}
+
+/// The configured display as one usable area (`display_width` x `eve_height_adjusted()`)
+pub fn display_area(config: &Config) -> WindowGeometry {
+    WindowGeometry {
+        x: 0,
+        y: 0,
+        width: config.display_width,
+        height: config.eve_height_adjusted(),
+    }
+}
+
+/// Decides which windows are EVE clients and how their titles are displayed
+#[derive(Debug, Clone, Default)]
+pub struct WindowFilter {
+    /// WM_CLASS values of helper windows (Steam overlay, web helper) that are never clients
+    pub exclude_wm_classes: Vec<String>,
+    /// WM_CLASS values that mark a window as an EVE client regardless of its title
+    pub wm_class_candidates: Vec<String>,
+    /// Titles starting with this are clients
+    pub title_prefix: Option<String>,
+    /// Titles matching this are clients; capture group 1 (if any) is the display title
+    pub title_regex: Option<Regex>,
+    /// Drop `title_prefix` from the display title
+    pub strip_prefix: bool,
+    /// Titles containing any of these (the launcher) are never clients
+    pub exclude_title_substrings: Vec<String>,
+}
+
+impl WindowFilter {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let window_match = &config.window_match;
+        let title_regex = window_match
+            .title_regex
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .context("Invalid window_match.title_regex")?;
+
+        Ok(Self {
+            exclude_wm_classes: config.exclude_wm_classes.clone(),
+            wm_class_candidates: config.wm_class_candidates.clone(),
+            title_prefix: window_match.title_prefix.clone(),
+            title_regex,
+            strip_prefix: window_match.strip_prefix,
+            exclude_title_substrings: window_match.exclude_title_substrings.clone(),
+        })
+    }
+
+    fn is_excluded_class(&self, wm_class: &str) -> bool {
+        self.exclude_wm_classes
+            .iter()
+            .any(|excluded| excluded.eq_ignore_ascii_case(wm_class))
+    }
+
+    fn is_candidate_class(&self, wm_class: &str) -> bool {
+        self.wm_class_candidates
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(wm_class))
+    }
+
+    /// Map a raw window title to the client's display title
+    /// `wm_classes` holds whatever WM_CLASS / app_id values the backend can see (may be empty)
+    /// Returns None if the window isn't an EVE client (e.g. the launcher or Steam overlay)
+    pub fn display_title(&self, title: &str, wm_classes: &[&str]) -> Option<String> {
+        if wm_classes.iter().any(|class| self.is_excluded_class(class)) {
+            return None;
+        }
+
+        if self
+            .exclude_title_substrings
+            .iter()
+            .any(|excluded| title.contains(excluded.as_str()))
+        {
+            return None;
+        }
+
+        // WM_CLASS doesn't depend on the client's language, the install
+        // (Lutris, raw WINE, Flatpak) or what the title says right now, so
+        // it's trusted over the title
+        if !title.is_empty()
+            && wm_classes
+                .iter()
+                .any(|class| self.is_candidate_class(class))
+        {
+            return Some(
+                self.match_title(title)
+                    .unwrap_or_else(|| self.strip_title(title)),
+            );
+        }
+
+        if let Some(display) = self.match_title(title) {
+            return Some(display);
+        }
+
+        // Clients at character select are titled just "EVE"
+        if title == CHARACTER_SELECT_TITLE {
+            return Some(title.to_string());
+        }
+
+        None
+    }
+
+    /// Display title for a window the configured prefix or regex accepts
+    fn match_title(&self, title: &str) -> Option<String> {
+        if let Some(prefix) = &self.title_prefix {
+            if title.starts_with(prefix.as_str()) {
+                return Some(self.strip_title(title));
+            }
+        }
+
+        let captures = self.title_regex.as_ref()?.captures(title)?;
+        Some(match captures.get(1) {
+            Some(group) => group.as_str().to_string(),
+            None => self.strip_title(title),
+        })
+    }
+
+    fn strip_title(&self, title: &str) -> String {
+        match &self.title_prefix {
+            Some(prefix) if self.strip_prefix => title
+                .strip_prefix(prefix.as_str())
+                .unwrap_or(title)
+                .to_string(),
+            _ => title.to_string(),
+        }
+    }
+}
+
+/// Take out the clients that have their own geometry in characters.txt,
+/// returning the rest and where each overridden one goes
+fn split_character_overrides(
+    windows: &[EveWindow],
+    overrides: &[(String, WindowGeometry)],
+) -> (Vec<EveWindow>, Vec<(u32, WindowGeometry)>) {
+    let mut rest = Vec::with_capacity(windows.len());
+    let mut placed = Vec::new();
+    for window in windows {
+        match overrides.iter().find(|(name, _)| *name == window.title) {
+            Some((_, geometry)) => placed.push((window.id, *geometry)),
+            None => rest.push(window.clone()),
+        }
+    }
+    (rest, placed)
+}
+
+/// Whether a window already sits at `target` within `tolerance` pixels, so
+/// moving it again would only flicker. False when its geometry is unknown.
+pub fn is_placed_at(
+    current: &HashMap<u32, WindowGeometry>,
+    window_id: u32,
+    target: WindowGeometry,
+    tolerance: u32,
+) -> bool {
+    current
+        .get(&window_id)
+        .is_some_and(|geometry| geometry.matches(&target, tolerance))
+}
+
+/// Where each client goes for a layout, in the same order as `windows`: its
+/// characters.txt geometry if it has one, otherwise its slot among the rest
+pub fn layout_targets(
+    windows: &[EveWindow],
+    overrides: &[(String, WindowGeometry)],
+    config: &Config,
+    mode: LayoutMode,
+    area: WindowGeometry,
+) -> Vec<WindowGeometry> {
+    let (rest, placed) = split_character_overrides(windows, overrides);
+    let mut slots = layout_rects(rest.len(), config, mode, area).into_iter();
+    windows
+        .iter()
+        .filter_map(
+            |window| match placed.iter().find(|(id, _)| *id == window.id) {
+                Some((_, geometry)) => Some(*geometry),
+                None => slots.next(),
+            },
+        )
+        .collect()
+}
+
+/// Where each of `count` clients goes for a layout, tiling `area`
+pub fn layout_rects(
+    count: usize,
+    config: &Config,
+    mode: LayoutMode,
+    area: WindowGeometry,
+) -> Vec<WindowGeometry> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let area_width = area.width;
+    let area_height = area.height;
+    let n = count as u32;
+
+    let rects = match mode {
+        LayoutMode::Stack => {
+            let width = config.eve_width.min(area_width);
+            let x = config
+                .eve_x
+                .unwrap_or((area_width.saturating_sub(width) / 2) as i32);
+            let geometry = WindowGeometry {
+                x,
+                y: config.eve_y(),
+                width,
+                height: area_height,
+            };
+            vec![geometry; count]
+        }
+        LayoutMode::Grid => {
+            let cols = (count as f64).sqrt().ceil() as u32;
+            let rows = n.div_ceil(cols);
+            let (width, height) = (area_width / cols, area_height / rows);
+            (0..n)
+                .map(|i| WindowGeometry {
+                    x: ((i % cols) * width) as i32,
+                    y: ((i / cols) * height) as i32,
+                    width,
+                    height,
+                })
+                .collect()
+        }
+        LayoutMode::Columns => {
+            let width = area_width / n;
+            (0..n)
+                .map(|i| WindowGeometry {
+                    x: (i * width) as i32,
+                    y: 0,
+                    width,
+                    height: area_height,
+                })
+                .collect()
+        }
+        LayoutMode::Cascade => {
+            // Shrink the clients so the last one still fits on screen, capping
+            // the step so every client keeps some of the area
+            let step = config.cascade_step.min(area_width.min(area_height) / n);
+            let spread = step * (n - 1);
+            let width = config.eve_width.min(area_width.saturating_sub(spread));
+            let height = area_height.saturating_sub(spread);
+            let left = (area_width.saturating_sub(width + spread) / 2) as i32;
+            (0..n)
+                .map(|i| {
+                    let offset = (i * step) as i32;
+                    WindowGeometry {
+                        x: left + offset,
+                        y: offset,
+                        width,
+                        height,
+                    }
+                })
+                .collect()
+        }
+    };
+
+    rects
+        .into_iter()
+        .map(|rect| WindowGeometry {
+            x: rect.x + area.x,
+            y: rect.y + area.y,
+            ..rect
+        })
+        .collect()
 }
 
 /// Trait for window management across different display servers and compositors
@@ -18,19 +338,177 @@ pub trait WindowManager: Send + Sync {
     /// Stack all EVE windows at the same position (centered)
     fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()>;
 
+    /// Arrange windows using a layout mode, on `stack_monitor` if one is
+    /// configured. Stack on the whole display is the same as `stack_windows`.
+    /// Characters in `overrides` (from characters.txt) go to their own geometry.
+    fn layout_windows(
+        &self,
+        windows: &[EveWindow],
+        config: &Config,
+        mode: LayoutMode,
+        overrides: &[(String, WindowGeometry)],
+    ) -> Result<()> {
+        let current = self.current_geometries(windows);
+
+        // Characters with their own geometry in characters.txt skip the layout
+        let (windows, placed) = split_character_overrides(windows, overrides);
+        for (window_id, geometry) in placed {
+            if !is_placed_at(&current, window_id, geometry, config.restack_tolerance_px) {
+                self.place_window(window_id, geometry)?;
+            }
+        }
+        let windows = &windows[..];
+
+        let monitor_area = self.stack_monitor_area(config)?;
+        if let (Some(name), None) = (&config.stack_monitor, monitor_area) {
+            let monitors = self.monitors()?;
+            let names: Vec<&str> = monitors.iter().map(|m| m.name.as_str()).collect();
+            warn!(
+                "Monitor '{}' not found (connected: {}), using the whole display",
+                name,
+                names.join(", ")
+            );
+        }
+
+        if mode == LayoutMode::Stack && monitor_area.is_none() {
+            return self.stack_windows(windows, config);
+        }
+
+        let area = monitor_area.unwrap_or_else(|| display_area(config));
+        for (window, geometry) in
+            windows
+                .iter()
+                .zip(layout_rects(windows.len(), config, mode, area))
+        {
+            if is_placed_at(&current, window.id, geometry, config.restack_tolerance_px) {
+                continue;
+            }
+            self.place_window(window.id, geometry)?;
+        }
+        Ok(())
+    }
+
+    /// Usable area of `stack_monitor`, or None when it isn't set or connected
+    fn stack_monitor_area(&self, config: &Config) -> Result<Option<WindowGeometry>> {
+        let Some(name) = &config.stack_monitor else {
+            return Ok(None);
+        };
+        Ok(self
+            .monitors()?
+            .iter()
+            .find(|m| m.name == *name)
+            .map(|m| m.usable_area(config)))
+    }
+
+    /// Move and resize a window (used by layouts other than Stack)
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        let _ = (window_id, geometry);
+        anyhow::bail!("Window layouts aren't supported on this backend")
+    }
+
     /// Get the currently active window ID
     fn get_active_window(&self) -> Result<u32>;
 
+    /// Give every window in `window_ids` but `active_id` this opacity, for
+    /// dim_inactive_opacity. Only X11 supports it; other backends ignore it.
+    fn dim_inactive(&self, window_ids: &[u32], active_id: u32, opacity: f32) -> Result<()> {
+        let _ = (window_ids, active_id, opacity);
+        Ok(())
+    }
+
+    /// Undo `dim_inactive` on every client, when the option is turned off
+    /// or nicotine exits
+    fn restore_opacity(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Focus a window and replay a press and release of an X11 keycode into it
+    fn send_key_to_window(&self, window_id: u32, keycode: u8) -> Result<()> {
+        let _ = (window_id, keycode);
+        anyhow::bail!("Input broadcasting is only supported on X11")
+    }
+
     /// Find a window by its title (returns window ID if found)
     fn find_window_by_title(&self, title: &str) -> Result<Option<u32>>;
 
-    /// Move a window to a specific position (X11 only, no-op on Wayland)
+    /// Whether this backend can move and resize windows. When false,
+    /// `move_window` is a no-op and stacking/layouts can't do anything.
+    fn supports_positioning(&self) -> bool {
+        false
+    }
+
+    /// Move a window to a specific position (no-op without `supports_positioning`)
     fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
-        // Default implementation: no-op (Wayland doesn't allow arbitrary window positioning)
+        // Default implementation: no-op (many Wayland compositors don't allow arbitrary window positioning)
         let _ = (window_id, x, y);
         Ok(())
     }
 
+    /// Whether this backend can raise and lower windows with `raise_all`/`lower_all`
+    fn supports_restacking(&self) -> bool {
+        false
+    }
+
+    /// Put every window above all others, e.g. to see the whole fleet at once
+    fn raise_all(&self, windows: &[EveWindow]) -> Result<()> {
+        let _ = windows;
+        anyhow::bail!("Raising windows isn't supported on this backend")
+    }
+
+    /// Put every window below all others
+    fn lower_all(&self, windows: &[EveWindow]) -> Result<()> {
+        let _ = windows;
+        anyhow::bail!("Lowering windows isn't supported on this backend")
+    }
+
+    /// Client window under the mouse pointer, if any
+    fn window_under_cursor(&self) -> Result<Option<u32>> {
+        anyhow::bail!("Finding the window under the cursor isn't supported on this backend")
+    }
+
+    /// Get a window's on-screen geometry (None where the backend can't query it)
+    fn get_window_geometry(&self, window_id: u32) -> Result<Option<WindowGeometry>> {
+        let _ = window_id;
+        Ok(None)
+    }
+
+    /// On-screen geometry of several windows, leaving out any it can't query.
+    /// Backends that list every window in one request override this so the
+    /// lookup costs one round trip instead of one per window.
+    fn window_geometries(&self, window_ids: &[u32]) -> Result<HashMap<u32, WindowGeometry>> {
+        let mut geometries = HashMap::new();
+        for &window_id in window_ids {
+            if let Some(geometry) = self.get_window_geometry(window_id)? {
+                geometries.insert(window_id, geometry);
+            }
+        }
+        Ok(geometries)
+    }
+
+    /// Connected monitors (empty where the backend can't list them)
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        Ok(Vec::new())
+    }
+
+    /// Where each of `windows` is now, looked up once for a whole restack.
+    /// Windows whose geometry is unknown are left out.
+    fn current_geometries(&self, windows: &[EveWindow]) -> HashMap<u32, WindowGeometry> {
+        let ids: Vec<u32> = windows.iter().map(|w| w.id).collect();
+        self.window_geometries(&ids).unwrap_or_default()
+    }
+
+    /// Whether a window is minimized (None where the backend can't tell)
+    fn is_minimized(&self, window_id: u32) -> Result<Option<bool>> {
+        let _ = window_id;
+        Ok(None)
+    }
+
+    /// Ask a window to close, as if its close button was clicked
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        let _ = window_id;
+        anyhow::bail!("Closing windows isn't supported on this backend")
+    }
+
     /// Minimize a window
     fn minimize_window(&self, window_id: u32) -> Result<()>;
 
@@ -53,6 +531,20 @@ pub enum WaylandCompositor {
     Other,    // Other/unknown compositor
 }
 
+/// Backend used for this session, as detected from the environment
+pub fn backend_name() -> &'static str {
+    match detect_display_server() {
+        DisplayServer::X11 => "X11",
+        DisplayServer::Wayland => match detect_wayland_compositor() {
+            WaylandCompositor::Kde => "KDE/KWin",
+            WaylandCompositor::Sway => "Sway",
+            WaylandCompositor::Hyprland => "Hyprland",
+            WaylandCompositor::Gnome => "GNOME Shell",
+            WaylandCompositor::Other => "generic wlroots (wlr-foreign-toplevel)",
+        },
+    }
+}
+
 /// Detect which display server is running
 pub fn detect_display_server() -> DisplayServer {
     if let Ok(session_type) = std::env::var("XDG_SESSION_TYPE") {
@@ -100,3 +592,307 @@ pub fn detect_wayland_compositor() -> WaylandCompositor {
 
     WaylandCompositor::Other
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steam_filter() -> WindowFilter {
+        WindowFilter {
+            exclude_wm_classes: vec!["Steam".to_string(), "steamwebhelper".to_string()],
+            wm_class_candidates: vec!["steam_app_8500".to_string(), "exefile.exe".to_string()],
+            title_prefix: Some("EVE - ".to_string()),
+            title_regex: None,
+            strip_prefix: true,
+            exclude_title_substrings: vec!["Launcher".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_display_title_strips_prefix() {
+        let filter = steam_filter();
+        assert_eq!(
+            filter.display_title("EVE - Alpha", &["steam_app_8500"]),
+            Some("Alpha".to_string())
+        );
+        assert_eq!(filter.display_title("EVE Launcher", &[]), None);
+        assert_eq!(filter.display_title("Firefox", &[]), None);
+    }
+
+    #[test]
+    fn test_display_title_excludes_steam_helper_classes() {
+        let filter = steam_filter();
+        assert_eq!(
+            filter.display_title("EVE - Alpha", &["steamwebhelper"]),
+            None
+        );
+        assert_eq!(filter.display_title("EVE", &["steam"]), None);
+    }
+
+    #[test]
+    fn test_display_title_matches_candidate_wm_class() {
+        let filter = steam_filter();
+        assert_eq!(
+            filter.display_title("EVE Online", &["exefile.exe"]),
+            Some("EVE Online".to_string())
+        );
+        assert_eq!(
+            filter.display_title("EVE - Alpha", &["Exefile.exe"]),
+            Some("Alpha".to_string())
+        );
+        // The launcher shares the client's class but is never a client
+        assert_eq!(
+            filter.display_title("EVE Launcher", &["steam_app_8500"]),
+            None
+        );
+        assert_eq!(filter.display_title("EVE Online", &["firefox"]), None);
+    }
+
+    #[test]
+    fn test_candidate_wm_class_keeps_client_through_title_changes() {
+        let filter = steam_filter();
+        assert_eq!(
+            filter.display_title("Loading", &["steam_app_8500"]),
+            Some("Loading".to_string())
+        );
+        // A blank title alone doesn't make a client; backends keep ones they already listed
+        assert_eq!(filter.display_title("", &["steam_app_8500"]), None);
+        // Without the class, the same titles aren't clients
+        assert_eq!(filter.display_title("Loading", &[]), None);
+        assert_eq!(filter.display_title("", &[]), None);
+    }
+
+    #[test]
+    fn test_display_title_with_custom_prefix_and_regex() {
+        let filter = WindowFilter {
+            title_prefix: Some("Other Game".to_string()),
+            title_regex: Some(Regex::new(r"^Client \[(.+)\]$").unwrap()),
+            strip_prefix: false,
+            ..steam_filter()
+        };
+
+        assert_eq!(
+            filter.display_title("Other Game - Bob", &[]),
+            Some("Other Game - Bob".to_string())
+        );
+        assert_eq!(
+            filter.display_title("Client [Carol]", &[]),
+            Some("Carol".to_string())
+        );
+        assert_eq!(filter.display_title("EVE - Alpha", &[]), None);
+    }
+
+    #[test]
+    fn test_display_title_with_localized_launcher_and_prefix() {
+        let filter = WindowFilter {
+            title_prefix: Some("EVE – ".to_string()),
+            exclude_title_substrings: vec!["Startprogramm".to_string()],
+            ..steam_filter()
+        };
+
+        // The class identifies a client whose title uses an unknown prefix
+        assert_eq!(
+            filter.display_title("EVE Online – Alpha", &["steam_app_8500"]),
+            Some("EVE Online – Alpha".to_string())
+        );
+        assert_eq!(
+            filter.display_title("EVE – Bravo", &[]),
+            Some("Bravo".to_string())
+        );
+        assert_eq!(
+            filter.display_title("EVE Startprogramm", &["steam_app_8500"]),
+            None
+        );
+    }
+
+    fn layout_config() -> Config {
+        Config {
+            display_width: 1920,
+            display_height: 1080,
+            panel_height: 40,
+            eve_width: 1000,
+            cascade_step: 40,
+            ..Config::for_display(1920, 1080)
+        }
+    }
+
+    #[test]
+    fn test_grid_layout_tiles_usable_area() {
+        let rects = layout_rects(
+            4,
+            &layout_config(),
+            LayoutMode::Grid,
+            display_area(&layout_config()),
+        );
+        let origins: Vec<(i32, i32)> = rects.iter().map(|r| (r.x, r.y)).collect();
+        assert_eq!(origins, vec![(0, 0), (960, 0), (0, 520), (960, 520)]);
+        assert!(rects.iter().all(|r| r.width == 960 && r.height == 520));
+
+        // Three clients still use a 2x2 grid
+        let rects = layout_rects(
+            3,
+            &layout_config(),
+            LayoutMode::Grid,
+            display_area(&layout_config()),
+        );
+        assert_eq!((rects[2].x, rects[2].y), (0, 520));
+    }
+
+    #[test]
+    fn test_columns_layout_splits_width() {
+        let rects = layout_rects(
+            3,
+            &layout_config(),
+            LayoutMode::Columns,
+            display_area(&layout_config()),
+        );
+        let xs: Vec<i32> = rects.iter().map(|r| r.x).collect();
+        assert_eq!(xs, vec![0, 640, 1280]);
+        assert!(rects.iter().all(|r| r.width == 640 && r.height == 1040));
+    }
+
+    #[test]
+    fn test_cascade_layout_offsets_each_client() {
+        let rects = layout_rects(
+            3,
+            &layout_config(),
+            LayoutMode::Cascade,
+            display_area(&layout_config()),
+        );
+        assert_eq!(rects[1].x - rects[0].x, 40);
+        assert_eq!(rects[2].y, 80);
+        assert_eq!(rects[0].height, 1040 - 80);
+        // The last client stays on screen
+        let last = rects[2];
+        assert!(last.x as u32 + last.width <= 1920);
+    }
+
+    #[test]
+    fn test_cascade_layout_caps_an_oversized_step() {
+        let config = Config {
+            cascade_step: u32::MAX,
+            ..layout_config()
+        };
+        let rects = layout_rects(3, &config, LayoutMode::Cascade, display_area(&config));
+
+        // Capped at a third of the display height
+        assert_eq!(rects[1].y, 1040 / 3);
+        for rect in &rects {
+            assert!(rect.width > 0 && rect.height > 0);
+            assert!(rect.x >= 0 && rect.x as u32 + rect.width <= 1920);
+            assert!(rect.y as u32 + rect.height <= 1040);
+        }
+    }
+
+    #[test]
+    fn test_geometry_matches_within_tolerance() {
+        let target = WindowGeometry {
+            x: 460,
+            y: 0,
+            width: 1000,
+            height: 1040,
+        };
+        let nudged = WindowGeometry {
+            x: 461,
+            height: 1038,
+            ..target
+        };
+        assert!(target.matches(&target, 0));
+        assert!(nudged.matches(&target, 2));
+        assert!(!nudged.matches(&target, 1));
+    }
+
+    #[test]
+    fn test_layout_on_monitor_is_offset_to_its_origin() {
+        let config = layout_config();
+        let side = Monitor {
+            name: "DP-2".to_string(),
+            x: 3440,
+            y: 200,
+            width: 1080,
+            height: 1920,
+        };
+        let area = side.usable_area(&config);
+        assert_eq!(area.height, 1920 - 40);
+
+        let rects = layout_rects(2, &config, LayoutMode::Columns, area);
+        assert_eq!((rects[0].x, rects[0].y), (3440, 200));
+        assert_eq!(rects[1].x, 3440 + 540);
+
+        // Stack centers the client on the monitor
+        let rects = layout_rects(1, &config, LayoutMode::Stack, area);
+        assert_eq!((rects[0].x, rects[0].width), (3440 + 40, 1000));
+    }
+
+    #[test]
+    fn test_character_overrides_are_taken_out_of_the_layout() {
+        let window = |id, title: &str| EveWindow {
+            id,
+            title: title.to_string(),
+            demands_attention: false,
+        };
+        let corner = WindowGeometry {
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+        };
+        let (rest, placed) = split_character_overrides(
+            &[window(1, "Main"), window(2, "Scout"), window(3, "Alt")],
+            &[("Scout".to_string(), corner)],
+        );
+        let ids: Vec<u32> = rest.iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(placed, vec![(2, corner)]);
+    }
+
+    #[test]
+    fn test_layout_targets_keep_overrides_out_of_the_layout() {
+        let window = |id, title: &str| EveWindow {
+            id,
+            title: title.to_string(),
+            demands_attention: false,
+        };
+        let corner = WindowGeometry {
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+        };
+        let config = layout_config();
+        let area = display_area(&config);
+        let windows = [window(1, "Main"), window(2, "Scout"), window(3, "Alt")];
+
+        let targets = layout_targets(
+            &windows,
+            &[("Scout".to_string(), corner)],
+            &config,
+            LayoutMode::Columns,
+            area,
+        );
+        let columns = layout_rects(2, &config, LayoutMode::Columns, area);
+        assert_eq!(targets, vec![columns[0], corner, columns[1]]);
+    }
+
+    #[test]
+    fn test_stack_uses_configured_position() {
+        let config = layout_config();
+        assert_eq!((config.eve_x(), config.eve_y()), (460, 0));
+
+        let config = Config {
+            eve_x: Some(100),
+            eve_y: Some(20),
+            ..layout_config()
+        };
+        assert_eq!((config.eve_x(), config.eve_y()), (100, 20));
+
+        let area = WindowGeometry {
+            x: 1920,
+            y: 0,
+            width: 2560,
+            height: 1440,
+        };
+        let rects = layout_rects(2, &config, LayoutMode::Stack, area);
+        assert_eq!((rects[1].x, rects[1].y), (1920 + 100, 20));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::config::Config;
+use crate::window_manager::{EveWindow, Monitor, WindowFilter, WindowManager};
+use anyhow::{Context, Result};
+use std::sync::Mutex;
+use wayland_client::globals::{registry_queue_init, GlobalList, GlobalListContents};
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{
+    event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self, ZwlrForeignToplevelHandleV1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self, ZwlrForeignToplevelManagerV1,
+};
+
+/// What the compositor has told us about one toplevel window
+#[derive(Default)]
+struct Toplevel {
+    title: String,
+    app_id: String,
+    activated: bool,
+    minimized: bool,
+}
+
+#[derive(Default)]
+struct ToplevelState {
+    toplevels: Vec<(ZwlrForeignToplevelHandleV1, Toplevel)>,
+    outputs: Vec<(WlOutput, Monitor)>,
+}
+
+struct Inner {
+    queue: EventQueue<ToplevelState>,
+    state: ToplevelState,
+    seat: WlSeat,
+}
+
+/// Backend for any wlroots-based compositor (river, Wayfire, labwc, ...) using
+/// the wlr-foreign-toplevel-management protocol
+pub struct WlrootsManager {
+    conn: Connection,
+    inner: Mutex<Inner>,
+    filter: WindowFilter,
+}
+
+impl WlrootsManager {
+    pub fn new(filter: WindowFilter) -> Result<Self> {
+        let conn = Connection::connect_to_env().context("Failed to connect to Wayland display")?;
+        let (globals, mut queue) = registry_queue_init::<ToplevelState>(&conn)
+            .context("Failed to read Wayland globals")?;
+        let qh = queue.handle();
+
+        globals
+            .bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ())
+            .context("Compositor doesn't support wlr-foreign-toplevel-management")?;
+        let seat = globals
+            .bind::<WlSeat, _, _>(&qh, 1..=1, ())
+            .context("Compositor has no seat to activate windows with")?;
+
+        // Outputs are only used to list monitors, so a compositor without any is fine
+        let mut state = ToplevelState::default();
+        bind_outputs(&globals, &qh, &mut state);
+
+        // Receive the initial set of toplevels and their titles
+        queue.roundtrip(&mut state)?;
+
+        Ok(Self {
+            conn,
+            inner: Mutex::new(Inner { queue, state, seat }),
+            filter,
+        })
+    }
+
+    /// Process pending compositor events so the toplevel list is current
+    fn with_toplevels<T>(&self, f: impl FnOnce(&Inner) -> T) -> Result<T> {
+        let mut inner = self.inner.lock().unwrap();
+        let Inner { queue, state, .. } = &mut *inner;
+        queue
+            .roundtrip(state)
+            .context("Lost connection to Wayland compositor")?;
+        Ok(f(&inner))
+    }
+
+    fn find_handle(inner: &Inner, window_id: u32) -> Result<ZwlrForeignToplevelHandleV1> {
+        inner
+            .state
+            .toplevels
+            .iter()
+            .find(|(handle, _)| handle.id().protocol_id() == window_id)
+            .map(|(handle, _)| handle.clone())
+            .with_context(|| format!("Window {} no longer exists", window_id))
+    }
+
+    fn request(
+        &self,
+        window_id: u32,
+        send: impl FnOnce(&ZwlrForeignToplevelHandleV1, &WlSeat),
+    ) -> Result<()> {
+        self.with_toplevels(|inner| {
+            let handle = Self::find_handle(inner, window_id)?;
+            send(&handle, &inner.seat);
+            Ok::<_, anyhow::Error>(())
+        })??;
+        self.conn
+            .flush()
+            .context("Failed to send request to compositor")?;
+        Ok(())
+    }
+}
+
+fn bind_outputs(globals: &GlobalList, qh: &QueueHandle<ToplevelState>, state: &mut ToplevelState) {
+    for global in globals.contents().clone_list() {
+        if global.interface == WlOutput::interface().name {
+            let output: WlOutput =
+                globals
+                    .registry()
+                    .bind(global.name, global.version.min(4), qh, ());
+            state.outputs.push((output, Monitor::default()));
+        }
+    }
+}
+
+/// Every wl_output and its current mode. Works on any compositor, since only
+/// the core protocol is needed.
+pub fn output_monitors() -> Result<Vec<Monitor>> {
+    let conn = Connection::connect_to_env().context("Failed to connect to Wayland display")?;
+    let (globals, mut queue) =
+        registry_queue_init::<ToplevelState>(&conn).context("Failed to read Wayland globals")?;
+    let qh = queue.handle();
+
+    let mut state = ToplevelState::default();
+    bind_outputs(&globals, &qh, &mut state);
+    queue.roundtrip(&mut state)?;
+
+    Ok(state
+        .outputs
+        .into_iter()
+        .map(|(_, monitor)| monitor)
+        .collect())
+}
+
+impl WindowManager for WlrootsManager {
+    fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
+        self.with_toplevels(|inner| {
+            inner
+                .state
+                .toplevels
+                .iter()
+                .filter_map(|(handle, toplevel)| {
+                    let title = self
+                        .filter
+                        .display_title(&toplevel.title, &[toplevel.app_id.as_str()])?;
+                    Some(EveWindow {
+                        id: handle.id().protocol_id(),
+                        title,
+                        demands_attention: false,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn activate_window(&self, window_id: u32) -> Result<()> {
+        self.request(window_id, |handle, seat| handle.activate(seat))
+    }
+
+    fn stack_windows(&self, _windows: &[EveWindow], _config: &Config) -> Result<()> {
+        anyhow::bail!(
+            "Stacking isn't available on this compositor (wlr-foreign-toplevel can't move windows)"
+        )
+    }
+
+    fn get_active_window(&self) -> Result<u32> {
+        self.with_toplevels(|inner| {
+            inner
+                .state
+                .toplevels
+                .iter()
+                .find(|(_, toplevel)| toplevel.activated)
+                .map(|(handle, _)| handle.id().protocol_id())
+        })?
+        .context("No active window")
+    }
+
+    fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
+        self.with_toplevels(|inner| {
+            inner
+                .state
+                .toplevels
+                .iter()
+                .find(|(_, toplevel)| toplevel.title == title)
+                .map(|(handle, _)| handle.id().protocol_id())
+        })
+    }
+
+    /// Outputs as advertised by wl_output. Without xdg-output the positions are
+    /// whatever the compositor reports in the geometry event (often 0,0).
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        self.with_toplevels(|inner| {
+            inner
+                .state
+                .outputs
+                .iter()
+                .map(|(_, monitor)| monitor.clone())
+                .collect()
+        })
+    }
+
+    fn is_minimized(&self, window_id: u32) -> Result<Option<bool>> {
+        self.with_toplevels(|inner| {
+            inner
+                .state
+                .toplevels
+                .iter()
+                .find(|(handle, _)| handle.id().protocol_id() == window_id)
+                .map(|(_, toplevel)| toplevel.minimized)
+        })
+    }
+
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        self.request(window_id, |handle, _| handle.close())
+    }
+
+    fn minimize_window(&self, window_id: u32) -> Result<()> {
+        self.request(window_id, |handle, _| handle.set_minimized())
+    }
+
+    fn restore_window(&self, window_id: u32) -> Result<()> {
+        self.request(window_id, |handle, _| handle.unset_minimized())
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for ToplevelState {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for ToplevelState {
+    fn event(
+        _state: &mut Self,
+        _seat: &WlSeat,
+        _event: <WlSeat as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlOutput, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        output: &WlOutput,
+        event: wl_output::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some((_, monitor)) = state.outputs.iter_mut().find(|(o, _)| o == output) else {
+            return;
+        };
+
+        match event {
+            wl_output::Event::Geometry { x, y, model, .. } => {
+                monitor.x = x;
+                monitor.y = y;
+                // wl_output v4 sends a proper connector name; older ones only have the model
+                if monitor.name.is_empty() {
+                    monitor.name = model;
+                }
+            }
+            wl_output::Event::Mode {
+                flags: WEnum::Value(flags),
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => {
+                monitor.width = width.max(0) as u32;
+                monitor.height = height.max(0) as u32;
+            }
+            wl_output::Event::Name { name } => {
+                monitor.name = name;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        _manager: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.push((toplevel, Toplevel::default()));
+        }
+    }
+
+    event_created_child!(ToplevelState, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(index) = state.toplevels.iter().position(|(h, _)| h == handle) else {
+            return;
+        };
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                state.toplevels[index].1.title = title;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                state.toplevels[index].1.app_id = app_id;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: raw } => {
+                // An array of native-endian u32 state values
+                let states: Vec<u32> = raw
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect();
+                let toplevel = &mut state.toplevels[index].1;
+                toplevel.activated =
+                    states.contains(&(zwlr_foreign_toplevel_handle_v1::State::Activated as u32));
+                toplevel.minimized =
+                    states.contains(&(zwlr_foreign_toplevel_handle_v1::State::Minimized as u32));
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                let (handle, _) = state.toplevels.remove(index);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,89 +1,412 @@
-use crate::config::Config;
-use crate::window_manager::{EveWindow, WindowManager};
+use crate::active_border::ActiveBorder;
+use crate::config::{Config, X11ActivationMethod};
+use crate::window_manager::{
+    is_placed_at, EveWindow, Monitor, WindowFilter, WindowGeometry, WindowManager,
+    CHARACTER_SELECT_TITLE,
+};
 use anyhow::{Context, Result};
-use std::sync::Arc;
+use log::{info, warn};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use x11rb::connection::Connection;
+use x11rb::errors::{ConnectionError, ReplyError, ReplyOrIdError};
+use x11rb::protocol::randr::ConnectionExt as _;
 use x11rb::protocol::xproto::*;
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::protocol::Event;
 use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::COPY_DEPTH_FROM_PARENT;
 
-pub struct X11Manager {
+/// How long to let the window manager settle focus before faking a key
+const BROADCAST_FOCUS_DELAY_MS: u64 = 30;
+
+x11rb::atom_manager! {
+    /// Atoms interned once at startup, so lookups don't cost a round-trip each
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST,
+        _NET_CLOSE_WINDOW,
+        _NET_RESTACK_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_STATE,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_WINDOW_OPACITY,
+        UTF8_STRING,
+        WM_CHANGE_STATE,
+        _NICOTINE_TIMESTAMP,
+    }
+}
+
+/// What `get_eve_windows` needs to know about one client window
+struct WindowProperties {
+    title: String,
+    wm_class: Vec<String>,
+    states: Vec<Atom>,
+}
+
+/// Wait before the first reconnection attempt, doubled after each failure
+const RECONNECT_MIN_BACKOFF: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long to wait for the PropertyNotify carrying the server time
+const SERVER_TIME_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Everything tied to one connection to the X server; replaced on reconnect
+struct Session {
     conn: Arc<RustConnection>,
     screen_num: usize,
-    net_active_window_atom: Atom,
+    atoms: Atoms,
+    active_border: Option<ActiveBorder>,
+    /// Unmapped window whose PropertyNotify events tell us the server time
+    time_window: Window,
 }
 
-impl X11Manager {
-    pub fn new() -> Result<Self> {
+impl Session {
+    /// Connect to `display`, or to $DISPLAY if it's None
+    fn connect(display: Option<&str>, border: Option<([u8; 3], u32)>) -> Result<Self> {
         let (conn, screen_num) =
-            RustConnection::connect(None).context("Failed to connect to X11 server")?;
+            RustConnection::connect(display).context("Failed to connect to X11 server")?;
 
         let conn = Arc::new(conn);
 
-        // Pre-cache the _NET_ACTIVE_WINDOW atom (do roundtrip once at startup)
-        let net_active_window_atom = conn
-            .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
-            .reply()?
-            .atom;
+        // All requests go out together and share one round-trip
+        let atoms = Atoms::new(&*conn)?
+            .reply()
+            .context("Failed to intern X11 atoms")?;
+
+        let time_window = conn.generate_id()?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            time_window,
+            conn.setup().roots[screen_num].root,
+            -1,
+            -1,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+
+        let active_border = match border {
+            Some((color, thickness)) => Some(ActiveBorder::new(
+                Arc::clone(&conn),
+                screen_num,
+                color,
+                thickness,
+            )?),
+            None => None,
+        };
 
         Ok(Self {
             conn,
             screen_num,
-            net_active_window_atom,
+            atoms,
+            active_border,
+            time_window,
         })
     }
 
-    pub fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
-        let screen = &self.conn.setup().roots[self.screen_num];
-        let root = screen.root;
+    /// Current X server time. Touching a property on our own window makes the
+    /// server send a PropertyNotify carrying it; nothing else selects events on
+    /// this connection, so the next matching one is ours. Falls back to
+    /// CURRENT_TIME if it doesn't arrive in time.
+    fn server_time(&self) -> Result<Timestamp> {
+        self.conn.change_property8(
+            PropMode::APPEND,
+            self.time_window,
+            self.atoms._NICOTINE_TIMESTAMP,
+            AtomEnum::STRING,
+            &[],
+        )?;
+        self.conn.flush()?;
 
-        // Get _NET_CLIENT_LIST atom
-        let net_client_list = self
-            .conn
-            .intern_atom(false, b"_NET_CLIENT_LIST")?
-            .reply()?
-            .atom;
-
-        // Get list of all windows
-        let client_list_reply = self
-            .conn
-            .get_property(false, root, net_client_list, AtomEnum::WINDOW, 0, u32::MAX)?
-            .reply()?;
+        let deadline = Instant::now() + SERVER_TIME_TIMEOUT;
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event()? {
+                Some(Event::PropertyNotify(event)) if event.window == self.time_window => {
+                    return Ok(event.time);
+                }
+                Some(_) => {}
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        warn!("X server didn't report its time, using CURRENT_TIME");
+        Ok(x11rb::CURRENT_TIME)
+    }
+}
 
-        let windows: Vec<u32> = client_list_reply
-            .value32()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get window list"))?
-            .collect();
+/// When the next reconnection may be tried, so a dead X server isn't hammered
+struct Reconnect {
+    next_attempt: Instant,
+    backoff: Duration,
+}
+
+pub struct X11Manager {
+    session: RwLock<Session>,
+    /// Display to reconnect to; None for $DISPLAY
+    display: Option<String>,
+    reconnect: Mutex<Reconnect>,
+    filter: WindowFilter,
+    /// Color and thickness of the active border, to recreate it on reconnect
+    border: Option<([u8; 3], u32)>,
+    activate_retries: u32,
+    activate_retry_delay: Duration,
+    activation_method: X11ActivationMethod,
+    /// Clients in the last listing, so one that blanks its title while
+    /// loading stays listed
+    listed: Mutex<HashSet<u32>>,
+}
+
+/// _NET_WM_WINDOW_OPACITY value for an opacity from 0.0 to 1.0
+fn opacity_value(opacity: f32) -> u32 {
+    (f64::from(opacity.clamp(0.0, 1.0)) * f64::from(u32::MAX)).round() as u32
+}
+
+/// Whether an error means the connection to the X server is gone, as opposed
+/// to a request failing (e.g. for a window that was just closed)
+fn is_connection_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<ConnectionError>()
+            || matches!(
+                cause.downcast_ref::<ReplyError>(),
+                Some(ReplyError::ConnectionError(_))
+            )
+            || matches!(
+                cause.downcast_ref::<ReplyOrIdError>(),
+                Some(ReplyOrIdError::ConnectionError(_))
+            )
+    })
+}
 
+impl X11Manager {
+    pub fn new(filter: WindowFilter) -> Result<Self> {
+        Self::with_display(filter, None)
+    }
+
+    /// Connect to a display other than $DISPLAY
+    pub fn with_display(filter: WindowFilter, display: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            session: RwLock::new(Session::connect(display, None)?),
+            display: display.map(str::to_string),
+            reconnect: Mutex::new(Reconnect {
+                next_attempt: Instant::now(),
+                backoff: RECONNECT_MIN_BACKOFF,
+            }),
+            filter,
+            border: None,
+            activate_retries: 0,
+            activate_retry_delay: Duration::ZERO,
+            activation_method: X11ActivationMethod::default(),
+            listed: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn conn(&self) -> Arc<RustConnection> {
+        Arc::clone(&self.session.read().unwrap().conn)
+    }
+
+    fn atoms(&self) -> Atoms {
+        self.session.read().unwrap().atoms
+    }
+
+    fn screen_num(&self) -> usize {
+        self.session.read().unwrap().screen_num
+    }
+
+    /// Run an X11 operation, reconnecting and retrying once if the connection
+    /// turns out to be dead (X server restarted)
+    fn with_reconnect<T>(&self, op: impl Fn() -> Result<T>) -> Result<T> {
+        match op() {
+            Err(e) if is_connection_error(&e) => {
+                self.reconnect()
+                    .with_context(|| format!("Lost connection to X server: {}", e))?;
+                op()
+            }
+            result => result,
+        }
+    }
+
+    fn reconnect(&self) -> Result<()> {
+        let mut reconnect = self.reconnect.lock().unwrap();
+        let now = Instant::now();
+        if now < reconnect.next_attempt {
+            anyhow::bail!("Waiting to reconnect");
+        }
+
+        match Session::connect(self.display.as_deref(), self.border) {
+            Ok(session) => {
+                *self.session.write().unwrap() = session;
+                reconnect.backoff = RECONNECT_MIN_BACKOFF;
+                info!("Reconnected to X server");
+                Ok(())
+            }
+            Err(e) => {
+                reconnect.next_attempt = now + reconnect.backoff;
+                reconnect.backoff = (reconnect.backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                Err(e)
+            }
+        }
+    }
+
+    /// Check up to `retries` times, `delay_ms` apart, that an activation took
+    /// and resend it once if it didn't. Some WMs drop activations that race
+    /// with their focus-stealing prevention.
+    pub fn set_activation_retries(&mut self, retries: u32, delay_ms: u64) {
+        self.activate_retries = retries;
+        self.activate_retry_delay = Duration::from_millis(delay_ms);
+    }
+
+    pub fn set_activation_method(&mut self, method: X11ActivationMethod) {
+        self.activation_method = method;
+    }
+
+    /// Draw a colored border around whichever client we activate
+    pub fn enable_active_border(&mut self, color: [u8; 3], thickness: u32) -> Result<()> {
+        let border = ActiveBorder::new(self.conn(), self.screen_num(), color, thickness)?;
+        self.session.get_mut().unwrap().active_border = Some(border);
+        self.border = Some((color, thickness));
+        Ok(())
+    }
+
+    pub fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
+        let windows = self.client_list()?;
         let mut eve_windows = Vec::new();
+        let mut listed = self.listed.lock().unwrap();
 
-        for &window in &windows {
-            if let Ok(title) = self.get_window_title(window) {
-                // Filter for EVE windows (steam_app_8500) and exclude launcher
-                if title.starts_with("EVE - ") && !title.contains("Launcher") {
-                    eve_windows.push(EveWindow {
-                        id: window,
-                        title: title.trim_start_matches("EVE - ").to_string(),
-                    });
+        for (window, properties) in windows.iter().zip(self.query_windows(&windows)?) {
+            let Some(properties) = properties else {
+                continue;
+            };
+            // Filter for EVE windows (steam_app_8500) and exclude launcher/Steam helpers
+            let wm_class: Vec<&str> = properties.wm_class.iter().map(String::as_str).collect();
+            let title = match self.filter.display_title(&properties.title, &wm_class) {
+                // EVE blanks its title while loading; keep a client we already
+                // had listed like one at character select instead of dropping it
+                None if properties.title.is_empty() && listed.contains(window) => {
+                    Some(CHARACTER_SELECT_TITLE.to_string())
                 }
+                title => title,
+            };
+            if let Some(title) = title {
+                eve_windows.push(EveWindow {
+                    id: *window,
+                    title,
+                    demands_attention: properties
+                        .states
+                        .contains(&self.atoms()._NET_WM_STATE_DEMANDS_ATTENTION),
+                });
             }
         }
 
+        *listed = eve_windows.iter().map(|w| w.id).collect();
         Ok(eve_windows)
     }
 
+    /// Top-level client windows from _NET_CLIENT_LIST
+    fn client_list(&self) -> Result<Vec<u32>> {
+        let root = self.conn().setup().roots[self.screen_num()].root;
+        let reply = self
+            .conn()
+            .get_property(
+                false,
+                root,
+                self.atoms()._NET_CLIENT_LIST,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+
+        let windows = reply
+            .value32()
+            .ok_or_else(|| anyhow::anyhow!("Failed to get window list"))?
+            .collect();
+        Ok(windows)
+    }
+
+    /// Read the title, WM_CLASS and _NET_WM_STATE of many windows at once. All
+    /// requests are sent before any reply is read, so they pipeline instead of
+    /// costing a round-trip per window. Windows that vanished meanwhile are None.
+    fn query_windows(&self, windows: &[u32]) -> Result<Vec<Option<WindowProperties>>> {
+        let conn = self.conn();
+        let mut pending = Vec::with_capacity(windows.len());
+        for &window in windows {
+            pending.push([
+                conn.get_property(
+                    false,
+                    window,
+                    self.atoms()._NET_WM_NAME,
+                    self.atoms().UTF8_STRING,
+                    0,
+                    1024,
+                )?,
+                conn.get_property(false, window, AtomEnum::WM_NAME, AtomEnum::STRING, 0, 1024)?,
+                conn.get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 1024)?,
+                conn.get_property(
+                    false,
+                    window,
+                    self.atoms()._NET_WM_STATE,
+                    AtomEnum::ATOM,
+                    0,
+                    1024,
+                )?,
+            ]);
+        }
+
+        Ok(pending
+            .into_iter()
+            .map(|[net_wm_name, wm_name, wm_class, wm_state]| {
+                let (net_wm_name, wm_name, wm_class, wm_state) = (
+                    net_wm_name.reply().ok()?,
+                    wm_name.reply().ok()?,
+                    wm_class.reply().ok()?,
+                    wm_state.reply().ok()?,
+                );
+
+                // Prefer _NET_WM_NAME (UTF-8), fall back to WM_NAME
+                let title = match String::from_utf8(net_wm_name.value) {
+                    Ok(title) if !title.is_empty() => title,
+                    _ => String::from_utf8_lossy(&wm_name.value).to_string(),
+                };
+
+                Some(WindowProperties {
+                    title,
+                    wm_class: wm_class
+                        .value
+                        .split(|&b| b == 0)
+                        .filter(|part| !part.is_empty())
+                        .map(|part| String::from_utf8_lossy(part).to_string())
+                        .collect(),
+                    states: wm_state
+                        .value32()
+                        .map(Iterator::collect)
+                        .unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
     pub fn get_active_window(&self) -> Result<u32> {
-        let screen = &self.conn.setup().roots[self.screen_num];
+        let conn = self.conn();
+        let screen = &conn.setup().roots[self.screen_num()];
         let root = screen.root;
 
-        let net_active_window = self
-            .conn
-            .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
-            .reply()?
-            .atom;
-
         let reply = self
-            .conn
-            .get_property(false, root, net_active_window, AtomEnum::WINDOW, 0, 1)?
+            .conn()
+            .get_property(
+                false,
+                root,
+                self.atoms()._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )?
             .reply()?;
 
         let active: Vec<u32> = reply
@@ -95,31 +418,174 @@ impl X11Manager {
     }
 
     pub fn activate_window(&self, window_id: u32) -> Result<()> {
-        let screen = &self.conn.setup().roots[self.screen_num];
+        self.send_activation(window_id)?;
+
+        if self.activate_retries > 0 && !self.wait_for_active(window_id) {
+            self.send_activation(window_id)?;
+        }
+
+        if let Some(border) = &self.session.read().unwrap().active_border {
+            if let Err(e) = border.surround(window_id) {
+                warn!("Failed to draw active border: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn dim_inactive(&self, window_ids: &[u32], active_id: u32, opacity: f32) -> Result<()> {
+        for &window_id in window_ids {
+            let opacity = if window_id == active_id { 1.0 } else { opacity };
+            self.set_window_opacity(window_id, opacity)?;
+        }
+        Ok(())
+    }
+
+    pub fn restore_opacity(&self) -> Result<()> {
+        for window in self.get_eve_windows()? {
+            self.set_window_opacity(window.id, 1.0)?;
+        }
+        Ok(())
+    }
+
+    /// Set _NET_WM_WINDOW_OPACITY, which compositors (picom, KWin, Mutter, ...)
+    /// apply to the window. Full opacity removes the property instead.
+    pub fn set_window_opacity(&self, window_id: u32, opacity: f32) -> Result<()> {
+        let conn = self.conn();
+        let atom = self.atoms()._NET_WM_WINDOW_OPACITY;
+        if opacity >= 1.0 {
+            conn.delete_property(window_id, atom)?;
+        } else {
+            conn.change_property32(
+                PropMode::REPLACE,
+                window_id,
+                atom,
+                AtomEnum::CARDINAL,
+                &[opacity_value(opacity)],
+            )?;
+        }
+        conn.flush()?;
+        Ok(())
+    }
+
+    /// Poll _NET_ACTIVE_WINDOW until it reports `window_id` or retries run out
+    fn wait_for_active(&self, window_id: u32) -> bool {
+        for _ in 0..self.activate_retries {
+            std::thread::sleep(self.activate_retry_delay);
+            if self.get_active_window().ok() == Some(window_id) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn send_activation(&self, window_id: u32) -> Result<()> {
+        match self.activation_method {
+            X11ActivationMethod::NetActiveWindow => self.request_activation(window_id),
+            X11ActivationMethod::SetInputFocus => self.focus_directly(window_id),
+            X11ActivationMethod::Wmctrl => {
+                let status = std::process::Command::new("wmctrl")
+                    .args(["-i", "-a", &format!("0x{:08x}", window_id)])
+                    .status()
+                    .context("Failed to run wmctrl")?;
+                if !status.success() {
+                    anyhow::bail!("wmctrl couldn't activate 0x{:08x}", window_id);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Ask the window manager to activate a window, EWMH style
+    fn request_activation(&self, window_id: u32) -> Result<()> {
+        let conn = self.conn();
+        let screen = &conn.setup().roots[self.screen_num()];
         let root = screen.root;
 
         let current_active = self.get_active_window().unwrap_or(0);
+        // Focus-stealing prevention (Mutter, Openbox, ...) drops activations
+        // stamped CurrentTime, so send the real time of this request
+        let time = self.session.read().unwrap().server_time()?;
 
         let event = ClientMessageEvent {
             response_type: CLIENT_MESSAGE_EVENT,
             format: 32,
             sequence: 0,
             window: window_id,
-            type_: self.net_active_window_atom,
-            data: ClientMessageData::from([2, x11rb::CURRENT_TIME, current_active, 0, 0]),
+            type_: self.atoms()._NET_ACTIVE_WINDOW,
+            data: ClientMessageData::from([2, time, current_active, 0, 0]),
         };
 
-        self.conn.send_event(
+        self.conn().send_event(
             false,
             root,
             EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
             event,
         )?;
 
-        self.conn
-            .set_input_focus(InputFocus::PARENT, window_id, x11rb::CURRENT_TIME)?;
+        self.conn()
+            .set_input_focus(InputFocus::PARENT, window_id, time)?;
 
-        self.conn.flush()?;
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    /// Raise and focus a window ourselves, for window managers that ignore
+    /// _NET_ACTIVE_WINDOW
+    fn focus_directly(&self, window_id: u32) -> Result<()> {
+        let conn = self.conn();
+        let time = self.session.read().unwrap().server_time()?;
+        conn.configure_window(
+            window_id,
+            &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+        )?;
+        conn.set_input_focus(InputFocus::PARENT, window_id, time)?;
+        conn.flush()?;
+        Ok(())
+    }
+
+    /// The client whose window, or the WM frame around it, is under the pointer
+    pub fn window_under_cursor(&self) -> Result<Option<u32>> {
+        let conn = self.conn();
+        let root = conn.setup().roots[self.screen_num()].root;
+        let clients = self.client_list()?;
+        let pointer = conn.query_pointer(root)?.reply()?;
+
+        // Descend from the top-level window (often a frame) to the client in it
+        let mut window = pointer.child;
+        while window != x11rb::NONE {
+            if clients.contains(&window) {
+                return Ok(Some(window));
+            }
+            window = conn
+                .translate_coordinates(root, window, pointer.root_x, pointer.root_y)?
+                .reply()?
+                .child;
+        }
+        Ok(None)
+    }
+
+    /// Ask the window manager to restack each window (_NET_RESTACK_WINDOW),
+    /// which moves its frame rather than just the client inside it
+    fn restack(&self, windows: &[EveWindow], mode: StackMode) -> Result<()> {
+        let root = self.conn().setup().roots[self.screen_num()].root;
+        for window in windows {
+            let event = ClientMessageEvent {
+                response_type: CLIENT_MESSAGE_EVENT,
+                format: 32,
+                sequence: 0,
+                window: window.id,
+                type_: self.atoms()._NET_RESTACK_WINDOW,
+                data: ClientMessageData::from([2, x11rb::NONE, u32::from(mode), 0, 0]),
+            };
+            self.conn().send_event(
+                false,
+                root,
+                EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+                event,
+            )?;
+        }
+        self.conn().flush()?;
         Ok(())
     }
 
@@ -139,92 +605,104 @@ impl X11Manager {
                 .width(width)
                 .height(height);
 
-            self.conn.configure_window(window.id, &values)?;
+            self.conn().configure_window(window.id, &values)?;
         }
 
-        self.conn.flush()?;
+        self.conn().flush()?;
         Ok(())
     }
 
-    fn get_window_title(&self, window: u32) -> Result<String> {
-        // Try _NET_WM_NAME first (UTF-8)
-        let net_wm_name = self.conn.intern_atom(false, b"_NET_WM_NAME")?.reply()?.atom;
-
-        let utf8_string = self.conn.intern_atom(false, b"UTF8_STRING")?.reply()?.atom;
-
-        if let Ok(reply) = self
-            .conn
-            .get_property(false, window, net_wm_name, utf8_string, 0, 1024)?
-            .reply()
-        {
-            if !reply.value.is_empty() {
-                if let Ok(title) = String::from_utf8(reply.value.clone()) {
-                    return Ok(title);
-                }
-            }
-        }
-
-        // Fall back to WM_NAME
-        if let Ok(reply) = self
-            .conn
-            .get_property(false, window, AtomEnum::WM_NAME, AtomEnum::STRING, 0, 1024)?
-            .reply()
-        {
-            if !reply.value.is_empty() {
-                return Ok(String::from_utf8_lossy(&reply.value).to_string());
-            }
-        }
+    pub fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
+        let windows = self.client_list()?;
+        let properties = self.query_windows(&windows)?;
 
-        Ok(String::new())
+        Ok(windows
+            .into_iter()
+            .zip(properties)
+            .find(|(_, properties)| properties.as_ref().is_some_and(|p| p.title == title))
+            .map(|(window, _)| window))
     }
 
-    pub fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
-        let screen = &self.conn.setup().roots[self.screen_num];
-        let root = screen.root;
-
-        let net_client_list = self
-            .conn
-            .intern_atom(false, b"_NET_CLIENT_LIST")?
-            .reply()?
-            .atom;
+    pub fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
+        let values = ConfigureWindowAux::new().x(x).y(y);
+        self.conn().configure_window(window_id, &values)?;
+        self.conn().flush()?;
+        Ok(())
+    }
 
-        let client_list_reply = self
-            .conn
-            .get_property(false, root, net_client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+    pub fn get_window_geometry(&self, window_id: u32) -> Result<WindowGeometry> {
+        let geometry = self.conn().get_geometry(window_id)?.reply()?;
+        let root = self.conn().setup().roots[self.screen_num()].root;
+        // Geometry is relative to the parent (often a WM frame), so translate to root
+        let origin = self
+            .conn()
+            .translate_coordinates(window_id, root, 0, 0)?
             .reply()?;
 
-        let windows: Vec<u32> = client_list_reply
-            .value32()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get window list"))?
-            .collect();
+        Ok(WindowGeometry {
+            x: i32::from(origin.dst_x),
+            y: i32::from(origin.dst_y),
+            width: u32::from(geometry.width),
+            height: u32::from(geometry.height),
+        })
+    }
 
-        for &window in &windows {
-            if let Ok(window_title) = self.get_window_title(window) {
-                if window_title == title {
-                    return Ok(Some(window));
-                }
-            }
-        }
+    /// Active monitors as reported by RandR 1.5
+    pub fn monitors(&self) -> Result<Vec<Monitor>> {
+        let root = self.conn().setup().roots[self.screen_num()].root;
+        let reply = self
+            .conn()
+            .randr_get_monitors(root, true)?
+            .reply()
+            .context("Failed to query RandR monitors")?;
 
-        Ok(None)
+        reply
+            .monitors
+            .iter()
+            .map(|info| {
+                let name = self.conn().get_atom_name(info.name)?.reply()?.name;
+                Ok(Monitor {
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    x: i32::from(info.x),
+                    y: i32::from(info.y),
+                    width: u32::from(info.width),
+                    height: u32::from(info.height),
+                })
+            })
+            .collect()
     }
 
-    pub fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
-        let values = ConfigureWindowAux::new().x(x).y(y);
-        self.conn.configure_window(window_id, &values)?;
-        self.conn.flush()?;
+    /// Focus a window and fake a key press/release with XTEST, which goes to
+    /// the focused window like real input does
+    pub fn send_key_to_window(&self, window_id: u32, keycode: u8) -> Result<()> {
+        let conn = self.conn();
+        let root = conn.setup().roots[self.screen_num()].root;
+
+        self.activate_window(window_id)?;
+        // Round-trip so the focus change is processed before the key arrives
+        conn.get_input_focus()?.reply()?;
+        std::thread::sleep(Duration::from_millis(BROADCAST_FOCUS_DELAY_MS));
+
+        conn.xtest_fake_input(KEY_PRESS_EVENT, keycode, x11rb::CURRENT_TIME, root, 0, 0, 0)?;
+        conn.xtest_fake_input(
+            KEY_RELEASE_EVENT,
+            keycode,
+            x11rb::CURRENT_TIME,
+            root,
+            0,
+            0,
+            0,
+        )?;
+        conn.flush()?;
         Ok(())
     }
 
     pub fn minimize_window(&self, window_id: u32) -> Result<()> {
         // Use WM_CHANGE_STATE with IconicState to minimize
-        let wm_change_state = self
-            .conn
-            .intern_atom(false, b"WM_CHANGE_STATE")?
-            .reply()?
-            .atom;
+        let wm_change_state = self.atoms().WM_CHANGE_STATE;
 
-        let screen = &self.conn.setup().roots[self.screen_num];
+        let conn = self.conn();
+        let screen = &conn.setup().roots[self.screen_num()];
         let root = screen.root;
 
         // IconicState = 3
@@ -237,60 +715,424 @@ impl X11Manager {
             data: ClientMessageData::from([3u32, 0, 0, 0, 0]),
         };
 
-        self.conn.send_event(
+        self.conn().send_event(
             false,
             root,
             EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
             event,
         )?;
 
-        self.conn.flush()?;
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    /// Check whether a window has the given _NET_WM_STATE atom set
+    fn has_wm_state(&self, window_id: u32, state_atom: Atom) -> Result<bool> {
+        let reply = self
+            .conn()
+            .get_property(
+                false,
+                window_id,
+                self.atoms()._NET_WM_STATE,
+                AtomEnum::ATOM,
+                0,
+                1024,
+            )?
+            .reply()?;
+
+        Ok(reply
+            .value32()
+            .is_some_and(|mut states| states.any(|state| state == state_atom)))
+    }
+
+    pub fn is_fullscreen(&self, window_id: u32) -> Result<bool> {
+        self.has_wm_state(window_id, self.atoms()._NET_WM_STATE_FULLSCREEN)
+    }
+
+    pub fn unfullscreen_window(&self, window_id: u32) -> Result<()> {
+        let net_wm_state = self.atoms()._NET_WM_STATE;
+        let fullscreen = self.atoms()._NET_WM_STATE_FULLSCREEN;
+
+        let conn = self.conn();
+        let screen = &conn.setup().roots[self.screen_num()];
+        let root = screen.root;
+
+        // _NET_WM_STATE_REMOVE = 0, source indication 1 (application)
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            sequence: 0,
+            window: window_id,
+            type_: net_wm_state,
+            data: ClientMessageData::from([0, fullscreen, 0, 1, 0]),
+        };
+
+        self.conn().send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            event,
+        )?;
+
+        self.conn().flush()?;
+        Ok(())
+    }
+
+    /// Ask the window manager to close a window; the client may still prompt
+    pub fn close_window(&self, window_id: u32) -> Result<()> {
+        let conn = self.conn();
+        let root = conn.setup().roots[self.screen_num()].root;
+
+        // Timestamp, then source indication 2 (pager)
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            sequence: 0,
+            window: window_id,
+            type_: self.atoms()._NET_CLOSE_WINDOW,
+            data: ClientMessageData::from([x11rb::CURRENT_TIME, 2, 0, 0, 0]),
+        };
+
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            event,
+        )?;
+        conn.flush()?;
         Ok(())
     }
 
     pub fn restore_window(&self, window_id: u32) -> Result<()> {
         // Map the window to restore it from minimized state
-        self.conn.map_window(window_id)?;
-        self.conn.flush()?;
+        self.conn().map_window(window_id)?;
+        self.conn().flush()?;
         Ok(())
     }
 }
 
 impl WindowManager for X11Manager {
     fn get_eve_windows(&self) -> Result<Vec<EveWindow>> {
-        self.get_eve_windows()
+        self.with_reconnect(|| self.get_eve_windows())
     }
 
     fn activate_window(&self, window_id: u32) -> Result<()> {
-        self.activate_window(window_id)
+        self.with_reconnect(|| self.activate_window(window_id))
+    }
+
+    fn dim_inactive(&self, window_ids: &[u32], active_id: u32, opacity: f32) -> Result<()> {
+        self.with_reconnect(|| self.dim_inactive(window_ids, active_id, opacity))
+    }
+
+    fn restore_opacity(&self) -> Result<()> {
+        self.with_reconnect(|| self.restore_opacity())
     }
 
     fn stack_windows(&self, windows: &[EveWindow], config: &Config) -> Result<()> {
-        let x = ((config.display_width - config.eve_width) / 2) as i32;
-        let y = 0;
+        let x = config.eve_x();
+        let y = config.eve_y();
         let width = config.eve_width;
-        let height = config.display_height - config.panel_height;
+        let height = config.eve_height_adjusted();
+
+        let target = WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        self.with_reconnect(|| {
+            // Repositioning a fullscreen window is futile, so either leave fullscreen first or skip it
+            let mut stackable = Vec::with_capacity(windows.len());
+            let current = self.current_geometries(windows);
+            for window in windows {
+                if is_placed_at(&current, window.id, target, config.restack_tolerance_px) {
+                    continue;
+                }
+                if self.is_fullscreen(window.id).unwrap_or(false) {
+                    if config.unfullscreen_before_stack {
+                        self.unfullscreen_window(window.id)?;
+                    } else {
+                        warn!(
+                            "Skipping fullscreen client '{}' (set 'unfullscreen_before_stack = true' to stack it)",
+                            window.title
+                        );
+                        continue;
+                    }
+                }
+                stackable.push(window.clone());
+            }
+
+            self.stack_windows_internal(&stackable, x, y, width, height)
+        })
+    }
 
-        self.stack_windows_internal(windows, x, y, width, height)
+    fn place_window(&self, window_id: u32, geometry: WindowGeometry) -> Result<()> {
+        let values = ConfigureWindowAux::new()
+            .x(geometry.x)
+            .y(geometry.y)
+            .width(geometry.width)
+            .height(geometry.height);
+        self.with_reconnect(|| {
+            let conn = self.conn();
+            conn.configure_window(window_id, &values)?;
+            conn.flush()?;
+            Ok(())
+        })
     }
 
     fn get_active_window(&self) -> Result<u32> {
-        self.get_active_window()
+        self.with_reconnect(|| self.get_active_window())
+    }
+
+    fn send_key_to_window(&self, window_id: u32, keycode: u8) -> Result<()> {
+        self.with_reconnect(|| self.send_key_to_window(window_id, keycode))
     }
 
     fn find_window_by_title(&self, title: &str) -> Result<Option<u32>> {
-        self.find_window_by_title(title)
+        self.with_reconnect(|| self.find_window_by_title(title))
+    }
+
+    fn supports_positioning(&self) -> bool {
+        true
+    }
+
+    fn supports_restacking(&self) -> bool {
+        true
+    }
+
+    fn raise_all(&self, windows: &[EveWindow]) -> Result<()> {
+        self.with_reconnect(|| self.restack(windows, StackMode::ABOVE))
+    }
+
+    fn lower_all(&self, windows: &[EveWindow]) -> Result<()> {
+        self.with_reconnect(|| self.restack(windows, StackMode::BELOW))
     }
 
     fn move_window(&self, window_id: u32, x: i32, y: i32) -> Result<()> {
-        self.move_window(window_id, x, y)
+        self.with_reconnect(|| self.move_window(window_id, x, y))
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        self.with_reconnect(|| self.monitors())
+    }
+
+    fn window_under_cursor(&self) -> Result<Option<u32>> {
+        self.with_reconnect(|| self.window_under_cursor())
+    }
+
+    fn get_window_geometry(&self, window_id: u32) -> Result<Option<WindowGeometry>> {
+        self.with_reconnect(|| self.get_window_geometry(window_id))
+            .map(Some)
+    }
+
+    fn is_minimized(&self, window_id: u32) -> Result<Option<bool>> {
+        self.with_reconnect(|| self.has_wm_state(window_id, self.atoms()._NET_WM_STATE_HIDDEN))
+            .map(Some)
+    }
+
+    fn close_window(&self, window_id: u32) -> Result<()> {
+        self.with_reconnect(|| self.close_window(window_id))
     }
 
     fn minimize_window(&self, window_id: u32) -> Result<()> {
-        self.minimize_window(window_id)
+        self.with_reconnect(|| self.minimize_window(window_id))
     }
 
     fn restore_window(&self, window_id: u32) -> Result<()> {
-        self.restore_window(window_id)
+        self.with_reconnect(|| self.restore_window(window_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opacity_value_is_clamped_and_scaled() {
+        assert_eq!(opacity_value(1.0), u32::MAX);
+        assert_eq!(opacity_value(0.0), 0);
+        assert_eq!(opacity_value(0.5), 0x8000_0000);
+        assert_eq!(opacity_value(-1.0), 0);
+        assert_eq!(opacity_value(2.0), u32::MAX);
+    }
+
+    #[test]
+    fn test_connection_errors_are_told_apart() {
+        let lost = anyhow::Error::from(ReplyError::ConnectionError(ConnectionError::UnknownError));
+        assert!(is_connection_error(&lost));
+        assert!(is_connection_error(
+            &anyhow::Error::from(ConnectionError::UnknownError).context("Failed to list windows")
+        ));
+
+        let refused = anyhow::anyhow!("Failed to get window list");
+        assert!(!is_connection_error(&refused));
+    }
+
+    /// A throwaway Xvfb server, killed when dropped
+    struct Xvfb {
+        child: std::process::Child,
+        display: String,
+    }
+
+    impl Xvfb {
+        fn start() -> Self {
+            let free = (90..120)
+                .find(|n| !std::path::Path::new(&format!("/tmp/.X11-unix/X{}", n)).exists())
+                .expect("no free X display number");
+            let display = format!(":{}", free);
+            let child = std::process::Command::new("Xvfb")
+                .args([
+                    display.as_str(),
+                    "-nolisten",
+                    "tcp",
+                    "-screen",
+                    "0",
+                    "1920x1080x24",
+                ])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .expect("failed to run Xvfb");
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while RustConnection::connect(Some(&display)).is_err() {
+                assert!(Instant::now() < deadline, "Xvfb never accepted connections");
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Self { child, display }
+        }
+    }
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    /// Map a top-level window with the given title
+    fn create_client(conn: &RustConnection, atoms: &Atoms, root: u32, title: &str) -> u32 {
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            640,
+            480,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            atoms._NET_WM_NAME,
+            atoms.UTF8_STRING,
+            title.as_bytes(),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        window
+    }
+
+    /// Stand in for a window manager: publish the client list and answer
+    /// _NET_ACTIVE_WINDOW requests by updating the root property
+    fn run_fake_wm(conn: Arc<RustConnection>, atoms: Atoms, root: u32, clients: Vec<u32>) {
+        conn.change_property32(
+            PropMode::REPLACE,
+            root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            &clients,
+        )
+        .unwrap();
+        conn.change_window_attributes(
+            root,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::SUBSTRUCTURE_NOTIFY),
+        )
+        .unwrap();
+        conn.sync().unwrap();
+
+        std::thread::spawn(move || {
+            // Ends when Xvfb goes away and the connection breaks
+            while let Ok(event) = conn.wait_for_event() {
+                if let Event::ClientMessage(message) = event {
+                    if message.type_ == atoms._NET_ACTIVE_WINDOW {
+                        let _ = conn.change_property32(
+                            PropMode::REPLACE,
+                            root,
+                            atoms._NET_ACTIVE_WINDOW,
+                            AtomEnum::WINDOW,
+                            &[message.window],
+                        );
+                        let _ = conn.flush();
+                    }
+                }
+            }
+        });
+    }
+
+    fn wait_for_active(manager: &X11Manager, window: u32) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while manager.get_active_window().unwrap() != window {
+            assert!(
+                Instant::now() < deadline,
+                "0x{:08x} never became active",
+                window
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb; run with `cargo test -- --ignored xvfb`"]
+    fn test_xvfb_lists_and_activates_clients() {
+        let xvfb = Xvfb::start();
+
+        let (conn, screen_num) = RustConnection::connect(Some(&xvfb.display)).unwrap();
+        let conn = Arc::new(conn);
+        let atoms = Atoms::new(&*conn).unwrap().reply().unwrap();
+        let root = conn.setup().roots[screen_num].root;
+
+        let alpha = create_client(&conn, &atoms, root, "EVE - Alpha");
+        let browser = create_client(&conn, &atoms, root, "Some Browser");
+        let bravo = create_client(&conn, &atoms, root, "EVE - Bravo");
+        run_fake_wm(Arc::clone(&conn), atoms, root, vec![alpha, browser, bravo]);
+
+        let filter = WindowFilter::from_config(&Config::for_display(1920, 1080)).unwrap();
+        let manager = X11Manager::with_display(filter, Some(&xvfb.display)).unwrap();
+
+        let windows = manager.get_eve_windows().unwrap();
+        let listed: Vec<(u32, &str)> = windows.iter().map(|w| (w.id, w.title.as_str())).collect();
+        assert_eq!(listed, vec![(alpha, "Alpha"), (bravo, "Bravo")]);
+
+        // A listed client that blanks its title stays, other blank windows don't
+        for window in [alpha, browser] {
+            conn.change_property8(
+                PropMode::REPLACE,
+                window,
+                atoms._NET_WM_NAME,
+                atoms.UTF8_STRING,
+                b"",
+            )
+            .unwrap();
+        }
+        conn.flush().unwrap();
+        let windows = manager.get_eve_windows().unwrap();
+        let listed: Vec<(u32, &str)> = windows.iter().map(|w| (w.id, w.title.as_str())).collect();
+        assert_eq!(
+            listed,
+            vec![(alpha, CHARACTER_SELECT_TITLE), (bravo, "Bravo")]
+        );
+
+        manager.activate_window(bravo).unwrap();
+        wait_for_active(&manager, bravo);
+        manager.activate_window(alpha).unwrap();
+        wait_for_active(&manager, alpha);
     }
 }